@@ -0,0 +1,62 @@
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mica::fixtures;
+use mica::minimax::*;
+
+fn move_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_moves");
+    for (name, state) in fixtures::all() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &state, |b, state| {
+            b.iter(|| black_box(state).get_moves())
+        });
+    }
+    group.finish();
+}
+
+fn apply_undo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_undo");
+    for (name, mut state) in fixtures::all() {
+        let moves = state.get_moves();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                for &mica_move in &moves {
+                    state.apply_move(black_box(mica_move));
+                    state.undo_move(mica_move);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn eval(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval");
+    for (name, state) in fixtures::all() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &state, |b, state| {
+            b.iter(|| black_box(state).eval())
+        });
+    }
+    group.finish();
+}
+
+// fixed depth searches, the node count of each is printed so speedups can be
+// told apart from searches that simply visit fewer nodes
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("minimax");
+    group.sample_size(10);
+    for (name, state) in fixtures::all() {
+        for depth in [2, 4] {
+            let mut probe = state.clone();
+            probe.minimax(depth, i32::MIN, i32::MAX);
+            eprintln!("minimax {name} depth {depth}: {} nodes", probe.nodes);
+
+            group.bench_with_input(BenchmarkId::new(name, depth), &depth, |b, &depth| {
+                b.iter(|| state.clone().minimax(depth, i32::MIN, i32::MAX))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, move_generation, apply_undo, eval, search);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::minimax::*;
+use crate::rng::Rng;
+use crate::search::DEFAULT_DEPTH;
+
+// Move choosers of fixed strength. The random and greedy baselines are weak
+// on purpose, they anchor the rating scale the real engine is measured on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agent {
+    // alpha-beta to the given depth
+    Minimax(u8),
+    // any legal move
+    Random,
+    // the move with the best evaluation one ply ahead, ties broken at random
+    Greedy,
+}
+
+impl FromStr for Agent {
+    type Err = String;
+
+    // `random`, `greedy`, `minimax` or `minimax:<depth>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("minimax", depth)) => depth.parse().map(Agent::Minimax).map_err(|_| format!("invalid minimax depth `{depth}`")),
+            None if s == "minimax" => Ok(Agent::Minimax(DEFAULT_DEPTH)),
+            None if s == "random" => Ok(Agent::Random),
+            None if s == "greedy" => Ok(Agent::Greedy),
+            _ => Err(format!("unknown engine `{s}`, expected random, greedy or minimax[:depth]")),
+        }
+    }
+}
+
+impl Agent {
+    pub fn choose(&self, state: &MicaState, rng: &mut Rng) -> Option<MicaMove> {
+        let moves = state.get_moves();
+        if moves.is_empty() {
+            return None;
+        }
+
+        match self {
+            Agent::Minimax(depth) => state.clone().minimax(*depth, i32::MIN, i32::MAX).1,
+            Agent::Random => Some(moves[rng.below(moves.len())]),
+            Agent::Greedy => {
+                let side = state.current_player as i32;
+                let score = |&mica_move: &MicaMove| {
+                    let mut next = state.clone();
+                    next.apply_move(mica_move);
+                    next.eval() * side
+                };
+                let best = moves.iter().map(score).max().unwrap();
+                let best_moves: Vec<MicaMove> = moves.iter().copied().filter(|mica_move| score(mica_move) == best).collect();
+                Some(best_moves[rng.below(best_moves.len())])
+            },
+        }
+    }
+}
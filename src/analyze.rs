@@ -0,0 +1,159 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use mica::api::{DifficultyAnswer, IterationStats};
+use mica::corpus::{self, CorpusStats};
+use mica::difficulty::PositionDifficulty;
+use mica::notation::{parse_position, PositionFormat};
+use mica::pool::{Pool, TaskScope};
+use mica::search::{SearchLimits, DEFAULT_DEPTH};
+use mica::selfplay::GameRecord;
+use mica::tt::TranspositionTable;
+
+use crate::{get_best_move, option, SEARCH_THREADS};
+
+// Aggregate statistics over a games file written by `mica train`.
+//
+//     mica analyze-corpus [--games FILE] [--format text|json]
+//
+// Reports results overall and by white's first placement, the average
+// length of the setting and moving phases and how often a mill is closed
+// on each point.
+pub fn run(args: &[String]) {
+    let games_path: String = option(args, "--games", "training/games.jsonl".to_string());
+    let format: String = option(args, "--format", "text".to_string());
+
+    let records: Vec<GameRecord> = fs::read_to_string(&games_path)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let stats = corpus::analyze(&records);
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+        "text" => print_report(&stats),
+        _ => {
+            eprintln!("Unknown format `{format}`, expected text or json");
+            std::process::exit(2);
+        },
+    }
+}
+
+fn print_report(stats: &CorpusStats) {
+    let results = &stats.results;
+    println!("{} games: +{} ={} -{} for white ({:.1}%)", results.games, results.white_wins, results.draws, results.black_wins, results.white_score() * 100.0);
+    if stats.skipped > 0 {
+        println!("{} games skipped, their moves do not replay", stats.skipped);
+    }
+    println!("average plies: {:.1} setting, {:.1} moving", stats.setting_plies, stats.moving_plies);
+
+    println!();
+    println!("first placement  games     +     =     -  white");
+    for opening in &stats.openings {
+        let results = &opening.results;
+        println!(
+            "{:<15} {:>6} {:>5} {:>5} {:>5} {:>5.1}%",
+            opening.point, results.games, results.white_wins, results.draws, results.black_wins, opening.white_score * 100.0
+        );
+    }
+
+    println!();
+    println!("point  mills  per game");
+    for mill in &stats.mills {
+        println!("{:<5} {:>6} {:>9.3}", mill.point, mill.mills, mill.per_game);
+    }
+}
+
+// Searches one position and reports every depth of the search.
+//
+//     mica analyze --position FEN [--depth 6] [--time-ms MS] [--format text|json] [--deterministic]
+//
+// Prints the score, best move, nodes, time and branching factor of each
+// depth, so search explosion and scores swinging between depths show up.
+// With `--deterministic` every run prints the same depths, scores and nodes,
+// the search runs on one thread and without a time budget.
+pub fn run_position(args: &[String]) {
+    let position: String = option(args, "--position", String::new());
+    let depth: u8 = option(args, "--depth", DEFAULT_DEPTH);
+    let time_ms: Option<u64> = args.iter().any(|arg| arg == "--time-ms").then(|| option(args, "--time-ms", 0));
+    let format: String = option(args, "--format", "text".to_string());
+    let deterministic = args.iter().any(|arg| arg == "--deterministic");
+
+    let mut state = match parse_position(PositionFormat::Fen, &position) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Invalid --position `{position}`: {e}");
+            std::process::exit(2);
+        },
+    };
+    state.tt = Some(Arc::new(TranspositionTable::new(64)));
+    let pool = Arc::new(Pool::new());
+    Arc::clone(&pool).init(SEARCH_THREADS);
+    let limits = SearchLimits { max_depth: depth, max_nodes: None, max_time: time_ms.map(Duration::from_millis) };
+    let search = get_best_move(state, &pool, &TaskScope::new(), &limits, None, 1, deterministic);
+    let iterations = IterationStats::from_iterations(&search.iterations);
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&iterations).unwrap()),
+        "text" => {
+            println!("depth  score  move        nodes  time ms  branching");
+            for iteration in &iterations {
+                println!(
+                    "{:>5} {:>6}  {:<9} {:>8} {:>8}  {:>9}",
+                    iteration.depth,
+                    iteration.score,
+                    iteration.best_move.as_deref().unwrap_or("-"),
+                    iteration.nodes,
+                    iteration.time_ms,
+                    iteration.branching_factor.map_or("-".to_string(), |factor| format!("{factor:.2}"))
+                );
+            }
+        },
+        _ => {
+            eprintln!("Unknown format `{format}`, expected text or json");
+            std::process::exit(2);
+        },
+    }
+}
+
+// Estimates how hard a position is for a human, see `PositionDifficulty`.
+//
+//     mica difficulty --position FEN [--depth 6] [--time-ms MS] [--format text|json] [--deterministic]
+pub fn run_difficulty(args: &[String]) {
+    let position: String = option(args, "--position", String::new());
+    let depth: u8 = option(args, "--depth", DEFAULT_DEPTH);
+    let time_ms: Option<u64> = args.iter().any(|arg| arg == "--time-ms").then(|| option(args, "--time-ms", 0));
+    let format: String = option(args, "--format", "text".to_string());
+    let deterministic = args.iter().any(|arg| arg == "--deterministic");
+
+    let mut state = match parse_position(PositionFormat::Fen, &position) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Invalid --position `{position}`: {e}");
+            std::process::exit(2);
+        },
+    };
+    state.tt = Some(Arc::new(TranspositionTable::new(64)));
+    let side = state.current_player as i32;
+    let pool = Arc::new(Pool::new());
+    Arc::clone(&pool).init(SEARCH_THREADS);
+    let limits = SearchLimits { max_depth: depth, max_nodes: None, max_time: time_ms.map(Duration::from_millis) };
+    let search = get_best_move(state, &pool, &TaskScope::new(), &limits, None, 1, deterministic);
+    let answer = DifficultyAnswer::new(&PositionDifficulty::estimate(&search, side), search.depth);
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&answer).unwrap()),
+        "text" => {
+            println!("difficulty {} ({}) at depth {}", answer.score, answer.grade, answer.depth);
+            println!("{} legal moves, {} as good as the best", answer.legal_moves, answer.best_moves);
+            println!("next best move {} stones worse", answer.gap.map_or("-".to_string(), |gap| gap.to_string()));
+            println!("best move changed {} times, score swung {} stones", answer.best_move_changes, answer.score_swing);
+        },
+        _ => {
+            eprintln!("Unknown format `{format}`, expected text or json");
+            std::process::exit(2);
+        },
+    }
+}
@@ -0,0 +1,423 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::diff::Change;
+use crate::difficulty::PositionDifficulty;
+use crate::minimax::{MicaMove, MicaPlayer, MicaRequest, PlayerField};
+use crate::notation::move_to_notation;
+use crate::search::Iteration;
+use crate::variants::Rules;
+
+// Bodies of the server's HTTP API. The server writes its answers and reads
+// its requests with these and `client` reads and writes the same ones, so a
+// client built from this crate can't drift from the server's schema.
+
+// Version of the schema, raised with every change a client written for the
+// last one would break on. New optional fields don't raise it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// what the search for an answer did, see `search::SearchStats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub nodes: u64,
+    #[serde(default)]
+    pub tt_hits: u64,
+    #[serde(default)]
+    pub beta_cutoffs: u64,
+    // deepest ply a node was searched at, `depth` is the deepest one that finished
+    #[serde(default)]
+    pub max_depth: u8,
+    pub depth: u8,
+    pub time_ms: u64,
+}
+
+// one depth of the search, to spot search explosion and scores that swing between depths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationStats {
+    pub depth: u8,
+    pub score: i32,
+    // in notation, null when the depth found no move
+    #[serde(rename = "move")]
+    pub best_move: Option<String>,
+    pub nodes: u64,
+    pub time_ms: u64,
+    // nodes per node of the depth before, null for the first depth
+    pub branching_factor: Option<f64>,
+}
+
+impl IterationStats {
+    pub fn from_iterations(iterations: &[Iteration]) -> Vec<IterationStats> {
+        iterations
+            .iter()
+            .enumerate()
+            .map(|(i, iteration)| IterationStats {
+                depth: iteration.depth,
+                score: iteration.score,
+                best_move: iteration.best_move.map(move_to_notation),
+                nodes: iteration.nodes,
+                time_ms: iteration.time.as_millis() as u64,
+                branching_factor: i.checked_sub(1).and_then(|previous| iteration.branching_factor(&iterations[previous])),
+            })
+            .collect()
+    }
+}
+
+// Where the time of a move request went, in microseconds. The search does
+// not include the time its tasks waited for the pool, a long queue wait next
+// to a short search means the pool was busy with other requests.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Latency {
+    pub parse_us: u64,
+    pub queue_us: u64,
+    pub search_us: u64,
+    // building the response body
+    pub serialize_us: u64,
+}
+
+// one of the best moves of a multi-PV answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Line {
+    #[serde(rename = "move")]
+    pub mica_move: String,
+    pub score: i32,
+    // starting with the move, in notation
+    pub pv: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mills {
+    // whether the move closed a mill
+    pub formed: bool,
+    // points of both players that are part of a mill after the move
+    pub points: Vec<String>,
+}
+
+// Limits a move request was searched with instead of the ones it asked for,
+// because the engine's threads were busy, see `--saturation` of the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Degraded {
+    // how long the oldest queued search had waited for a thread
+    pub queue_wait_ms: u64,
+    pub max_depth: u8,
+    pub time_ms: Option<u64>,
+    pub max_nodes: Option<u64>,
+}
+
+// An answer to a move request as a client reads it. The fields only the
+// rich profile sends are None or empty in minimal answers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MoveAnswer {
+    // `[["set", player, x, y, z]]`, `[["move", player, to..., from...]]` and a
+    // `["remove", player, x, y, z]` part after either for mills, null when
+    // there is no move, see `best_move`
+    #[serde(rename = "move")]
+    pub mica_move: Value,
+    pub changes: Vec<Change>,
+    pub expected_reply: Option<String>,
+    #[serde(default)]
+    pub degraded: Option<Degraded>,
+    #[serde(default)]
+    pub lines: Vec<Line>,
+    // echoed for requests with a `request_id`
+    #[serde(default)]
+    pub request_id: Option<Value>,
+    #[serde(default)]
+    pub computed_in_ms: Option<u64>,
+    #[serde(default)]
+    pub engine_version: Option<String>,
+    #[serde(default)]
+    pub score: Option<i32>,
+    #[serde(default)]
+    pub pv: Vec<String>,
+    #[serde(default)]
+    pub stats: Option<Stats>,
+    #[serde(default)]
+    pub iterations: Vec<IterationStats>,
+    #[serde(default)]
+    pub position: Option<String>,
+    // `setting`, `moving`, `flying` or `endgame`, see `variants::Phase`
+    #[serde(default)]
+    pub phase: Option<String>,
+    #[serde(default)]
+    pub mills: Option<Mills>,
+    #[serde(default)]
+    pub latency: Option<Latency>,
+}
+
+impl MoveAnswer {
+    // the move the engine chose, None when it had none or the parts are malformed
+    pub fn best_move(&self) -> Option<MicaMove> {
+        let parts = self.mica_move.as_array()?;
+        let point = |part: &Value, at: usize| -> Option<(u8, u8, u8)> {
+            let coordinate = |i: usize| part.get(i).and_then(Value::as_u64).and_then(|value| u8::try_from(value).ok());
+            Some((coordinate(at)?, coordinate(at + 1)?, coordinate(at + 2)?))
+        };
+        let first = parts.first()?;
+        let removed = match parts.get(1) {
+            Some(part) if part.get(0)?.as_str()? == "remove" => Some(point(part, 2)?),
+            Some(_) => return None,
+            None => None,
+        };
+        let (x, y, z) = point(first, 2)?;
+        Some(match (first.get(0)?.as_str()?, removed) {
+            ("set", None) => MicaMove::Set { x, y, z },
+            ("set", Some((remove_x, remove_y, remove_z))) => MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z },
+            ("move", removed) => {
+                let (from_x, from_y, from_z) = point(first, 5)?;
+                let (to_x, to_y, to_z) = (x, y, z);
+                match removed {
+                    None => MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z },
+                    Some((remove_x, remove_y, remove_z)) => MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z },
+                }
+            },
+            _ => return None,
+        })
+    }
+}
+
+// answer of `POST /positions/difficulty`, see `PositionDifficulty`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DifficultyAnswer {
+    pub score: u8,
+    // `easy`, `medium` or `hard`, what a puzzle of the position is graded as
+    pub grade: String,
+    pub legal_moves: usize,
+    pub best_moves: usize,
+    pub gap: Option<i32>,
+    pub best_move_changes: usize,
+    pub score_swing: i32,
+    // of the search the estimate comes from
+    pub depth: u8,
+}
+
+impl DifficultyAnswer {
+    pub fn new(difficulty: &PositionDifficulty, depth: u8) -> Self {
+        DifficultyAnswer {
+            score: difficulty.score,
+            grade: difficulty.grade().name().to_string(),
+            legal_moves: difficulty.legal_moves,
+            best_moves: difficulty.best_moves,
+            gap: difficulty.gap,
+            best_move_changes: difficulty.best_move_changes,
+            score_swing: difficulty.score_swing,
+            depth,
+        }
+    }
+}
+
+// Rule options a move request or game changes from its variant's, the ones
+// left out stay as the variant has them, see `variants::Rules`. A server
+// only takes the ones its `--rule-options` allow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flying: Option<bool>,
+    #[serde(default, alias = "millFallback", skip_serializing_if = "Option::is_none")]
+    pub mill_fallback: Option<bool>,
+    // quiet moves in a row that draw the game, like `draw_after` of a move request
+    #[serde(default, alias = "drawAfter", skip_serializing_if = "Option::is_none")]
+    pub draw_after: Option<u16>,
+}
+
+impl RuleOptions {
+    pub const NAMES: [&'static str; 3] = ["flying", "mill_fallback", "draw_after"];
+
+    pub fn apply(&self, rules: Rules) -> Rules {
+        Rules {
+            flying: self.flying.unwrap_or(rules.flying),
+            mill_fallback: self.mill_fallback.unwrap_or(rules.mill_fallback),
+        }
+    }
+
+    // the names of the options that are set, from `NAMES`
+    pub fn names(&self) -> Vec<&'static str> {
+        let set = [self.flying.is_some(), self.mill_fallback.is_some(), self.draw_after.is_some()];
+        RuleOptions::NAMES.into_iter().zip(set).filter(|&(_, set)| set).map(|(name, _)| name).collect()
+    }
+}
+
+fn default_engine() -> PlayerField {
+    PlayerField::Name(MicaPlayer::Black)
+}
+
+fn default_game_time_ms() -> u64 {
+    5 * 60 * 1000
+}
+
+// body of `POST /game`, every field is optional
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRequest {
+    #[serde(default)]
+    pub variant: Option<String>,
+    #[serde(default = "default_engine")]
+    pub engine: PlayerField,
+    #[serde(default = "default_game_time_ms", alias = "timeMs")]
+    pub time_ms: u64,
+    #[serde(default, alias = "incrementMs")]
+    pub increment_ms: u64,
+    // a named engine opponent, see `personality`
+    #[serde(default)]
+    pub personality: Option<String>,
+    // seeds the personality's random choices, picked by the server when missing
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub rules: Option<RuleOptions>,
+}
+
+impl Default for GameRequest {
+    fn default() -> Self {
+        GameRequest {
+            variant: None,
+            engine: default_engine(),
+            time_ms: default_game_time_ms(),
+            increment_ms: 0,
+            personality: None,
+            seed: None,
+            rules: None,
+        }
+    }
+}
+
+// body of `POST /game/{id}/move`, the move may be left out when the engine is to move
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameMove {
+    #[serde(rename = "move")]
+    pub mica_move: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameClock {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    pub increment_ms: u64,
+}
+
+// how a finished game ended, `reason` is `time`, `stones`, `blocked`, `length`
+// or `quiet` for a game drawn by its `draw_after` rule option
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameOutcome {
+    pub winner: PlayerField,
+    pub reason: String,
+}
+
+// a game session as the game endpoints answer with it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub id: u64,
+    pub variant: String,
+    pub engine: PlayerField,
+    pub personality: Option<String>,
+    // the options the game changed from its variant's rules
+    #[serde(default)]
+    pub rules: RuleOptions,
+    pub to_move: PlayerField,
+    // as FEN
+    pub position: String,
+    // of the position, see `variants::Phase`
+    pub phase: String,
+    // in notation
+    pub moves: Vec<String>,
+    pub clock: GameClock,
+    pub outcome: Option<GameOutcome>,
+    // the move the engine answered with, only sent when creating a game and playing a move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_move: Option<String>,
+}
+
+// body of `POST /games/graph`, a game to score from the start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphRequest {
+    // gym action indices or notation, a game record's moves work as they are
+    pub moves: Vec<Value>,
+    #[serde(default)]
+    pub variant: Option<String>,
+    #[serde(default)]
+    pub rules: Option<RuleOptions>,
+    // of the search scoring every position, the server's default when missing
+    #[serde(default)]
+    pub depth: Option<u8>,
+}
+
+// Answer of `GET /game/{id}/graph` and `POST /games/graph`: every position
+// of a game from the start, one entry per position in every array so they
+// plot as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreGraph {
+    // in notation, the move from each position to the next, one fewer than positions
+    pub moves: Vec<String>,
+    // for white in stones, as a search of `depth` plies scores the position
+    pub scores: Vec<i32>,
+    // stones still in the game, on the board or to set
+    pub white_stones: Vec<u8>,
+    pub black_stones: Vec<u8>,
+    pub depth: u8,
+}
+
+// body of `POST /batch`, move requests of independent games
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub positions: Vec<MicaRequest>,
+}
+
+// the answer to one position of a batch, in the order they were sent
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchEntry {
+    // the status the position would have been answered with on its own
+    pub status: u16,
+    // one of them is set, the answer for a 200 and the error otherwise
+    #[serde(default)]
+    pub answer: Option<MoveAnswer>,
+    #[serde(default)]
+    pub error: Option<ErrorBody>,
+}
+
+// answer of `POST /batch`
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchAnswer {
+    pub answers: Vec<BatchEntry>,
+}
+
+// the search limits of a server and the sizes of its bulk endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityLimits {
+    pub depth: u8,
+    pub time_ms: u64,
+    pub max_nodes: u64,
+    // positions of one `POST /batch`
+    pub batch: usize,
+    // moves of one `POST /games/graph`
+    pub graph_plies: usize,
+}
+
+// Answer of `GET /capabilities`, what a server deployed at some version
+// understands, so a client can check before relying on a field or value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub schema_version: u32,
+    pub engine_version: String,
+    // of move requests, fields not listed here are ignored unless marked critical
+    pub request_fields: Vec<String>,
+    pub variants: Vec<String>,
+    pub default_variant: String,
+    // options requests may change, see `RuleOptions`
+    pub rule_options: Vec<String>,
+    // values of `validation`, `profile`, `difficulty`, `engine` and `evaluator`
+    pub validation_modes: Vec<String>,
+    pub profiles: Vec<String>,
+    pub difficulties: Vec<String>,
+    pub engines: Vec<String>,
+    pub evaluators: Vec<String>,
+    // how positions are written in answers and moves in game records
+    pub position_format: String,
+    pub move_formats: Vec<String>,
+    pub limits: CapabilityLimits,
+}
+
+// an error answer, `error` says what went wrong and `details` where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+    #[serde(default)]
+    pub details: Vec<String>,
+}
@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use mica::agents::Agent;
+use mica::gym::{encode_action, Env};
+use mica::minimax::{MicaPlayer, MinimaxPlayer};
+use mica::rng::Rng;
+use mica::selfplay::{termination, GameHeaders, GameRecord};
+
+use crate::option;
+
+// Plays two agents against each other and reports the first one's results.
+//
+//     mica match <agent> <agent> [--games N] [--seed N] [--max-plies N] [--out FILE]
+//
+// Agents are `random`, `greedy`, `minimax` or `minimax:<depth>`. Colours
+// alternate every game and games longer than --max-plies are draws. With
+// --out every game is written there as a game record, one per line.
+pub fn run(args: &[String]) {
+    let names: Vec<&String> = args.iter().take_while(|arg| !arg.starts_with("--")).collect();
+    let agents: Vec<Agent> = names.iter().map(|arg| arg.parse().unwrap()).collect();
+    let [first, second] = agents[..] else {
+        eprintln!("usage: mica match <agent> <agent> [--games N] [--seed N] [--max-plies N] [--out FILE]");
+        return;
+    };
+    let games: u64 = option(args, "--games", 10);
+    let seed: u64 = option(args, "--seed", 0);
+    let max_plies: usize = option(args, "--max-plies", 200);
+    let out_path: String = option(args, "--out", String::new());
+    let mut out = (!out_path.is_empty()).then(|| BufWriter::new(File::create(&out_path).unwrap()));
+
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    for game in 0..games {
+        let mut rng = Rng::new(seed + game);
+        let first_is_white = game % 2 == 0;
+        let mut env = Env::new();
+        let mut winner = MicaPlayer::None;
+        let (white, black) = if first_is_white { (names[0], names[1]) } else { (names[1], names[0]) };
+        let mut record = GameRecord { seed: seed + game, moves: Vec::new(), result: 0, headers: GameHeaders::new("match", white, black) };
+        record.headers.termination = Some("length".to_string());
+
+        for _ in 0..max_plies {
+            let mover = env.state().current_player;
+            let agent = if (mover == MicaPlayer::White) == first_is_white { first } else { second };
+            let Some(mica_move) = agent.choose(env.state(), &mut rng) else {
+                winner = mover.into_next_player();
+                record.headers.termination = Some(termination(env.state()).to_string());
+                break;
+            };
+            let (_, _, done) = env.step(encode_action(mica_move)).unwrap();
+            record.moves.push(encode_action(mica_move));
+            if done {
+                winner = mover;
+                record.headers.termination = Some(termination(env.state()).to_string());
+                break;
+            }
+        }
+        if let Some(out) = &mut out {
+            record.result = winner as i8;
+            writeln!(out, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+        }
+
+        let first_side = if first_is_white { MicaPlayer::White } else { MicaPlayer::Black };
+        match winner {
+            MicaPlayer::None => draws += 1,
+            side if side == first_side => wins += 1,
+            _ => losses += 1,
+        }
+    }
+
+    let score = (wins as f64 + draws as f64 / 2.0) / games.max(1) as f64;
+    println!("{first:?} vs {second:?}: +{wins} ={draws} -{losses} ({:.1}%)", score * 100.0);
+}
@@ -0,0 +1,114 @@
+use std::process;
+use mica::coords::{to_notation, POINTS};
+use mica::eval::{evaluate_hundredths, PhaseWeights};
+use mica::fixtures;
+use mica::minimax::*;
+use mica::notation::{format_position, PositionFormat};
+use mica::symmetry::Symmetry;
+
+use crate::option;
+
+// Checks what any evaluation weights should keep true on random positions.
+//
+//     mica audit-eval [--positions 2000] [--seed 0] [--weights W] [--checks colors,symmetry,monotonicity]
+//
+// Positions are scored in hundredths of a stone, see `evaluate_hundredths`,
+// so a broken invariant shows before rounding hides it:
+//
+// - colors: swapping the colors of every stone, the stones to set and the
+//   side to move negates the score
+// - symmetry: every symmetric image of the position scores the same
+// - monotonicity: a stone added for the side to move on any empty point
+//   scores no worse for that side
+//
+// Prints a few positions that break each invariant and exits with status 1
+// when any does. --weights are checked instead of the table the engine
+// plays with, as `mica tune` takes them, and --checks picks the invariants.
+// Mobility and windmills are worth more than a stone in some moving phase
+// positions, a stone that fills the point its own windmill swings to loses
+// it, so weights with either break monotonicity now and then by design.
+
+// longest random game played to reach a position, enough for the moving phase
+const MAX_PLIES: u64 = 70;
+// positions printed per invariant before the rest are only counted
+const MAX_REPORTED: usize = 5;
+
+// the position with white and black swapped
+fn swap_colors(state: &MicaState) -> MicaState {
+    let mut swapped = MicaState::new();
+    swapped.rules = state.rules;
+    for &(x, y, z) in POINTS.iter() {
+        let player = match state.stone(x, y, z) {
+            MicaPlayer::White => MicaPlayer::Black,
+            MicaPlayer::Black => MicaPlayer::White,
+            MicaPlayer::None => MicaPlayer::None,
+        };
+        swapped.put_stone(x, y, z, player);
+    }
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    swapped.set_stones_to_set(black_to_set, white_to_set);
+    swapped.current_player = state.current_player;
+    swapped.current_player.toggle();
+    swapped
+}
+
+fn check_colors(state: &MicaState, weights: &PhaseWeights) -> Option<String> {
+    let (score, swapped) = (evaluate_hundredths(state, weights), evaluate_hundredths(&swap_colors(state), weights));
+    (swapped != -score).then(|| format!("scores {score}, {swapped} with the colors swapped"))
+}
+
+fn check_symmetry(state: &MicaState, weights: &PhaseWeights) -> Option<String> {
+    let score = evaluate_hundredths(state, weights);
+    Symmetry::all().into_iter().find_map(|symmetry| {
+        let image = evaluate_hundredths(&symmetry.apply_state(state), weights);
+        (image != score).then(|| format!("scores {score}, {image} under {symmetry:?}"))
+    })
+}
+
+fn check_monotonicity(state: &MicaState, weights: &PhaseWeights) -> Option<String> {
+    let side = state.current_player as i32;
+    let score = evaluate_hundredths(state, weights) * side;
+    POINTS.iter().filter(|&&(x, y, z)| state.stone(x, y, z) == MicaPlayer::None).find_map(|&(x, y, z)| {
+        let mut added = state.clone();
+        added.put_stone(x, y, z, state.current_player);
+        let with_stone = evaluate_hundredths(&added, weights) * side;
+        (with_stone < score).then(|| format!("scores {score} for the side to move, {with_stone} with a stone added on {}", to_notation(x, y, z).unwrap()))
+    })
+}
+
+pub fn run(args: &[String]) {
+    let positions: u64 = option(args, "--positions", 2000);
+    let seed: u64 = option(args, "--seed", 0);
+    let weights: PhaseWeights = option(args, "--weights", PhaseWeights::default());
+    let names: String = option(args, "--checks", "colors,symmetry,monotonicity".to_string());
+
+    type Check = fn(&MicaState, &PhaseWeights) -> Option<String>;
+    let known: [(&str, Check); 3] = [("colors", check_colors), ("symmetry", check_symmetry), ("monotonicity", check_monotonicity)];
+    let checks: Vec<(&str, Check)> = names
+        .split(',')
+        .map(|name| {
+            known.into_iter().find(|&(known, _)| known == name).unwrap_or_else(|| {
+                eprintln!("Unknown check `{name}`, expected one of colors, symmetry, monotonicity");
+                process::exit(2);
+            })
+        })
+        .collect();
+    let mut violations = vec![0usize; checks.len()];
+    for position_seed in seed..seed + positions {
+        let state = fixtures::random(position_seed, (position_seed % MAX_PLIES) as usize);
+        for (i, (name, check)) in checks.iter().enumerate() {
+            if let Some(problem) = check(&state, &weights) {
+                if violations[i] < MAX_REPORTED {
+                    println!("FAIL  {name}: {} (seed {position_seed}): {problem}", format_position(PositionFormat::Fen, &state));
+                }
+                violations[i] += 1;
+            }
+        }
+    }
+
+    let summary: Vec<String> = checks.iter().zip(&violations).map(|((name, _), count)| format!("{name} {count}")).collect();
+    println!("{positions} positions, violations: {}", summary.join(", "));
+    if violations.iter().any(|&count| count > 0) {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::Path;
+
+use crate::minimax::{MicaMove, MicaState, Minimax};
+use crate::notation::{move_from_notation, move_to_notation};
+use crate::rng::Rng;
+use crate::symmetry::canonical_key;
+
+// Moves to play in positions of the setting phase that come up in every
+// game, so the search budget goes to the positions after them. Positions are
+// kept by `canonical_key`, one entry covers every symmetric image, and their
+// moves are stored for the canonical image.
+//
+// A book file has one position per line, its canonical key in hex followed
+// by its moves in notation with their weights, `#` starts a comment. A
+// weight may be followed by the games won, drawn and lost after the move by
+// the side that played it. The empty board with white to move:
+//
+//     0000000000000132 a1=3 d1=5/2/1/0
+//
+// A move is picked at random with a chance in proportion to its weight.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    // for the canonical image of the position in the book, for the position
+    // on the board once out of it
+    pub mica_move: MicaMove,
+    pub weight: u32,
+    // games after the move, for the side that played it, zero for books without them
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl BookMove {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+#[derive(Default)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<BookMove>>,
+}
+
+// states holding a book print its size, not every position in it
+impl fmt::Debug for OpeningBook {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "OpeningBook({} positions)", self.positions.len())
+    }
+}
+
+impl OpeningBook {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut book = OpeningBook::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            let mut fields = line.split_whitespace();
+            let Some(key) = fields.next() else {
+                continue;
+            };
+            let key = u64::from_str_radix(key, 16).map_err(|e| format!("line {}: position `{key}`: {e}", i + 1))?;
+            let moves = book.positions.entry(key).or_default();
+            for field in fields {
+                let (notation, counts) = field.split_once('=').ok_or_else(|| format!("line {}: `{field}` is not move=weight", i + 1))?;
+                let mica_move = move_from_notation(notation).ok_or_else(|| format!("line {}: `{notation}` is not a move", i + 1))?;
+                let counts = counts
+                    .split('/')
+                    .map(|count| count.parse().map_err(|e| format!("line {}: `{count}` of `{field}`: {e}", i + 1)))
+                    .collect::<Result<Vec<u32>, String>>()?;
+                let (weight, wins, draws, losses) = match counts[..] {
+                    [weight] => (weight, 0, 0, 0),
+                    [weight, wins, draws, losses] => (weight, wins, draws, losses),
+                    _ => return Err(format!("line {}: `{field}` is neither move=weight nor move=weight/wins/draws/losses", i + 1)),
+                };
+                moves.push(BookMove { mica_move, weight, wins, draws, losses });
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        OpeningBook::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // positions sorted by key, so the same book always writes the same file
+    pub fn to_text(&self) -> String {
+        let mut keys: Vec<u64> = self.positions.keys().copied().collect();
+        keys.sort_unstable();
+        let mut text = String::from("# position move=weight/wins/draws/losses, see `mica::book`\n");
+        for key in keys {
+            write!(text, "{key:016x}").unwrap();
+            for book_move in &self.positions[&key] {
+                write!(text, " {}={}", move_to_notation(book_move.mica_move), book_move.weight).unwrap();
+                if book_move.games() > 0 {
+                    write!(text, "/{}/{}/{}", book_move.wins, book_move.draws, book_move.losses).unwrap();
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // Counts a game in which the move was played in the position, the move as
+    // played in `state` and `result` for white: 1, 0 or -1.
+    pub fn record(&mut self, state: &MicaState, mica_move: MicaMove, result: i8) {
+        let (key, symmetry) = canonical_key(state);
+        let mica_move = symmetry.apply_move(mica_move);
+        let moves = self.positions.entry(key).or_default();
+        let book_move = match moves.iter().position(|book_move| book_move.mica_move == mica_move) {
+            Some(i) => &mut moves[i],
+            None => {
+                moves.push(BookMove { mica_move, weight: 0, wins: 0, draws: 0, losses: 0 });
+                moves.last_mut().unwrap()
+            },
+        };
+        match result * state.current_player as i8 {
+            1 => book_move.wins += 1,
+            0 => book_move.draws += 1,
+            _ => book_move.losses += 1,
+        }
+    }
+
+    // Weighs every move by the points its games scored, two for a win and
+    // one for a draw. Moves played in fewer than `min_games` games get no
+    // weight, their results say little.
+    pub fn reweigh(&mut self, min_games: u32) {
+        for book_move in self.positions.values_mut().flatten() {
+            book_move.weight = match book_move.games() >= min_games {
+                true => 2 * book_move.wins + book_move.draws,
+                false => 0,
+            };
+        }
+    }
+
+    // the book's moves for the position, turned to how it is on the board
+    pub fn moves(&self, state: &MicaState) -> Vec<BookMove> {
+        let (key, symmetry) = canonical_key(state);
+        let inverse = symmetry.inverse();
+        self.positions
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .map(|book_move| BookMove { mica_move: inverse.apply_move(book_move.mica_move), ..*book_move })
+            .collect()
+    }
+
+    // A legal move for the position by weight, None when the book has none
+    // for it. Moves that are not legal, from a book written for other rules,
+    // are never picked.
+    pub fn pick(&self, state: &MicaState, rng: &mut Rng) -> Option<MicaMove> {
+        let legal = state.get_moves();
+        let mut moves = self.moves(state);
+        moves.retain(|book_move| legal.contains(&book_move.mica_move));
+        let total: u64 = moves.iter().map(|book_move| book_move.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut ticket = rng.next_u64() % total;
+        moves.into_iter().find_map(|book_move| match ticket.checked_sub(book_move.weight as u64) {
+            Some(rest) => {
+                ticket = rest;
+                None
+            },
+            None => Some(book_move.mica_move),
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+
+use crate::minimax::{MicaMove, MAX_MOVES};
+
+// Scratch memory of a search, used as a bump arena. Every node of `negamax`
+// generates its moves on top of `moves` and gives them back by releasing to
+// the mark it started at, so the buffers grow to the deepest line once and
+// every node after that reuses them. Ordering keys, the stones a mill may
+// take and the best lines live here too.
+//
+// A state keeps its arena between searches. Workers searching one root move
+// after the other move theirs from state to state and `reset` it in between,
+// so a root task allocates nothing once its worker has searched one.
+#[derive(Debug, Clone, Default)]
+pub struct BumpArena {
+    pub(crate) moves: Vec<MicaMove>,
+    // of the moves being ordered, see `MicaState::order_moves`
+    pub(crate) keys: Vec<u64>,
+    // stones a mill may take in the position moves are generated for
+    pub(crate) removals: Vec<(u8, u8, u8)>,
+    // Best line found from the last node searched with this many plies left,
+    // the one at the searched depth is the principal variation.
+    pub(crate) pv: Vec<Vec<MicaMove>>,
+}
+
+impl BumpArena {
+    // room for the moves of a few plies up front
+    pub fn new() -> Self {
+        BumpArena {
+            moves: Vec::with_capacity(4 * MAX_MOVES),
+            keys: Vec::with_capacity(MAX_MOVES),
+            removals: Vec::with_capacity(9),
+            pv: Vec::new(),
+        }
+    }
+
+    // where the moves taken so far end, see `release`
+    pub fn mark(&self) -> usize {
+        self.moves.len()
+    }
+
+    // gives back every move taken after `mark`
+    pub fn release(&mut self, mark: usize) {
+        self.moves.truncate(mark);
+    }
+
+    // empties every buffer, keeping what they allocated for the next search
+    pub fn reset(&mut self) {
+        self.moves.clear();
+        self.keys.clear();
+        self.removals.clear();
+        self.pv.iter_mut().for_each(Vec::clear);
+    }
+}
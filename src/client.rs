@@ -0,0 +1,241 @@
+use std::fmt;
+use std::future::Future;
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::{BatchAnswer, BatchEntry, BatchRequest, Capabilities, DifficultyAnswer, ErrorBody, Game, GameMove, GameRequest, GraphRequest, MoveAnswer, ScoreGraph};
+use crate::minimax::MicaRequest;
+
+// Typed client for the server's HTTP API, built with the `client` feature.
+//
+//     let client = Client::new("http://127.0.0.1:7878")?;
+//     let answer = client.best_move(&MicaRequest::from_state(&state))?;
+//
+// Requests and answers are the `api` types the server itself uses. Every
+// call has an `_async` twin that runs it on a thread of its own and returns
+// a future of its result, which any executor can wait on. Streamed move
+// requests are not supported, `stream` has to stay false.
+
+// how long a request may take to send and to answer, searches included
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    // the server answered with an error status
+    Server { status: u16, error: ErrorBody },
+    // the answer was not the JSON the endpoint sends
+    Malformed(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(formatter, "{e}"),
+            ClientError::Server { status, error } => write!(formatter, "{status} {}: {}", error.error, error.details.join(", ")),
+            ClientError::Malformed(e) => write!(formatter, "malformed answer: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+// The result of a call made with one of the `_async` methods, ready once the
+// thread running the call finishes.
+pub struct Pending<T> {
+    // the result once there is one and the waker of the last poll before that
+    shared: Arc<Mutex<(Option<T>, Option<Waker>)>>,
+}
+
+impl<T: Send + 'static> Pending<T> {
+    fn spawn(call: impl FnOnce() -> T + Send + 'static) -> Self {
+        let shared = Arc::new(Mutex::new((None, None::<Waker>)));
+        let done = Arc::clone(&shared);
+        thread::spawn(move || {
+            let result = call();
+            let mut done = done.lock().unwrap();
+            done.0 = Some(result);
+            if let Some(waker) = done.1.take() {
+                waker.wake();
+            }
+        });
+        Pending { shared }
+    }
+}
+
+impl<T> Future for Pending<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<T> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.0.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.1 = Some(context.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    // to connect to, `host:port`
+    address: String,
+    host: String,
+}
+
+impl Client {
+    // a client of the server at `http://host[:port]`, the port defaults to 80
+    pub fn new(url: &str) -> Result<Self, ClientError> {
+        let unsupported = || ClientError::Io(io::Error::new(io::ErrorKind::InvalidInput, format!("`{url}` is not an http:// url")));
+        let authority = url.strip_prefix("http://").ok_or_else(unsupported)?.trim_end_matches('/');
+        if authority.is_empty() || authority.contains('/') {
+            return Err(unsupported());
+        }
+        let address = if authority.contains(':') { authority.to_string() } else { format!("{authority}:80") };
+        Ok(Client { address, host: authority.to_string() })
+    }
+
+    // `GET /capabilities`, the schema version, fields and limits of the server
+    pub fn capabilities(&self) -> Result<Capabilities, ClientError> {
+        self.call_raw("GET", "/capabilities", "")
+    }
+
+    // `POST /`, the engine's move in the position of the request
+    pub fn best_move(&self, request: &MicaRequest) -> Result<MoveAnswer, ClientError> {
+        self.call("POST", "/", request)
+    }
+
+    // `POST /batch`, the engine's moves in the positions of independent games
+    // searched side by side, an entry per request in their order
+    pub fn best_moves(&self, requests: &BatchRequest) -> Result<Vec<BatchEntry>, ClientError> {
+        self.call("POST", "/batch", requests).map(|answer: BatchAnswer| answer.answers)
+    }
+
+    // `POST /positions/difficulty`, how hard the position of the request is for a human
+    pub fn position_difficulty(&self, request: &MicaRequest) -> Result<DifficultyAnswer, ClientError> {
+        self.call("POST", "/positions/difficulty", request)
+    }
+
+    // `POST /game`, a new game against the engine
+    pub fn create_game(&self, request: &GameRequest) -> Result<Game, ClientError> {
+        self.call("POST", "/game", request)
+    }
+
+    // `GET /game/{id}`
+    pub fn game(&self, id: u64) -> Result<Game, ClientError> {
+        self.call_raw("GET", &format!("/game/{id}"), "")
+    }
+
+    // `POST /game/{id}/move`, plays the client's move in notation, None when
+    // the engine is to move, and answers with the game after the engine's reply
+    pub fn play(&self, id: u64, mica_move: Option<&str>) -> Result<Game, ClientError> {
+        let body = GameMove { mica_move: mica_move.map(str::to_string) };
+        self.call("POST", &format!("/game/{id}/move"), &body)
+    }
+
+    // `GET /game/{id}/graph`, the scores of every position of the game so far
+    pub fn game_graph(&self, id: u64) -> Result<ScoreGraph, ClientError> {
+        self.call_raw("GET", &format!("/game/{id}/graph"), "")
+    }
+
+    // `POST /games/graph`, the scores of every position of a game played elsewhere
+    pub fn score_graph(&self, request: &GraphRequest) -> Result<ScoreGraph, ClientError> {
+        self.call("POST", "/games/graph", request)
+    }
+
+    pub fn capabilities_async(&self) -> Pending<Result<Capabilities, ClientError>> {
+        let client = self.clone();
+        Pending::spawn(move || client.capabilities())
+    }
+
+    pub fn best_move_async(&self, request: &MicaRequest) -> Pending<Result<MoveAnswer, ClientError>> {
+        let (client, body) = (self.clone(), serde_json::to_string(request).unwrap());
+        Pending::spawn(move || client.call_raw("POST", "/", &body))
+    }
+
+    pub fn best_moves_async(&self, requests: &BatchRequest) -> Pending<Result<Vec<BatchEntry>, ClientError>> {
+        let (client, body) = (self.clone(), serde_json::to_string(requests).unwrap());
+        Pending::spawn(move || client.call_raw("POST", "/batch", &body).map(|answer: BatchAnswer| answer.answers))
+    }
+
+    pub fn position_difficulty_async(&self, request: &MicaRequest) -> Pending<Result<DifficultyAnswer, ClientError>> {
+        let (client, body) = (self.clone(), serde_json::to_string(request).unwrap());
+        Pending::spawn(move || client.call_raw("POST", "/positions/difficulty", &body))
+    }
+
+    pub fn create_game_async(&self, request: &GameRequest) -> Pending<Result<Game, ClientError>> {
+        let (client, body) = (self.clone(), serde_json::to_string(request).unwrap());
+        Pending::spawn(move || client.call_raw("POST", "/game", &body))
+    }
+
+    pub fn game_async(&self, id: u64) -> Pending<Result<Game, ClientError>> {
+        let client = self.clone();
+        Pending::spawn(move || client.game(id))
+    }
+
+    pub fn play_async(&self, id: u64, mica_move: Option<&str>) -> Pending<Result<Game, ClientError>> {
+        let (client, mica_move) = (self.clone(), mica_move.map(str::to_string));
+        Pending::spawn(move || client.play(id, mica_move.as_deref()))
+    }
+
+    pub fn game_graph_async(&self, id: u64) -> Pending<Result<ScoreGraph, ClientError>> {
+        let client = self.clone();
+        Pending::spawn(move || client.game_graph(id))
+    }
+
+    pub fn score_graph_async(&self, request: &GraphRequest) -> Pending<Result<ScoreGraph, ClientError>> {
+        let (client, body) = (self.clone(), serde_json::to_string(request).unwrap());
+        Pending::spawn(move || client.call_raw("POST", "/games/graph", &body))
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, path: &str, body: &impl Serialize) -> Result<T, ClientError> {
+        self.call_raw(method, path, &serde_json::to_string(body).unwrap())
+    }
+
+    fn call_raw<T: DeserializeOwned>(&self, method: &str, path: &str, body: &str) -> Result<T, ClientError> {
+        let (status, body) = self.send(method, path, body)?;
+        if !(200..300).contains(&status) {
+            let error = serde_json::from_str(&body).map_err(|e| ClientError::Malformed(format!("{status} answer: {e}")))?;
+            return Err(ClientError::Server { status, error });
+        }
+        serde_json::from_str(&body).map_err(|e| ClientError::Malformed(e.to_string()))
+    }
+
+    // one `Connection: close` request, the status and body of its answer
+    fn send(&self, method: &str, path: &str, body: &str) -> io::Result<(u16, String)> {
+        let mut stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.host,
+            body.len()
+        );
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = String::new();
+        BufReader::new(&stream).read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed response status line"))?;
+        Ok((status, body.to_string()))
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use crate::minimax::*;
+
+// Chess-clock time control for a game played against the engine: both sides
+// start with the same time and get `increment` back after every move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub white: Duration,
+    pub black: Duration,
+    pub increment: Duration,
+}
+
+impl Clock {
+    pub fn new(initial: Duration, increment: Duration) -> Self {
+        Clock {
+            white: initial,
+            black: initial,
+            increment,
+        }
+    }
+
+    pub fn remaining(&self, player: MicaPlayer) -> Duration {
+        match player {
+            MicaPlayer::White => self.white,
+            MicaPlayer::Black => self.black,
+            MicaPlayer::None => Duration::ZERO,
+        }
+    }
+
+    // takes the time a move took off the player's clock, false when their flag fell
+    pub fn charge(&mut self, player: MicaPlayer, elapsed: Duration) -> bool {
+        let increment = self.increment;
+        let clock = match player {
+            MicaPlayer::White => &mut self.white,
+            MicaPlayer::Black => &mut self.black,
+            MicaPlayer::None => return true,
+        };
+        if elapsed >= *clock {
+            *clock = Duration::ZERO;
+            return false;
+        }
+        *clock = *clock - elapsed + increment;
+        true
+    }
+}
+
+// legal moves in a typical middlegame position, positions with more get more time
+const TYPICAL_MOVES: usize = 12;
+
+// Time the side to move should spend on its next move.
+//
+// The remaining time is spread over the moves the game is still expected to
+// last, then scaled by the number of legal moves: forced positions are played
+// instantly and wide open ones get up to twice the share. A quarter of the
+// clock is the most a single move may use.
+pub fn allocate(state: &MicaState, remaining: Duration, increment: Duration) -> Duration {
+    let legal_moves = state.get_moves().len();
+    if legal_moves <= 1 {
+        return Duration::ZERO;
+    }
+
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    let to_set = match state.current_player {
+        MicaPlayer::White => white_to_set,
+        _ => black_to_set,
+    };
+    let moves_to_go = to_set as u32 + 20;
+
+    let share = remaining / moves_to_go + increment * 3 / 4;
+    let complexity = (legal_moves as f64 / TYPICAL_MOVES as f64).clamp(0.5, 2.0);
+    share.mul_f64(complexity).min(remaining / 4)
+}
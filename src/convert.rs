@@ -0,0 +1,67 @@
+use std::io::{self, BufRead, Write};
+use mica::notation::{self, MoveFormat, PositionFormat};
+
+use crate::option;
+
+// Converts positions or move lists read line by line from stdin.
+//
+//     mica convert --from <json|flat|fen|packed> --to <json|flat|fen|packed>
+//     mica convert --from <actions|notation> --to <actions|notation>
+enum Format {
+    Position(PositionFormat),
+    Moves(MoveFormat),
+}
+
+fn parse_format(name: &str) -> Option<Format> {
+    match name {
+        "json" => Some(Format::Position(PositionFormat::Json)),
+        "flat" => Some(Format::Position(PositionFormat::Flat)),
+        "fen" => Some(Format::Position(PositionFormat::Fen)),
+        "packed" => Some(Format::Position(PositionFormat::Packed)),
+        "actions" => Some(Format::Moves(MoveFormat::Actions)),
+        "notation" => Some(Format::Moves(MoveFormat::Notation)),
+        _ => None,
+    }
+}
+
+fn convert_line(from: &Format, to: &Format, line: &str) -> Result<String, String> {
+    match (from, to) {
+        (Format::Position(from), Format::Position(to)) => {
+            notation::parse_position(*from, line).map(|state| notation::format_position(*to, &state))
+        },
+        (Format::Moves(from), Format::Moves(to)) => {
+            notation::parse_moves(*from, line).map(|moves| notation::format_moves(*to, &moves))
+        },
+        _ => Err("cannot convert between positions and move lists".to_string()),
+    }
+}
+
+pub fn run(args: &[String]) {
+    let from_name: String = option(args, "--from", "json".to_string());
+    let to_name: String = option(args, "--to", "fen".to_string());
+    let (Some(from), Some(to)) = (parse_format(&from_name), parse_format(&to_name)) else {
+        eprintln!("Unknown format, expected one of json, flat, fen, packed, actions, notation");
+        std::process::exit(2);
+    };
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut failed = false;
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        if line.trim().is_empty() {
+            continue;
+        }
+        match convert_line(&from, &to, line.trim()) {
+            Ok(converted) => writeln!(stdout, "{converted}").unwrap(),
+            Err(e) => {
+                eprintln!("{e}");
+                failed = true;
+            },
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
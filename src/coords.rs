@@ -0,0 +1,218 @@
+// Conversions between the three ways a board point is written:
+//
+// - array coordinates (x, y, z): x is the ring (0 is the outer ring), y the
+//   row and z the column inside the ring, with (y, z) = (1, 1) never used
+// - flat indices 0-23: ring by ring, outer ring first, clockwise from the
+//   top-left corner of each ring
+// - a1-g7 notation: files a-g from left to right, ranks 1-7 from bottom to top
+
+use alloc::format;
+use alloc::string::String;
+
+pub const POINTS: [(u8, u8, u8); 24] = [
+    (0, 0, 0), (0, 0, 1), (0, 0, 2), (0, 1, 2), (0, 2, 2), (0, 2, 1), (0, 2, 0), (0, 1, 0),
+    (1, 0, 0), (1, 0, 1), (1, 0, 2), (1, 1, 2), (1, 2, 2), (1, 2, 1), (1, 2, 0), (1, 1, 0),
+    (2, 0, 0), (2, 0, 1), (2, 0, 2), (2, 1, 2), (2, 2, 2), (2, 2, 1), (2, 2, 0), (2, 1, 0),
+];
+
+pub fn is_point(x: u8, y: u8, z: u8) -> bool {
+    x < 3 && y < 3 && z < 3 && !(y == 1 && z == 1)
+}
+
+pub fn to_index(x: u8, y: u8, z: u8) -> Option<usize> {
+    if !is_point(x, y, z) {
+        return None;
+    }
+    // position along the ring, clockwise from the top-left corner
+    let along = match (y, z) {
+        (0, z) => z,
+        (1, 2) => 3,
+        (2, z) => 6 - z,
+        _ => 7,
+    };
+    Some(x as usize * 8 + along as usize)
+}
+
+pub fn from_index(index: usize) -> Option<(u8, u8, u8)> {
+    POINTS.get(index).copied()
+}
+
+pub fn to_notation(x: u8, y: u8, z: u8) -> Option<String> {
+    if !is_point(x, y, z) {
+        return None;
+    }
+    // distance of the ring from the center point d4
+    let distance = 3 - x as i8;
+    let file = (b'd' as i8 + (z as i8 - 1) * distance) as u8 as char;
+    let rank = 4 - (y as i8 - 1) * distance;
+    Some(format!("{file}{rank}"))
+}
+
+pub fn from_notation(notation: &str) -> Option<(u8, u8, u8)> {
+    let bytes = notation.trim().as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let file = bytes[0].to_ascii_lowercase() as i8 - b'd' as i8;
+    let rank = bytes[1] as i8 - b'4' as i8;
+    if !(-3..=3).contains(&file) || !(-3..=3).contains(&rank) {
+        return None;
+    }
+
+    // the ring follows from the larger of the two offsets from the center
+    let distance = file.abs().max(rank.abs());
+    if distance == 0 || (file != 0 && rank != 0 && file.abs() != rank.abs()) {
+        return None;
+    }
+    let x = (3 - distance) as u8;
+    let y = (1 - rank / distance) as u8;
+    let z = (1 + file / distance) as u8;
+    Some((x, y, z))
+}
+
+// points a stone moves to in one step, by point
+const fn neighbours() -> [u32; 24] {
+    let mut neighbours = [0; 24];
+    let mut point = 0;
+    while point < 24 {
+        let (ring, along) = (point / 8, point % 8);
+        // along the ring
+        neighbours[point] |= 1 << (ring * 8 + (along + 1) % 8) | 1 << (ring * 8 + (along + 7) % 8);
+        // across the rings, only the middle points are connected
+        if along % 2 == 1 {
+            if ring > 0 {
+                neighbours[point] |= 1 << (point - 8);
+            }
+            if ring < 2 {
+                neighbours[point] |= 1 << (point + 8);
+            }
+        }
+        point += 1;
+    }
+    neighbours
+}
+
+// the sixteen mills, as flat index bit sets, four sides of every ring and the four lines across them
+const fn mills() -> [u32; 16] {
+    let mut mills = [0; 16];
+    let mut ring = 0;
+    while ring < 3 {
+        let mut side = 0;
+        while side < 4 {
+            let start = ring * 8 + side * 2;
+            mills[ring * 4 + side] = 1 << start | 1 << (start + 1) | 1 << (ring * 8 + (side * 2 + 2) % 8);
+            side += 1;
+        }
+        ring += 1;
+    }
+    let mut across = 0;
+    while across < 4 {
+        let along = across * 2 + 1;
+        mills[12 + across] = 1 << along | 1 << (8 + along) | 1 << (16 + along);
+        across += 1;
+    }
+    mills
+}
+
+// the two mills every point is part of, by flat index
+const fn point_mills() -> [[u32; 2]; 24] {
+    let mut point_mills = [[0; 2]; 24];
+    let mut point = 0;
+    while point < 24 {
+        let (mut mill, mut found) = (0, 0);
+        while mill < 16 {
+            if MILLS[mill] & 1 << point != 0 {
+                point_mills[point][found] = MILLS[mill];
+                found += 1;
+            }
+            mill += 1;
+        }
+        point += 1;
+    }
+    point_mills
+}
+
+// a stone moves from a point to the points of its bit set in one step, by flat index
+pub const NEIGHBOURS: [u32; 24] = neighbours();
+pub const MILLS: [u32; 16] = mills();
+pub const POINT_MILLS: [[u32; 2]; 24] = point_mills();
+
+// the flat indices in a bit set, lowest first
+pub fn bits(mut set: u32) -> impl Iterator<Item = usize> {
+    core::iter::from_fn(move || {
+        (set != 0).then(|| {
+            let point = set.trailing_zeros() as usize;
+            set &= set - 1;
+            point
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_point_round_trips() {
+        for (index, &(x, y, z)) in POINTS.iter().enumerate() {
+            assert!(is_point(x, y, z));
+            assert_eq!(to_index(x, y, z), Some(index));
+            assert_eq!(from_index(index), Some((x, y, z)));
+            let notation = to_notation(x, y, z).unwrap();
+            assert_eq!(from_notation(&notation), Some((x, y, z)), "{notation}");
+            assert_eq!(from_notation(&notation.to_uppercase()), Some((x, y, z)), "{notation}");
+        }
+        assert_eq!(from_index(24), None);
+        assert_eq!(to_index(0, 1, 1), None);
+        assert_eq!(from_notation("d4"), None);
+        assert_eq!(from_notation("b1"), None);
+    }
+
+    #[test]
+    fn notation_names_the_corners() {
+        assert_eq!(to_notation(0, 0, 0).as_deref(), Some("a7"));
+        assert_eq!(to_notation(0, 2, 2).as_deref(), Some("g1"));
+        assert_eq!(to_notation(2, 1, 0).as_deref(), Some("c4"));
+        assert_eq!(to_notation(1, 0, 1).as_deref(), Some("d6"));
+    }
+
+    // one step along a side of a ring, or across the rings through the middle of a side
+    fn adjacent((x1, y1, z1): (u8, u8, u8), (x2, y2, z2): (u8, u8, u8)) -> bool {
+        let along = x1 == x2 && y1.abs_diff(y2) + z1.abs_diff(z2) == 1;
+        let across = (y1, z1) == (y2, z2) && (y1 == 1 || z1 == 1) && x1.abs_diff(x2) == 1;
+        along || across
+    }
+
+    #[test]
+    fn neighbours_are_the_adjacent_points() {
+        for a in 0..24 {
+            for b in 0..24 {
+                let connected = NEIGHBOURS[a] & 1 << b != 0;
+                assert_eq!(connected, NEIGHBOURS[b] & 1 << a != 0, "{a} and {b}");
+                assert_eq!(connected, adjacent(POINTS[a], POINTS[b]), "{a} and {b}");
+            }
+        }
+    }
+
+    // three points of a ring along one side, or the middles of the same side across the rings
+    fn is_line(points: &[(u8, u8, u8)]) -> bool {
+        let &[(x1, y1, z1), (x2, y2, z2), (x3, y3, z3)] = points else {
+            return false;
+        };
+        let side = x1 == x2 && x2 == x3 && ((y1 == y2 && y2 == y3 && y1 != 1) || (z1 == z2 && z2 == z3 && z1 != 1));
+        let across = (y1, z1) == (y2, z2) && (y2, z2) == (y3, z3);
+        side || across
+    }
+
+    #[test]
+    fn mills_are_the_lines_of_three() {
+        for mill in MILLS {
+            let points: alloc::vec::Vec<_> = bits(mill).map(|point| POINTS[point]).collect();
+            assert!(is_line(&points), "{points:?}");
+        }
+        for (point, point_mills) in POINT_MILLS.iter().enumerate() {
+            let containing: alloc::vec::Vec<_> = MILLS.iter().copied().filter(|mill| mill & 1 << point != 0).collect();
+            assert_eq!(point_mills.as_slice(), containing, "{point}");
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use serde::Serialize;
+
+use crate::coords::{from_index, to_index, to_notation};
+use crate::gym::decode_action;
+use crate::minimax::*;
+use crate::selfplay::GameRecord;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Results {
+    pub games: u64,
+    pub white_wins: u64,
+    pub draws: u64,
+    pub black_wins: u64,
+}
+
+impl Results {
+    // `result` is a game result for white, 1, 0 or -1
+    fn add(&mut self, result: i8) {
+        self.games += 1;
+        match result {
+            1 => self.white_wins += 1,
+            -1 => self.black_wins += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    // white's share of the points, a draw counts half
+    pub fn white_score(&self) -> f64 {
+        (self.white_wins as f64 + self.draws as f64 / 2.0) / self.games.max(1) as f64
+    }
+}
+
+// Results of the games that started with white's first stone on one point.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpeningStats {
+    pub point: String,
+    #[serde(flatten)]
+    pub results: Results,
+    pub white_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MillStats {
+    pub point: String,
+    // mills closed by a stone set or moved onto this point
+    pub mills: u64,
+    pub per_game: f64,
+}
+
+// Aggregate statistics over a set of games.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CorpusStats {
+    #[serde(flatten)]
+    pub results: Results,
+    // average plies per game while stones are being set and after
+    pub setting_plies: f64,
+    pub moving_plies: f64,
+    // games whose moves do not replay, they are left out of everything else
+    pub skipped: u64,
+    pub openings: Vec<OpeningStats>,
+    pub mills: Vec<MillStats>,
+}
+
+fn point_name(point: usize) -> String {
+    let (x, y, z) = from_index(point).unwrap();
+    to_notation(x, y, z).unwrap()
+}
+
+// Replays every game and counts, per point, how often white opened there and
+// how those games ended, and how many mills were closed there.
+pub fn analyze(records: &[GameRecord]) -> CorpusStats {
+    let mut stats = CorpusStats::default();
+    let mut openings = [Results::default(); 24];
+    let mut mills = [0u64; 24];
+    let (mut setting_plies, mut moving_plies) = (0, 0);
+
+    'games: for record in records {
+        let mut state = MicaState::new();
+        let mut first_point = None;
+        let (mut setting, mut moving) = (0, 0);
+        let mut game_mills = [0u64; 24];
+        for &action in &record.moves {
+            let Some(mica_move) = decode_action(action) else {
+                stats.skipped += 1;
+                continue 'games;
+            };
+            let (white_to_set, black_to_set) = state.stones_to_set();
+            if white_to_set + black_to_set > 0 {
+                setting += 1;
+            } else {
+                moving += 1;
+            }
+            let to = match mica_move {
+                MicaMove::Set { x, y, z } | MicaMove::SetRemove { x, y, z, .. } => to_index(x, y, z),
+                MicaMove::Move { to_x, to_y, to_z, .. } | MicaMove::MoveRemove { to_x, to_y, to_z, .. } => to_index(to_x, to_y, to_z),
+            };
+            let to = to.unwrap();
+            first_point.get_or_insert(to);
+            if matches!(mica_move, MicaMove::SetRemove { .. } | MicaMove::MoveRemove { .. }) {
+                game_mills[to] += 1;
+            }
+            if state.checked_apply_move(mica_move).is_err() {
+                stats.skipped += 1;
+                continue 'games;
+            }
+            state.current_player.toggle();
+        }
+
+        stats.results.add(record.result);
+        if let Some(point) = first_point {
+            openings[point].add(record.result);
+        }
+        setting_plies += setting;
+        moving_plies += moving;
+        for (total, game) in mills.iter_mut().zip(game_mills) {
+            *total += game;
+        }
+    }
+
+    let games = stats.results.games.max(1) as f64;
+    stats.setting_plies = setting_plies as f64 / games;
+    stats.moving_plies = moving_plies as f64 / games;
+    stats.openings = openings
+        .into_iter()
+        .enumerate()
+        .filter(|(_, results)| results.games > 0)
+        .map(|(point, results)| OpeningStats { point: point_name(point), results, white_score: results.white_score() })
+        .collect();
+    stats.mills = mills
+        .iter()
+        .enumerate()
+        .map(|(point, &count)| MillStats { point: point_name(point), mills: count, per_game: count as f64 / games })
+        .collect();
+    stats
+}
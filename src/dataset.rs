@@ -0,0 +1,153 @@
+use std::io::{self, Write};
+
+use crate::selfplay::{self, GameRecord};
+
+// Exports game records as an uncompressed `.npz` archive that numpy can load
+// directly. Every position becomes one row, seen from the side to move:
+//
+//   boards  uint8  (N, 2, 24)  stones of the side to move, stones of the opponent
+//   in_hand uint8  (N, 2)      stones left to set for the side to move and the opponent
+//   policy  int64  (N,)        gym action index of the move played
+//   result  int8   (N,)        game outcome for the side to move
+pub fn write_npz<W: Write>(records: &[GameRecord], out: W) -> io::Result<()> {
+    let mut boards = Vec::new();
+    let mut in_hand = Vec::new();
+    let mut policy = Vec::new();
+    let mut result = Vec::new();
+
+    for record in records {
+        for sample in selfplay::samples(record) {
+            let mover = sample.obs[24];
+            for side in [mover, -mover] {
+                boards.extend(sample.obs[..24].iter().map(|&stone| (stone == side) as u8));
+            }
+            let (white_to_set, black_to_set) = (sample.obs[25] as u8, sample.obs[26] as u8);
+            if mover == 1 {
+                in_hand.extend([white_to_set, black_to_set]);
+            } else {
+                in_hand.extend([black_to_set, white_to_set]);
+            }
+            policy.extend((sample.action as i64).to_le_bytes());
+            result.push(sample.outcome as u8);
+        }
+    }
+
+    let rows = result.len();
+    let mut npz = NpzWriter::new(out);
+    npz.add_array("boards", "|u1", &[rows, 2, 24], &boards)?;
+    npz.add_array("in_hand", "|u1", &[rows, 2], &in_hand)?;
+    npz.add_array("policy", "<i8", &[rows], &policy)?;
+    npz.add_array("result", "|i1", &[rows], &result)?;
+    npz.finish()
+}
+
+fn npy_bytes(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+
+    // magic, version and header length take 10 bytes, the whole header is padded to 64
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(10 + header.len() + data.len());
+    bytes.extend(b"\x93NUMPY\x01\x00");
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// minimal zip writer, entries are stored without compression
+struct NpzWriter<W: Write> {
+    out: W,
+    offset: u32,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl<W: Write> NpzWriter<W> {
+    fn new(out: W) -> Self {
+        NpzWriter {
+            out,
+            offset: 0,
+            central_directory: Vec::new(),
+            entries: 0,
+        }
+    }
+
+    fn add_array(&mut self, name: &str, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+        let name = format!("{name}.npy");
+        let data = npy_bytes(descr, shape, data);
+        let crc = crc32(&data);
+        let size = data.len() as u32;
+        // 1980-01-01 00:00, the earliest date zip can represent
+        let (time, date) = (0u16, 0x21u16);
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend(0x0403_4b50u32.to_le_bytes());
+        local.extend(20u16.to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(time.to_le_bytes());
+        local.extend(date.to_le_bytes());
+        local.extend(crc.to_le_bytes());
+        local.extend(size.to_le_bytes());
+        local.extend(size.to_le_bytes());
+        local.extend((name.len() as u16).to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(name.as_bytes());
+
+        let central = &mut self.central_directory;
+        central.extend(0x0201_4b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(time.to_le_bytes());
+        central.extend(date.to_le_bytes());
+        central.extend(crc.to_le_bytes());
+        central.extend(size.to_le_bytes());
+        central.extend(size.to_le_bytes());
+        central.extend((name.len() as u16).to_le_bytes());
+        central.extend([0u8; 12]);
+        central.extend(self.offset.to_le_bytes());
+        central.extend(name.as_bytes());
+
+        self.out.write_all(&local)?;
+        self.out.write_all(&data)?;
+        self.offset += local.len() as u32 + size;
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.out.write_all(&self.central_directory)?;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend(0x0605_4b50u32.to_le_bytes());
+        end.extend([0u8; 4]);
+        end.extend(self.entries.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend((self.central_directory.len() as u32).to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+
+        self.out.write_all(&end)?;
+        self.out.flush()
+    }
+}
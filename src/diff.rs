@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::coords::POINTS;
+use crate::minimax::*;
+
+// One visible change between two boards, for animating a transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Change {
+    Placed { player: PlayerField, to: (u8, u8, u8) },
+    Moved { player: PlayerField, from: (u8, u8, u8), to: (u8, u8, u8) },
+    Removed { player: PlayerField, from: (u8, u8, u8) },
+}
+
+// Changes that turn `before` into `after`, placements and moves first and
+// removals last. A stone of a player that disappears from one point and
+// appears on another is reported as a move; any other disappearance is a
+// removal and any other appearance a placement.
+pub fn diff(before: &MicaState, after: &MicaState) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut removals = Vec::new();
+
+    for player in [MicaPlayer::White, MicaPlayer::Black] {
+        let mut gone = Vec::new();
+        let mut appeared = Vec::new();
+        for &(x, y, z) in POINTS.iter() {
+            let (was, is) = (before.stone(x, y, z), after.stone(x, y, z));
+            if was == player && is != player {
+                gone.push((x, y, z));
+            }
+            if was != player && is == player {
+                appeared.push((x, y, z));
+            }
+        }
+
+        let player_field = PlayerField::Name(player);
+        let moved = gone.len().min(appeared.len());
+        for (&from, &to) in gone.iter().zip(appeared.iter()) {
+            changes.push(Change::Moved { player: player_field, from, to });
+        }
+        for &to in &appeared[moved..] {
+            changes.push(Change::Placed { player: player_field, to });
+        }
+        for &from in &gone[moved..] {
+            removals.push(Change::Removed { player: player_field, from });
+        }
+    }
+
+    changes.extend(removals);
+    changes
+}
+
+// changes made by playing `mica_move` for the side to move of `state`
+pub fn move_changes(state: &MicaState, mica_move: MicaMove) -> Vec<Change> {
+    let mut after = state.clone();
+    after.apply_move(mica_move);
+    diff(state, &after)
+}
@@ -0,0 +1,140 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use core::time::Duration;
+
+use crate::minimax::DECISIVE;
+use crate::rng::Rng;
+use crate::search::{RootMove, SearchResult};
+
+// Strength a client asks for with the `difficulty` field of a move request.
+// Explicit `depth` and `time_ms` fields still win over the difficulty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty `{s}`, expected easy, medium or hard")),
+        }
+    }
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn depth(self) -> u8 {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Medium => 4,
+            Difficulty::Hard => 8,
+        }
+    }
+
+    pub fn time(self) -> Duration {
+        match self {
+            Difficulty::Easy => Duration::from_millis(200),
+            Difficulty::Medium => Duration::from_secs(1),
+            Difficulty::Hard => Duration::from_secs(5),
+        }
+    }
+
+    // up to this many stones of random preference are added to every root move
+    pub fn noise(self) -> u8 {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Medium => 1,
+            Difficulty::Hard => 0,
+        }
+    }
+
+    // The searched root move with the best score for `side` plus noise. A
+    // won game outscores any noise, so a forced win is never passed up.
+    // Searched with a margin of `noise`, a move with only a bound can at most
+    // tie with the best move, exact scores win ties.
+    pub fn pick(self, roots: &[RootMove], side: i32, rng: &mut Rng) -> Option<RootMove> {
+        roots
+            .iter()
+            .filter_map(|root| Some(((root.score? * side + rng.below(self.noise() as usize + 1) as i32, root.exact), root)))
+            .reduce(|best, root| if root.0 > best.0 { root } else { best })
+            .map(|(_, root)| root.clone())
+    }
+}
+
+// How hard it is for a human to find the best move of a position, from a
+// search that gave every root move an exact score. A position is harder the
+// more moves there are to choose from, the fewer of them are as good as the
+// best one and the more the search changed its mind between depths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionDifficulty {
+    // 0 for a position with a single legal move, up to 100
+    pub score: u8,
+    pub legal_moves: usize,
+    // root moves that score as well as the best one
+    pub best_moves: usize,
+    // how much worse the next best move is, in stones, None when every move is as good
+    pub gap: Option<i32>,
+    // between consecutive depths of the search
+    pub best_move_changes: usize,
+    // stones the score moved by between consecutive depths, summed, see `stones`
+    pub score_swing: i32,
+}
+
+impl PositionDifficulty {
+    pub fn estimate(search: &SearchResult, side: i32) -> Self {
+        let scores: Vec<i32> = search.roots.iter().filter(|root| root.exact).filter_map(|root| Some(stones(root.score? * side))).collect();
+        let best = scores.iter().copied().max();
+        let best_moves = scores.iter().filter(|&&score| Some(score) == best).count();
+        let gap = best.and_then(|best| scores.iter().filter(|&&score| score < best).max().map(|&next| best - next));
+        let best_move_changes = search.iterations.windows(2).filter(|pair| pair[0].best_move != pair[1].best_move).count();
+        let score_swing = search.iterations.windows(2).map(|pair| (stones(pair[1].score) - stones(pair[0].score)).abs()).sum::<i32>();
+
+        let legal_moves = search.roots.len();
+        // a wide choice of moves that are worse than the best, 24 moves and more count as much as 24
+        let worse = legal_moves.saturating_sub(best_moves);
+        let choice = (legal_moves.min(24) * 25 * worse / (24 * legal_moves.max(1))) as i32;
+        // a single move that holds the score, several equal ones share less
+        let uniqueness = match best_moves {
+            0 => 0,
+            1 => 10 * gap.unwrap_or(0).min(4),
+            n => 20 / n as i32,
+        };
+        let volatility = (best_move_changes as i32 * 10 + score_swing.min(5) * 3).min(35);
+        let score = if legal_moves <= 1 { 0 } else { (choice + uniqueness + volatility).min(100) as u8 };
+        PositionDifficulty { score, legal_moves, best_moves, gap, best_move_changes, score_swing }
+    }
+
+    // the difficulty a puzzle of this position is graded as
+    pub fn grade(&self) -> Difficulty {
+        match self.score {
+            0..=33 => Difficulty::Easy,
+            34..=66 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+}
+
+// a score in stones, a won game five stones more than the most a side can be ahead
+fn stones(score: i32) -> i32 {
+    match score {
+        score if score >= DECISIVE => 14,
+        score if score <= -DECISIVE => -14,
+        score => score,
+    }
+}
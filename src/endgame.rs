@@ -0,0 +1,41 @@
+use std::path::Path;
+use std::time::Instant;
+use mica::tablebase::{TableResult, Tablebase};
+
+use crate::option;
+
+// Solves the moving phase endgames with few stones and writes them to a
+// tablebase file the server can load with `--tablebase`.
+//
+//     mica tablebase [--stones 7] [--out mica-tablebase.bin]
+//
+// `--stones` is the most stones on the board, three or more a side. Every
+// stone more multiplies the positions several times over.
+pub fn run(args: &[String]) {
+    let stones: u8 = option(args, "--stones", 7);
+    let out_path: String = option(args, "--out", "mica-tablebase.bin".to_string());
+
+    let started = Instant::now();
+    let tablebase = Tablebase::generate(stones, |more, fewer| {
+        eprintln!("Solved {more} against {fewer} stones after {:.1}s", started.elapsed().as_secs_f64());
+    });
+    for ((mine, theirs), table) in tablebase.tables() {
+        let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+        for &byte in table {
+            match TableResult::from_byte(byte) {
+                TableResult::Win(plies) => {
+                    wins += 1;
+                    longest = longest.max(plies);
+                },
+                TableResult::Loss(plies) => {
+                    losses += 1;
+                    longest = longest.max(plies);
+                },
+                TableResult::Draw => draws += 1,
+            }
+        }
+        println!("{mine}v{theirs}: {} positions, {wins} won, {losses} lost, {draws} drawn, longest {longest} plies", table.len());
+    }
+    tablebase.save(Path::new(&out_path)).unwrap();
+    eprintln!("Wrote {out_path}");
+}
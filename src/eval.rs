@@ -0,0 +1,249 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::coords::{bits, MILLS, NEIGHBOURS, POINT_MILLS};
+use crate::minimax::MicaState;
+use crate::tuned;
+use crate::variants::{Phase, STONES_PER_PLAYER};
+
+// What the evaluation counts besides the stones on the board, every term in
+// hundredths of a stone. A position is scored in whole stones, the terms are
+// added up before rounding towards zero, so a term only tips the score once
+// it adds up to a stone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalWeights {
+    // per step move white has more than black in the moving phase, a side
+    // that can hardly move is close to being blocked
+    pub mobility: i32,
+    // per mill white has closed more than black
+    pub mills: i32,
+    // per two stones of a mill with its third point empty, a mill coming
+    pub open_twos: i32,
+    // per two opponent stones of a mill with the side's own stone on its third point
+    pub blocked_twos: i32,
+    // per stone of black's with no empty neighbour more than of white's, a
+    // side with every stone blocked loses, stones that can fly never are
+    pub blocked: i32,
+    // Per windmill white has more than black in the moving phase: a stone
+    // of a closed mill with a step to an empty point that closes another
+    // one, so every move back and forth takes a stone. Practically a win.
+    pub windmills: i32,
+}
+
+// the moving phase's weights of the table the engine plays with, see `tuned`
+impl Default for EvalWeights {
+    fn default() -> Self {
+        tuned::WEIGHTS.moving
+    }
+}
+
+impl EvalWeights {
+    // only the stones count
+    pub const MATERIAL: EvalWeights = EvalWeights { mobility: 0, mills: 0, open_twos: 0, blocked_twos: 0, blocked: 0, windmills: 0 };
+
+    pub const NAMES: [&'static str; 6] = ["mobility", "mills", "open_twos", "blocked_twos", "blocked", "windmills"];
+
+    // every weight in the order of `NAMES`
+    pub fn values_mut(&mut self) -> [&mut i32; 6] {
+        [&mut self.mobility, &mut self.mills, &mut self.open_twos, &mut self.blocked_twos, &mut self.blocked, &mut self.windmills]
+    }
+}
+
+// `name=value` pairs separated by commas, the weights left out keep their default
+impl FromStr for EvalWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = EvalWeights::default();
+        for pair in s.split(',').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').ok_or_else(|| format!("`{pair}` is not name=value"))?;
+            let i = EvalWeights::NAMES
+                .iter()
+                .position(|&known| known == name)
+                .ok_or_else(|| format!("unknown weight `{name}`, expected one of {}", EvalWeights::NAMES.join(", ")))?;
+            *weights.values_mut()[i] = value.parse().map_err(|e| format!("weight `{name}`: {e}"))?;
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for EvalWeights {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut weights = *self;
+        for (i, (name, value)) in EvalWeights::NAMES.iter().zip(weights.values_mut()).enumerate() {
+            write!(formatter, "{}{name}={value}", if i > 0 { "," } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+// A weight set for every phase of the game, see `Phase`. While stones are
+// being set the weights move from the setting ones towards the moving ones
+// with every stone set, so the last stones are set with an eye on the
+// moving phase. The flying ones take over once a side may fly, a side down
+// to three stones without flying plays on with the moving ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseWeights {
+    pub setting: EvalWeights,
+    pub moving: EvalWeights,
+    pub flying: EvalWeights,
+}
+
+impl Default for PhaseWeights {
+    fn default() -> Self {
+        tuned::WEIGHTS
+    }
+}
+
+impl PhaseWeights {
+    pub const PHASES: [&'static str; 3] = ["setting", "moving", "flying"];
+
+    // only the stones count
+    pub const MATERIAL: PhaseWeights = PhaseWeights { setting: EvalWeights::MATERIAL, moving: EvalWeights::MATERIAL, flying: EvalWeights::MATERIAL };
+
+    // every weight of every phase, `phase.name` in the order of `PHASES` and `EvalWeights::NAMES`
+    pub fn names() -> Vec<String> {
+        PhaseWeights::PHASES.iter().flat_map(|phase| EvalWeights::NAMES.iter().map(move |name| format!("{phase}.{name}"))).collect()
+    }
+
+    // in the order of `names`
+    pub fn values_mut(&mut self) -> Vec<&mut i32> {
+        [&mut self.setting, &mut self.moving, &mut self.flying].into_iter().flat_map(EvalWeights::values_mut).collect()
+    }
+
+    // the weights of the phase of `state`, interpolated while setting
+    pub fn at(&self, state: &MicaState) -> EvalWeights {
+        match state.phase() {
+            Phase::Setting => {
+                let (white_to_set, black_to_set) = state.stones_to_set();
+                let (stones, to_set) = (2 * STONES_PER_PLAYER as i32, (white_to_set + black_to_set) as i32);
+                let mut weights = self.setting;
+                let mut moving = self.moving;
+                for (weight, moving) in weights.values_mut().into_iter().zip(moving.values_mut()) {
+                    *weight = (*weight * to_set + *moving * (stones - to_set)) / stones;
+                }
+                weights
+            },
+            Phase::Moving | Phase::Endgame => self.moving,
+            Phase::Flying => self.flying,
+        }
+    }
+}
+
+// `name=value` pairs separated by commas like `EvalWeights`, a name set for
+// every phase or prefixed by one of `PHASES` for that phase alone, e.g.
+// `mills=30,setting.open_twos=15`. The weights left out keep their default.
+impl FromStr for PhaseWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = PhaseWeights::default();
+        for pair in s.split(',').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').ok_or_else(|| format!("`{pair}` is not name=value"))?;
+            let (phases, weight) = match name.split_once('.') {
+                Some((phase, weight)) => {
+                    let i = PhaseWeights::PHASES
+                        .iter()
+                        .position(|&known| known == phase)
+                        .ok_or_else(|| format!("unknown phase `{phase}`, expected one of {}", PhaseWeights::PHASES.join(", ")))?;
+                    (i..i + 1, weight)
+                },
+                None => (0..PhaseWeights::PHASES.len(), name),
+            };
+            let i = EvalWeights::NAMES
+                .iter()
+                .position(|&known| known == weight)
+                .ok_or_else(|| format!("unknown weight `{weight}`, expected one of {}", EvalWeights::NAMES.join(", ")))?;
+            let value: i32 = value.parse().map_err(|e| format!("weight `{name}`: {e}"))?;
+            let mut all = [&mut weights.setting, &mut weights.moving, &mut weights.flying];
+            for phase in &mut all[phases] {
+                *phase.values_mut()[i] = value;
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for PhaseWeights {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut weights = *self;
+        for (i, (name, value)) in PhaseWeights::names().iter().zip(weights.values_mut()).enumerate() {
+            write!(formatter, "{}{name}={value}", if i > 0 { "," } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+// white's advantage in stones, with the weights of the state's phase
+pub fn evaluate(state: &MicaState, weights: &PhaseWeights) -> i32 {
+    if *weights == PhaseWeights::MATERIAL {
+        return state.material();
+    }
+    evaluate_with(state, &weights.at(state))
+}
+
+// White's advantage in hundredths of a stone before rounding, so every
+// change of a weight shows, for tuning the weights, see `evaluate`.
+pub fn evaluate_hundredths(state: &MicaState, weights: &PhaseWeights) -> i32 {
+    100 * state.material() + terms(state, &weights.at(state))
+}
+
+// white's advantage in stones with `weights` whatever the phase
+pub fn evaluate_with(state: &MicaState, weights: &EvalWeights) -> i32 {
+    let material = state.material();
+    if *weights == EvalWeights::MATERIAL {
+        return material;
+    }
+    material + terms(state, weights) / 100
+}
+
+// every term for white less black's, in hundredths of a stone
+fn terms(state: &MicaState, weights: &EvalWeights) -> i32 {
+    let (white, black) = state.occupancy();
+    let empty = !(white | black);
+    let mut terms = lines(white, black, weights) - lines(black, white, weights);
+    if state.stones_to_set() == (0, 0) {
+        terms += weights.mobility * (mobility(white, empty) - mobility(black, empty));
+        terms += weights.windmills * (windmills(white, empty) - windmills(black, empty));
+    }
+    let flies = |stones: u32| state.rules.flying && stones.count_ones() == 3;
+    let blocked = |stones: u32| if flies(stones) { 0 } else { blocked(stones, empty) };
+    terms += weights.blocked * (blocked(black) - blocked(white));
+    terms
+}
+
+// the mill terms for the side with `mine` against the side with `theirs`
+fn lines(mine: u32, theirs: u32, weights: &EvalWeights) -> i32 {
+    MILLS
+        .iter()
+        .map(|&mill| match ((mill & mine).count_ones(), (mill & theirs).count_ones()) {
+            (3, _) => weights.mills,
+            (2, 0) => weights.open_twos,
+            (1, 2) => weights.blocked_twos,
+            _ => 0,
+        })
+        .sum()
+}
+
+// Stones of `mine` in a closed mill that close another mill, one without
+// the point they leave, with a step to an empty neighbour.
+fn windmills(mine: u32, empty: u32) -> i32 {
+    let closes = |point: usize, to: usize| POINT_MILLS[to].iter().any(|&mill| mill & 1 << point == 0 && mill & !(1 << to) & !mine == 0);
+    bits(mine)
+        .filter(|&point| POINT_MILLS[point].iter().any(|&mill| mill & !mine == 0))
+        .filter(|&point| bits(NEIGHBOURS[point] & empty).any(|to| closes(point, to)))
+        .count() as i32
+}
+
+// stones in `stones` without an empty neighbour
+fn blocked(stones: u32, empty: u32) -> i32 {
+    bits(stones).filter(|&point| NEIGHBOURS[point] & empty == 0).count() as i32
+}
+
+// step moves of the stones in `stones`, removals after a mill are not counted
+fn mobility(stones: u32, empty: u32) -> i32 {
+    bits(stones).map(|point| (NEIGHBOURS[point] & empty).count_ones() as i32).sum()
+}
@@ -0,0 +1,25 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use mica::dataset;
+use mica::selfplay::GameRecord;
+
+use crate::option;
+
+// Converts a games file written by `mica train` into training tensors.
+//
+//     mica export [--games FILE] [--out FILE]
+pub fn run(args: &[String]) {
+    let games_path: String = option(args, "--games", "training/games.jsonl".to_string());
+    let out_path: String = option(args, "--out", "training/dataset.npz".to_string());
+
+    let records: Vec<GameRecord> = fs::read_to_string(&games_path)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let out = BufWriter::new(File::create(&out_path).unwrap());
+    dataset::write_npz(&records, out).unwrap();
+    eprintln!("Exported {} games to {out_path}", records.len());
+}
@@ -0,0 +1,49 @@
+use crate::minimax::*;
+use crate::notation::{parse_position, PositionFormat};
+use crate::rng::Rng;
+
+// Reference positions for benchmarks, one per stage of the game, in the fen
+// format of `notation`.
+
+pub const OPENING: &str = "......../......../........ w 9 9";
+pub const SETTING: &str = "W.B..W../.B..W.../B..B...W w 5 5";
+pub const MOVING: &str = "WB.W.B.B/.W.B.W../W..B...W w 0 0";
+pub const ENDGAME: &str = "W..W.B../.....B../W.....B. w 0 0";
+
+pub fn opening() -> MicaState {
+    parse_position(PositionFormat::Fen, OPENING).unwrap()
+}
+
+pub fn setting() -> MicaState {
+    parse_position(PositionFormat::Fen, SETTING).unwrap()
+}
+
+pub fn moving() -> MicaState {
+    parse_position(PositionFormat::Fen, MOVING).unwrap()
+}
+
+pub fn endgame() -> MicaState {
+    parse_position(PositionFormat::Fen, ENDGAME).unwrap()
+}
+
+pub fn all() -> [(&'static str, MicaState); 4] {
+    [("opening", opening()), ("setting", setting()), ("moving", moving()), ("endgame", endgame())]
+}
+
+// position after up to `plies` random legal moves from `seed`, stopping early
+// rather than entering a finished position, so every generated state has moves
+pub fn random(seed: u64, plies: usize) -> MicaState {
+    let mut rng = Rng::new(seed);
+    let mut state = MicaState::new();
+    for _ in 0..plies {
+        let moves = state.get_moves();
+        let mut next = state.clone();
+        next.apply_move(moves[rng.below(moves.len())]);
+        next.current_player.toggle();
+        if next.is_end() || next.get_moves().is_empty() {
+            break;
+        }
+        state = next;
+    }
+    state
+}
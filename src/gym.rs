@@ -0,0 +1,143 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::coords::{to_index, POINTS};
+use crate::minimax::*;
+
+// 24 board points followed by the side to move and both players' stones to set
+pub type Observation = [i8; 27];
+
+// actions are encoded as (from, to, remove) over board point indices, where
+// `from` and `remove` use the extra index 24 when the move has no such part
+const NO_POINT: usize = POINTS.len();
+pub const ACTION_COUNT: usize = (POINTS.len() + 1) * POINTS.len() * (POINTS.len() + 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalAction(pub usize);
+
+fn point_index(x: u8, y: u8, z: u8) -> usize {
+    to_index(x, y, z).unwrap()
+}
+
+fn action_index(from: usize, to: usize, remove: usize) -> usize {
+    (from * POINTS.len() + to) * (POINTS.len() + 1) + remove
+}
+
+pub fn encode_action(mica_move: MicaMove) -> usize {
+    match mica_move {
+        MicaMove::Set { x, y, z } => action_index(NO_POINT, point_index(x, y, z), NO_POINT),
+        MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } => {
+            action_index(point_index(from_x, from_y, from_z), point_index(to_x, to_y, to_z), NO_POINT)
+        },
+        MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z } => {
+            action_index(NO_POINT, point_index(x, y, z), point_index(remove_x, remove_y, remove_z))
+        },
+        MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z } => {
+            action_index(point_index(from_x, from_y, from_z), point_index(to_x, to_y, to_z), point_index(remove_x, remove_y, remove_z))
+        },
+    }
+}
+
+pub fn decode_action(action: usize) -> Option<MicaMove> {
+    if action >= ACTION_COUNT {
+        return None;
+    }
+
+    let remove = action % (POINTS.len() + 1);
+    let to = action / (POINTS.len() + 1) % POINTS.len();
+    let from = action / (POINTS.len() + 1) / POINTS.len();
+    let (x, y, z) = POINTS[to];
+
+    let mica_move = match (from, remove) {
+        (NO_POINT, NO_POINT) => MicaMove::Set { x, y, z },
+        (NO_POINT, remove) => {
+            let (remove_x, remove_y, remove_z) = POINTS[remove];
+            MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z }
+        },
+        (from, NO_POINT) => {
+            let (from_x, from_y, from_z) = POINTS[from];
+            MicaMove::Move { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z }
+        },
+        (from, remove) => {
+            let (from_x, from_y, from_z) = POINTS[from];
+            let (remove_x, remove_y, remove_z) = POINTS[remove];
+            MicaMove::MoveRemove { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z, remove_x, remove_y, remove_z }
+        },
+    };
+
+    Some(mica_move)
+}
+
+pub struct Env {
+    state: MicaState,
+    done: bool,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env {
+            state: MicaState::new(),
+            done: false,
+        }
+    }
+
+    pub fn state(&self) -> &MicaState {
+        &self.state
+    }
+
+    pub fn reset(&mut self) -> Observation {
+        self.state = MicaState::new();
+        self.done = false;
+        self.observation()
+    }
+
+    pub fn observation(&self) -> Observation {
+        let mut obs = [0; 27];
+        for (i, &(x, y, z)) in POINTS.iter().enumerate() {
+            obs[i] = self.state.stone(x, y, z) as i8;
+        }
+        let (white_to_set, black_to_set) = self.state.stones_to_set();
+        obs[24] = self.state.current_player as i8;
+        obs[25] = white_to_set as i8;
+        obs[26] = black_to_set as i8;
+        obs
+    }
+
+    pub fn legal_actions(&self) -> Vec<usize> {
+        if self.done {
+            return Vec::new();
+        }
+        self.state.get_moves().into_iter().map(encode_action).collect()
+    }
+
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; ACTION_COUNT];
+        for action in self.legal_actions() {
+            mask[action] = true;
+        }
+        mask
+    }
+
+    // reward is from the perspective of the player who made the move
+    pub fn step(&mut self, action: usize) -> Result<(Observation, f32, bool), IllegalAction> {
+        let mica_move = decode_action(action).ok_or(IllegalAction(action))?;
+        if self.done {
+            return Err(IllegalAction(action));
+        }
+
+        self.state.checked_apply_move(mica_move).map_err(|_| IllegalAction(action))?;
+        self.state.current_player.toggle();
+
+        // the side to move loses when it is down to two stones or cannot move
+        self.done = self.state.is_end() || self.state.get_moves().is_empty();
+        let reward = if self.done { 1.0 } else { 0.0 };
+
+        Ok((self.observation(), reward, self.done))
+    }
+}
@@ -0,0 +1,61 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::coords::to_index;
+use crate::minimax::{MicaMove, MicaPlayer};
+
+const SLOTS: usize = 2 * 24 * 24;
+
+// History heuristic for ordering quiet moves. Every quiet move that cuts off
+// a search gets credit by (player, from, to), more the deeper the search
+// below it, and quiet moves with more credit are tried first elsewhere in
+// the tree.
+//
+// Only stone moves are scored. Crediting placements by their point made the
+// setting phase searches bigger, so those keep generation order.
+//
+// The table is empty until the first cutoff, so cloning a state that never
+// searched stays cheap.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    scores: Vec<u32>,
+}
+
+fn slot(player: MicaPlayer, mica_move: MicaMove) -> Option<usize> {
+    let MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } = mica_move else {
+        return None;
+    };
+    let from = to_index(from_x, from_y, from_z)?;
+    let to = to_index(to_x, to_y, to_z)?;
+    let player = match player {
+        MicaPlayer::White => 0,
+        MicaPlayer::Black => 1,
+        MicaPlayer::None => return None,
+    };
+    Some((player * 24 + from) * 24 + to)
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    pub fn record_cutoff(&mut self, player: MicaPlayer, mica_move: MicaMove, depth: u8) {
+        let Some(slot) = slot(player, mica_move) else {
+            return;
+        };
+        if self.scores.is_empty() {
+            self.scores = vec![0; SLOTS];
+        }
+        let score = &mut self.scores[slot];
+        *score = score.saturating_add(depth as u32 * depth as u32);
+    }
+
+    pub fn score(&self, player: MicaPlayer, mica_move: MicaMove) -> u32 {
+        slot(player, mica_move).and_then(|slot| self.scores.get(slot).copied()).unwrap_or(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.scores = Vec::new();
+    }
+}
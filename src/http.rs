@@ -0,0 +1,342 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use mica::api::{ErrorBody, Latency};
+
+// requests with larger bodies are rejected before the body is read, chunked
+// ones as soon as their chunks add up to more
+pub const MAX_BODY: usize = 1 << 20;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // path split on `/` without empty segments, so `/analysis/3` is ["analysis", "3"]
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('?').next().unwrap().split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    // the parts of a `multipart/form-data` body, None for other content types
+    pub fn form_parts(&self) -> Option<Result<Vec<Part>, String>> {
+        let content_type = self.header("content-type")?;
+        let (mime, params) = content_type.split_once(';').unwrap_or((content_type, ""));
+        if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+        let boundary = params
+            .split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value.trim_matches('"'));
+        Some(match boundary {
+            Some(boundary) if !boundary.is_empty() => parse_form(&self.body, boundary),
+            _ => Err("multipart body without a boundary".to_string()),
+        })
+    }
+}
+
+// one field of a `multipart/form-data` body, uploaded files have a filename
+pub struct Part {
+    pub filename: Option<String>,
+    pub body: String,
+}
+
+fn parse_form(body: &str, boundary: &str) -> Result<Vec<Part>, String> {
+    let delimiter = format!("--{boundary}");
+    let mut sections = body.split(delimiter.as_str());
+    // anything before the first delimiter is preamble
+    sections.next();
+
+    let mut parts = Vec::new();
+    for section in sections {
+        // the closing delimiter has two more dashes, anything after it is epilogue
+        if section.starts_with("--") {
+            return Ok(parts);
+        }
+        let section = section.strip_prefix("\r\n").ok_or("malformed multipart delimiter")?;
+        let (head, body) = section.split_once("\r\n\r\n").ok_or("multipart part without a header")?;
+        let body = body.strip_suffix("\r\n").ok_or("multipart part not followed by a delimiter")?;
+
+        let mut filename = None;
+        let mut named = false;
+        for line in head.split("\r\n") {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            if !key.trim().eq_ignore_ascii_case("content-disposition") {
+                continue;
+            }
+            for param in value.split(';').skip(1) {
+                match param.trim().split_once('=') {
+                    Some(("name", _)) => named = true,
+                    Some(("filename", value)) => filename = Some(value.trim_matches('"').to_string()),
+                    _ => (),
+                }
+            }
+        }
+        if !named {
+            return Err("multipart part without a name".to_string());
+        }
+        parts.push(Part { filename, body: body.to_string() });
+    }
+    Err("multipart body without a closing delimiter".to_string())
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+    // streamed responses send every line received here as its own chunk, `body` is unused
+    pub chunks: Option<Receiver<String>>,
+    // where the time went, for answers to move requests
+    pub latency: Option<Latency>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Response { status, content_type: "application/json", body: body.to_string(), chunks: None, latency: None }
+    }
+
+    // a static file, the pages of the web UI
+    #[cfg(feature = "web-ui")]
+    pub fn file(content_type: &'static str, body: &str) -> Self {
+        Response { status: 200, content_type, body: body.to_string(), chunks: None, latency: None }
+    }
+
+    pub fn stream(status: u16, chunks: Receiver<String>) -> Self {
+        Response { status, content_type: "application/x-ndjson", body: String::new(), chunks: Some(chunks), latency: None }
+    }
+
+    pub fn error(status: u16, error: &str, details: Vec<String>) -> Self {
+        Response::json(status, serde_json::to_value(ErrorBody { error: error.to_string(), details }).unwrap())
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::FileTooLarge, format!("request body larger than {MAX_BODY} bytes"))
+}
+
+// the status a request `read_request` failed on is answered with
+pub fn error_status(error: &io::Error) -> u16 {
+    match error.kind() {
+        io::ErrorKind::FileTooLarge => 413,
+        _ => 400,
+    }
+}
+
+pub fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| invalid("empty request"))?.to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let header = |name: &str| headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+    let body = if header("transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")) {
+        read_chunked(&mut reader)?
+    } else {
+        let length = header("content-length").and_then(|value| value.parse::<usize>().ok()).unwrap_or(0);
+        if length > MAX_BODY {
+            return Err(too_large());
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+// a `Transfer-Encoding: chunked` body, given up on once it passes MAX_BODY
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line)?;
+        // chunk extensions after `;` carry nothing we use
+        let size = size_line.split(';').next().unwrap().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid("malformed chunk size"))?;
+        if size == 0 {
+            break;
+        }
+        if body.len().checked_add(size).is_none_or(|length| length > MAX_BODY) {
+            return Err(too_large());
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut line_end = String::new();
+        reader.read_line(&mut line_end)?;
+    }
+    // trailer fields up to the empty line
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    Ok(body)
+}
+
+pub fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let status = response.status;
+    if let Some(chunks) = &response.chunks {
+        let head = format!(
+            "HTTP/1.1 {status} {}\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            reason(status),
+            response.content_type
+        );
+        stream.write_all(head.as_bytes())?;
+        for line in chunks {
+            stream.write_all(format!("{:x}\r\n{line}\n\r\n", line.len() + 1).as_bytes())?;
+            stream.flush()?;
+        }
+        return stream.write_all(b"0\r\n\r\n");
+    }
+
+    let length = response.body.len();
+    let head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {}\r\nContent-Length: {length}\r\nConnection: close\r\n\r\n",
+        reason(status),
+        response.content_type
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())
+}
+
+// splits `http://host[:port]/path` into the address to connect to, the host and the path
+pub fn parse_url(url: &str) -> Option<(String, String, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return None;
+    }
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{authority}:80") };
+    Some((address, authority.to_string(), path.to_string()))
+}
+
+// POSTs a JSON body and returns the response status code
+pub fn post_json(url: &str, body: &str) -> io::Result<u16> {
+    let (address, host, path) = parse_url(url).ok_or_else(|| invalid("only http:// urls are supported"))?;
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("malformed response status line"))
+}
+
+// sends one request to `address` and returns the status code and body of the response
+pub fn send(address: &str, method: &str, path: &str, body: &str) -> io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(30)))?;
+
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes())?;
+    read_response(&stream)
+}
+
+// reads a whole `Connection: close` response
+pub fn read_response(stream: &TcpStream) -> io::Result<(u16, String)> {
+    let mut response = String::new();
+    BufReader::new(stream).read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("malformed response status line"))?;
+    Ok((status, body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_bodies_past_the_limit_are_too_large() {
+        let body = read_chunked(&mut "3\r\nabc\r\n2;ext\r\nde\r\n0\r\n\r\n".as_bytes()).unwrap();
+        assert_eq!(body, b"abcde");
+
+        let huge = format!("3\r\nabc\r\n{:x}\r\n", usize::MAX);
+        let error = read_chunked(&mut huge.as_bytes()).unwrap_err();
+        assert_eq!(error_status(&error), 413);
+
+        let error = read_chunked(&mut "zz\r\n".as_bytes()).unwrap_err();
+        assert_eq!(error_status(&error), 400);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+}
+
+struct Job {
+    status: JobStatus,
+    result: Option<Value>,
+    cancelled: Arc<AtomicBool>,
+    // the request body, kept so unfinished jobs can be replayed after a restart
+    request: String,
+    // search details and when they expire, for jobs that asked for them
+    details: Option<(Value, Instant)>,
+}
+
+fn job_bytes(job: &Job) -> usize {
+    mem::size_of::<Job>()
+        + job.request.len()
+        + job.result.as_ref().map_or(0, |result| result.to_string().len())
+        + job.details.as_ref().map_or(0, |(details, _)| details.to_string().len())
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingJob {
+    id: u64,
+    request: String,
+}
+
+// Asynchronous analysis jobs, keyed by the id handed out when they were submitted.
+// Jobs that have not finished are mirrored to `path` so a restart can pick them up.
+pub struct Jobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, Job>>,
+    path: Option<PathBuf>,
+}
+
+impl Jobs {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Jobs {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(HashMap::new()),
+            path,
+        }
+    }
+
+    // loads the jobs left unfinished by the previous run, they are queued again
+    pub fn restore(path: PathBuf) -> Self {
+        let pending: Vec<PendingJob> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        let jobs = Jobs::new(Some(path));
+        let next_id = pending.iter().map(|job| job.id + 1).max().unwrap_or(1);
+        jobs.next_id.store(next_id, Ordering::Relaxed);
+        {
+            let mut map = jobs.jobs.lock().unwrap();
+            for PendingJob { id, request } in pending {
+                map.insert(id, Job {
+                    status: JobStatus::Queued,
+                    result: None,
+                    cancelled: Arc::new(AtomicBool::new(false)),
+                    request,
+                    details: None,
+                });
+            }
+        }
+        jobs
+    }
+
+    // queued jobs with their request bodies and cancellation flags
+    pub fn queued(&self) -> Vec<(u64, String, Arc<AtomicBool>)> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut queued: Vec<_> = jobs
+            .iter()
+            .filter(|(_, job)| job.status == JobStatus::Queued)
+            .map(|(&id, job)| (id, job.request.clone(), Arc::clone(&job.cancelled)))
+            .collect();
+        queued.sort_by_key(|&(id, _, _)| id);
+        queued
+    }
+
+    fn persist(&self, jobs: &HashMap<u64, Job>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut pending: Vec<PendingJob> = jobs
+            .iter()
+            .filter(|(_, job)| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+            .map(|(&id, job)| PendingJob { id, request: job.request.clone() })
+            .collect();
+        pending.sort_by_key(|job| job.id);
+
+        // write to a temporary file first so a crash never leaves a torn file behind
+        let tmp = path.with_extension("tmp");
+        let written = fs::write(&tmp, serde_json::to_string(&pending).unwrap()).and_then(|_| fs::rename(&tmp, path));
+        if let Err(e) = written {
+            eprintln!("Failed to persist jobs to {}: {e}", path.display());
+        }
+    }
+
+    // registers a queued job, the flag is raised when the job gets cancelled
+    pub fn create(&self, request: String) -> (u64, Arc<AtomicBool>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let job = Job {
+            status: JobStatus::Queued,
+            result: None,
+            cancelled: Arc::clone(&cancelled),
+            request,
+            details: None,
+        };
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.insert(id, job);
+        self.persist(&jobs);
+        (id, cancelled)
+    }
+
+    pub fn start(&self, id: u64) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            if job.status == JobStatus::Queued {
+                job.status = JobStatus::Running;
+            }
+        }
+    }
+
+    pub fn finish(&self, id: u64, result: Value) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(&id) {
+            if job.status != JobStatus::Cancelled {
+                job.status = JobStatus::Done;
+                job.result = Some(result);
+            }
+        }
+        self.persist(&jobs);
+    }
+
+    pub fn keep_details(&self, id: u64, details: Value, ttl: Duration) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.details = Some((details, Instant::now() + ttl));
+        }
+    }
+
+    // the job's search details, dropped once they have expired
+    pub fn details(&self, id: u64) -> Option<Value> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id)?;
+        if job.details.as_ref().is_some_and(|(_, expires)| Instant::now() >= *expires) {
+            job.details = None;
+        }
+        job.details.as_ref().map(|(details, _)| details.clone())
+    }
+
+    pub fn cancel(&self, id: u64) -> Option<JobStatus> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id)?;
+        if job.status != JobStatus::Done {
+            job.status = JobStatus::Cancelled;
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        let status = job.status;
+        self.persist(&jobs);
+        Some(status)
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.lock().unwrap().len()
+    }
+
+    // rough size of the stored requests and results
+    pub fn memory_bytes(&self) -> usize {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.values().map(job_bytes).sum()
+    }
+
+    // drops finished and cancelled jobs, oldest first, until at most `max_bytes` remain
+    pub fn evict(&self, max_bytes: usize) -> usize {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut total: usize = jobs.values().map(job_bytes).sum();
+        let mut finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| matches!(job.status, JobStatus::Done | JobStatus::Cancelled))
+            .map(|(&id, _)| id)
+            .collect();
+        finished.sort();
+
+        let mut evicted = 0;
+        for id in finished {
+            if total <= max_bytes {
+                break;
+            }
+            if let Some(job) = jobs.remove(&id) {
+                total -= job_bytes(&job);
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    pub fn get(&self, id: u64) -> Option<Value> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&id)?;
+        Some(json!({ "id": id, "status": job.status, "result": job.result }))
+    }
+}
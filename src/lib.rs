@@ -0,0 +1,55 @@
+// Without the `std` feature only the rules and the search are built: moves,
+// evaluation, hashing and the transposition table, which need nothing but
+// an allocator.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod agents;
+#[cfg(feature = "std")]
+pub mod api;
+#[cfg(feature = "std")]
+pub mod book;
+pub mod bump;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "std")]
+pub mod clock;
+pub mod coords;
+#[cfg(feature = "std")]
+pub mod corpus;
+#[cfg(feature = "std")]
+pub mod dataset;
+#[cfg(feature = "std")]
+pub mod diff;
+pub mod difficulty;
+pub mod eval;
+#[cfg(feature = "std")]
+pub mod fixtures;
+pub mod gym;
+pub mod history;
+pub mod minimax;
+#[cfg(feature = "std")]
+pub mod notation;
+#[cfg(feature = "nn")]
+pub mod nn;
+pub mod packed;
+pub mod personality;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod rng;
+pub mod search;
+#[cfg(feature = "std")]
+pub mod selfplay;
+pub mod symmetry;
+pub mod tablebase;
+pub mod tt;
+pub mod tuned;
+#[cfg(feature = "std")]
+pub mod validation;
+pub mod variants;
+pub mod zobrist;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,161 @@
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use mica::minimax::*;
+use mica::pool::{Pool, TaskScope};
+use mica::search::SearchLimits;
+use mica::tt::TranspositionTable;
+
+use crate::{get_best_move, MicaBestMove};
+
+// Line based bridge for driving the engine as an external Ludii agent.
+//
+// The Ludii-side agent sends one line per decision:
+//
+//     state <sites> <mover> <p1_in_hand> <p2_in_hand>
+//
+// where <sites> is a 24 character string of `1`, `2` or `.` indexed by site,
+// and <mover> is the Ludii player id (1 is white, 2 is black). The bridge
+// answers with:
+//
+//     move <from> <to> <remove>
+//
+// using `-` for the parts the move does not have, or `pass` when there is no
+// legal move. Sites are Ludii's own site ids of its Nine Men's Morris board,
+// see `SITES`, so the Ludii-side agent passes them through unchanged.
+
+// The point of every Ludii site id. Ludii numbers the points of the board row
+// by row from the bottom, left to right in each row, so site 0 is a1 and
+// site 23 is g7.
+const SITES: [(u8, u8, u8); 24] = [
+    (0, 2, 0), (0, 2, 1), (0, 2, 2),
+    (1, 2, 0), (1, 2, 1), (1, 2, 2),
+    (2, 2, 0), (2, 2, 1), (2, 2, 2),
+    (0, 1, 0), (1, 1, 0), (2, 1, 0), (2, 1, 2), (1, 1, 2), (0, 1, 2),
+    (2, 0, 0), (2, 0, 1), (2, 0, 2),
+    (1, 0, 0), (1, 0, 1), (1, 0, 2),
+    (0, 0, 0), (0, 0, 1), (0, 0, 2),
+];
+
+fn site_of(x: u8, y: u8, z: u8) -> usize {
+    SITES.iter().position(|&point| point == (x, y, z)).unwrap()
+}
+
+fn parse_player(token: &str) -> Option<MicaPlayer> {
+    match token {
+        "1" => Some(MicaPlayer::White),
+        "2" => Some(MicaPlayer::Black),
+        _ => None,
+    }
+}
+
+fn parse_state(line: &str) -> Result<MicaState, String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 5 || parts[0] != "state" {
+        return Err(format!("expected `state <sites> <mover> <p1_in_hand> <p2_in_hand>`, got `{line}`"));
+    }
+
+    let sites = parts[1].as_bytes();
+    if sites.len() != SITES.len() {
+        return Err(format!("expected {} sites, got {}", SITES.len(), sites.len()));
+    }
+
+    let mut state = MicaState::new();
+    for (site, &(x, y, z)) in SITES.iter().enumerate() {
+        let player = match sites[site] {
+            b'1' => MicaPlayer::White,
+            b'2' => MicaPlayer::Black,
+            b'.' => MicaPlayer::None,
+            c => return Err(format!("invalid site value `{}` at site {site}", c as char)),
+        };
+        state.put_stone(x, y, z, player);
+    }
+
+    state.current_player = parse_player(parts[2]).ok_or(format!("invalid mover `{}`", parts[2]))?;
+    let white_to_set = parts[3].parse().map_err(|_| format!("invalid p1 stones in hand `{}`", parts[3]))?;
+    let black_to_set = parts[4].parse().map_err(|_| format!("invalid p2 stones in hand `{}`", parts[4]))?;
+    state.set_stones_to_set(white_to_set, black_to_set);
+
+    Ok(state)
+}
+
+fn format_move(mica_move: Option<MicaMove>) -> String {
+    let (from, to, remove) = match mica_move {
+        None => return "pass".to_string(),
+        Some(MicaMove::Set { x, y, z }) => (None, site_of(x, y, z), None),
+        Some(MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z }) => {
+            (Some(site_of(from_x, from_y, from_z)), site_of(to_x, to_y, to_z), None)
+        },
+        Some(MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z }) => {
+            (None, site_of(x, y, z), Some(site_of(remove_x, remove_y, remove_z)))
+        },
+        Some(MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z }) => {
+            (Some(site_of(from_x, from_y, from_z)), site_of(to_x, to_y, to_z), Some(site_of(remove_x, remove_y, remove_z)))
+        },
+    };
+
+    let site = |s: Option<usize>| s.map_or("-".to_string(), |s| s.to_string());
+    format!("move {} {to} {}", site(from), site(remove))
+}
+
+pub fn run(pool: Arc<Pool<MicaBestMove>>) {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let tt = Arc::new(TranspositionTable::new(64));
+
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to read from Ludii: {e}");
+                break;
+            },
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "quit" {
+            break;
+        }
+
+        let reply = match parse_state(line) {
+            Ok(mut state) => {
+                state.tt = Some(Arc::clone(&tt));
+                format_move(get_best_move(state, &pool, &TaskScope::new(), &SearchLimits::default(), Some(0), 1, false).best_move)
+            },
+            Err(e) => format!("error {e}"),
+        };
+        writeln!(stdout, "{reply}").unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mica::coords::{to_notation, POINTS};
+
+    use super::*;
+
+    #[test]
+    fn sites_count_rows_from_the_bottom() {
+        let notations: Vec<String> = SITES.iter().map(|&(x, y, z)| to_notation(x, y, z).unwrap()).collect();
+        assert_eq!(notations.join(" "), "a1 d1 g1 b2 d2 f2 c3 d3 e3 a4 b4 c4 e4 f4 g4 c5 d5 e5 b6 d6 f6 a7 d7 g7");
+        for (site, &(x, y, z)) in SITES.iter().enumerate() {
+            assert_eq!(site_of(x, y, z), site);
+        }
+        assert!(POINTS.iter().all(|point| SITES.contains(point)));
+    }
+
+    #[test]
+    fn moves_are_written_with_site_ids() {
+        let mica_move = MicaMove::MoveRemove { from_x: 0, from_y: 2, from_z: 0, to_x: 0, to_y: 1, to_z: 0, remove_x: 0, remove_y: 0, remove_z: 2 };
+        assert_eq!(format_move(Some(mica_move)), "move 0 9 23");
+        assert_eq!(format_move(Some(MicaMove::Set { x: 2, y: 1, z: 2 })), "move - 12 -");
+        assert_eq!(format_move(None), "pass");
+
+        let state = parse_state(&format!("state 1{}2 2 8 8", ".".repeat(22))).unwrap();
+        assert_eq!(state.stone(0, 2, 0), MicaPlayer::White);
+        assert_eq!(state.stone(0, 0, 2), MicaPlayer::Black);
+        assert_eq!(state.current_player, MicaPlayer::Black);
+    }
+}
@@ -1,113 +1,489 @@
-use std::io::prelude::*;
-use std::net::{TcpListener, TcpStream};
-use std::sync::mpsc::Receiver;
-use std::sync::Arc;
-use pool::{MicaTask, Pool};
-use serde_json::json;
+use std::cell::RefCell;
+use std::env;
+use std::fmt;
+use std::mem;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use mica::bump::BumpArena;
+use mica::minimax::*;
+use mica::pool::{MicaTask, Pool, TaskScope};
+use mica::rng::Rng;
+use mica::search::{Engine, Iteration, RootMove, SearchLimits, SearchResult, SearchStats};
+use mica::tt::TranspositionTable;
 
-mod minimax;
-mod pool;
+mod analyze;
+mod arena;
+mod audit;
+mod convert;
+mod endgame;
+mod export;
+mod http;
+mod jobs;
+mod ludii;
+#[cfg(feature = "notes")]
+mod notes;
+mod opening;
+mod protocol;
+mod response;
+mod selfcheck;
+mod server;
+mod sessions;
+mod train;
+mod tune;
+mod usage;
+mod verify;
+#[cfg(feature = "web-ui")]
+mod web;
 
-use minimax::*;
+// threads of the pool searches run on
+const SEARCH_THREADS: usize = 8;
 
-type MicaBestMove = i32;
+// score of a root move, whether it is exact, what the search for it did and
+// the line expected after it, None when the move was skipped
+type MicaBestMove = Option<(i32, bool, SearchStats, Vec<MicaMove>)>;
 
-fn get_best_move(mica_request: MicaRequest, pool: Arc<Pool<MicaBestMove>>, rx: Arc<Receiver<MicaBestMove>>) -> Option<MicaMove> {
-    // Arc::clone(&pool).submit(|| 0);
-    let game = MicaState::from_request(mica_request);
-    let moves = game.get_moves();
+// what one search may still spend, shared by all of its root tasks
+struct RootBudget {
+    // the root tasks are its tasks, cancelled with it
+    scope: TaskScope,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    searched_nodes: Arc<AtomicU64>,
+    // root moves more than this much worse than the `multi_pv`th best one so
+    // far only get an upper bound, None searches every root move with the
+    // full window
+    margin: Option<i32>,
+    multi_pv: usize,
+    // root moves are searched one after the other on the calling thread
+    deterministic: bool,
+}
+
+thread_local! {
+    // move lists and best lines of the root tasks this thread runs
+    static WORKER_ARENA: RefCell<BumpArena> = RefCell::new(BumpArena::new());
+}
+
+// Runs `search` on `state` with the arena of this thread, emptied of what
+// the task before it left, so root tasks reuse their worker's buffers.
+fn with_worker_arena<R>(state: &mut MicaState, search: impl FnOnce(&mut MicaState) -> R) -> R {
+    WORKER_ARENA.with(|arena| mem::swap(&mut state.arena, &mut arena.borrow_mut()));
+    state.arena.reset();
+    let result = search(state);
+    WORKER_ARENA.with(|arena| mem::swap(&mut state.arena, &mut arena.borrow_mut()));
+    result
+}
+
+// The best root scores so far, as many as there are lines to report.
+struct TopScores {
+    scores: Mutex<Vec<i32>>,
+    count: usize,
+}
+
+impl TopScores {
+    fn new(count: usize) -> Self {
+        TopScores { scores: Mutex::new(Vec::with_capacity(count + 1)), count }
+    }
+
+    // the score a move has to beat to be one of the best, -i32::MAX while there are too few
+    fn floor(&self) -> i32 {
+        let scores = self.scores.lock().unwrap();
+        if scores.len() < self.count {
+            -i32::MAX
+        } else {
+            scores[self.count - 1]
+        }
+    }
+
+    fn insert(&self, score: i32) {
+        let mut scores = self.scores.lock().unwrap();
+        let i = scores.partition_point(|&kept| kept >= score);
+        scores.insert(i, score);
+        scores.truncate(self.count);
+    }
+}
+
+// Searches every root move in its own pool task, in the order given, to
+// `depth` plies from the root: the position after it `depth - 1` deep, as
+// `MicaState::iterative_deepening` does. A task that has not started when the
+// search is cancelled or out of nodes is skipped, one that runs past the
+// deadline gives up; neither gets a score. With a margin the tasks share the
+// best scores found so far and only look for moves within the margin of the
+// `multi_pv`th best one. Also returns how long the first task waited for a
+// thread, a busy pool shows as a long wait, and what the tasks did between
+// them. A deterministic budget runs the tasks in order on the calling thread
+// instead, so every root move sees the same table and best scores on every
+// run.
+fn search_roots(game: &MicaState, moves: &[MicaMove], depth: u8, pool: &Arc<Pool<MicaBestMove>>, budget: &RootBudget) -> (Vec<RootMove>, Duration, SearchStats) {
+    let side = game.current_player as i32;
+    // for the side to move at the root
+    let best = Arc::new(TopScores::new(budget.multi_pv));
+    let submitted = Instant::now();
+    // microseconds after `submitted`
+    let first_started = Arc::new(AtomicU64::new(u64::MAX));
+    let mut results = Vec::with_capacity(moves.len());
     for &next_move in moves.iter() {
         let mut game_clone = game.clone();
         game_clone.apply_move(next_move);
         game_clone.current_player.toggle();
+        // won games are counted from the root, a ply up
+        game_clone.set_ply(1);
+        game_clone.deadline = budget.deadline;
+        game_clone.cancel = Some(Arc::clone(budget.scope.cancelled()));
+        let cancelled = Arc::clone(budget.scope.cancelled());
+        let searched_nodes = Arc::clone(&budget.searched_nodes);
+        let (deadline, max_nodes, margin) = (budget.deadline, budget.max_nodes, budget.margin);
+        let best = Arc::clone(&best);
+        let first_started = Arc::clone(&first_started);
         let task: MicaTask<MicaBestMove> = Box::new(move || {
-            let (value, _) = game_clone.minimax(6, i32::MIN, i32::MAX);
-            println!("Thread got value {value}");
-            value
+            first_started.fetch_min(submitted.elapsed().as_micros() as u64, Ordering::Relaxed);
+            // a cancelled search has nobody waiting for it, an exhausted one has no budget left
+            let out_of_time = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let out_of_nodes = max_nodes.is_some_and(|max_nodes| searched_nodes.load(Ordering::Relaxed) >= max_nodes);
+            if cancelled.load(Ordering::Relaxed) || out_of_time || out_of_nodes {
+                return None;
+            }
+            let alpha = margin.map_or(-i32::MAX, |margin| best.floor().saturating_sub(margin).max(-i32::MAX));
+            let (value, line) = with_worker_arena(&mut game_clone, |game| (-game.negamax(depth - 1, -i32::MAX, -alpha).0, game.best_line(depth - 1).to_vec()));
+            searched_nodes.fetch_add(game_clone.nodes, Ordering::Relaxed);
+            if game_clone.aborted {
+                return None;
+            }
+            best.insert(value);
+            Some((value * side, value > alpha, SearchStats::of(&game_clone, 0), line))
+        });
+        results.push(match budget.deterministic {
+            true => {
+                let (sender, receiver) = mpsc::channel();
+                sender.send(task()).unwrap();
+                receiver
+            },
+            false => Arc::clone(pool).submit_in(&budget.scope, task),
         });
-        Arc::clone(&pool).submit(task);
     }
 
-    let mut best_value = match game.current_player {
-        MicaPlayer::White => i32::MIN,
-        MicaPlayer::Black => i32::MAX,
-        _ => 0,
+    let mut stats = SearchStats::default();
+    let roots = moves
+        .iter()
+        .zip(results)
+        .map(|(&mica_move, result)| {
+            let result = result.recv().unwrap();
+            if let Some((_, _, task_stats, _)) = &result {
+                stats.add(task_stats);
+            }
+            RootMove {
+                mica_move,
+                score: result.as_ref().map(|&(value, _, _, _)| value),
+                exact: result.as_ref().is_some_and(|&(_, exact, _, _)| exact),
+                nodes: result.as_ref().map_or(0, |(_, _, task_stats, _)| task_stats.nodes),
+                pv: result.map_or(Vec::new(), |(_, _, _, line)| [mica_move].into_iter().chain(line).collect()),
+            }
+        })
+        .collect();
+    // every task has run once all results are in
+    (roots, Duration::from_micros(first_started.load(Ordering::Relaxed)), stats)
+}
+
+// best move for the side to move, its score and the total number of nodes searched
+//
+// Searches one ply deeper at a time up to `limits.max_depth`, trying the
+// previous depth's best move first. With a time budget no new depth starts
+// after the soft deadline and a depth still running at the hard deadline is
+// abandoned, see `SearchLimits::deadlines`. The node budget is checked before
+// every root move. The answer comes from the deepest depth that finished,
+// or from whatever root moves of the first depth got searched. Root moves
+// more than `margin` worse than the `multi_pv`th best one only get an upper
+// bound as their score, None gives every root move an exact score. With
+// more than one line the next depth searches the root moves best first.
+// Positions in the state's opening book are answered from the book, unless
+// more than one line is asked for. A deterministic search gives the same
+// answer for the same state and limits on every run: it searches the root
+// moves one at a time, ignores the time budget and picks book moves with a
+// fixed seed.
+fn get_best_move(game: MicaState, pool: &Arc<Pool<MicaBestMove>>, scope: &TaskScope, limits: &SearchLimits, margin: Option<i32>, multi_pv: usize, deterministic: bool) -> SearchResult {
+    if multi_pv <= 1 {
+        if let Some(result) = book_move(&game, deterministic.then_some(0)) {
+            return result;
+        }
+    }
+    let started = Instant::now();
+    let mut moves = game.get_ordered_moves();
+    let deadlines = limits.deadlines(started).filter(|_| !deterministic);
+    let budget = RootBudget {
+        scope: scope.clone(),
+        deadline: deadlines.map(|(_, hard)| hard),
+        max_nodes: limits.max_nodes,
+        searched_nodes: Arc::new(AtomicU64::new(0)),
+        margin,
+        multi_pv: multi_pv.max(1),
+        deterministic,
     };
-    let mut best_move = None;
-    for (i, value) in rx.iter().take(moves.len()).enumerate() {
-        println!("{value}");
-        match game.current_player {
-            MicaPlayer::White => {
-                if value > best_value {
-                    best_value = value;
-                    best_move = Some(moves[i]);
-                }
-            },
-            MicaPlayer::Black => {
-                if value < best_value {
-                    best_value = value;
-                    best_move = Some(moves[i]);
-                }
-            },
-            _ => (),
+    let mut result: Option<SearchResult> = None;
+    let mut stats = SearchStats::default();
+    let mut iterations = Vec::new();
+    let mut queue_wait = Duration::ZERO;
+
+    for depth in 1..=limits.max_depth {
+        let past_soft_deadline = deadlines.is_some_and(|(soft, _)| Instant::now() >= soft);
+        let out_of_nodes = limits.max_nodes.is_some_and(|max_nodes| budget.searched_nodes.load(Ordering::Relaxed) >= max_nodes);
+        if result.is_some() && (past_soft_deadline || out_of_nodes || scope.is_cancelled()) {
+            break;
+        }
+
+        let depth_started = Instant::now();
+        let (roots, waited, depth_stats) = search_roots(&game, &moves, depth, pool, &budget);
+        queue_wait += waited;
+        let depth_nodes = depth_stats.nodes;
+        stats.add(&depth_stats);
+        let finished = roots.iter().all(|root| root.score.is_some());
+        if !finished && result.is_some() {
+            break;
+        }
+
+        let side = game.current_player as i32;
+        // a bound can equal the best score without the move being as good, exact scores win ties
+        let best = roots
+            .iter()
+            .filter_map(|root| Some(((root.score? * side, root.exact), root)))
+            .reduce(|best, root| if root.0 > best.0 { root } else { best });
+        let (score, best_move, pv) = match best {
+            Some(((score, _), root)) => (score * side, Some(root.mica_move), root.pv.clone()),
+            // out of budget before any root move was searched, any legal move beats none
+            None => (game.game_over_value().unwrap_or_else(|| game.eval()), moves.first().copied(), Vec::new()),
+        };
+        iterations.push(Iteration { depth, score, best_move, nodes: depth_nodes, time: depth_started.elapsed() });
+        result = Some(SearchResult { best_move, score, stats: SearchStats::default(), depth, roots, iterations: Vec::new(), pv, queue_wait: Duration::ZERO });
+        if !finished {
+            break;
+        }
+        if multi_pv > 1 {
+            moves = result.as_ref().unwrap().ranked_roots(side).iter().map(|root| root.mica_move).collect();
+        }
+        if let Some(best_move) = best_move {
+            moves.retain(|&mica_move| mica_move != best_move);
+            moves.insert(0, best_move);
         }
     }
 
-    // let (_, best_move) = game.minimax(6, i32::MIN, i32::MAX);
-    best_move
-}
-
-fn handle_connection(mut stream: TcpStream, pool: Arc<Pool<MicaBestMove>>, rx: Arc<Receiver<MicaBestMove>>) {
-    // let mut buf_reader = BufReader::new(&mut stream);
-    let mut buf = [0; 1024];
-
-    let n = stream.read(&mut buf).unwrap();
-
-    let req = String::from_utf8_lossy(&buf[..n]);
-    let request: String = req.lines().skip_while(|line| !line.is_empty()).collect();
-
-    let mica_request: MicaRequest = serde_json::from_str(&request).unwrap();
-    println!("Mica request\n{:?}", mica_request);
-    let player = mica_request.player;
-    
-    let best_move = get_best_move(mica_request, pool, rx);
-
-    let result = match best_move {
-        None => json!({ "move": null }),
-        Some(MicaMove::Set { x, y, z }) => json!({ "move": [["set", player, x, y, z]] }),
-        Some(MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z }) => json!({ "move": [["move", player, to_x, to_y, to_z, from_x, from_y, from_z]] }),
-        Some(MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z }) => {
-            json!({ "move": [
-                ["set", player, x, y, z],
-                ["remove", player, remove_x, remove_y, remove_z]
-            ]})
-        },
-        Some(MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z }) => {
-            json!({ "move": [
-                ["move",player,  to_x, to_y, to_z, from_x, from_y, from_z],
-                ["remove", player, remove_x, remove_y, remove_z]
-            ]})
+    let mut result = result.unwrap_or_else(|| SearchResult {
+        best_move: None,
+        score: game.game_over_value().unwrap_or_else(|| game.eval()),
+        stats: SearchStats::default(),
+        depth: 0,
+        roots: Vec::new(),
+        iterations: Vec::new(),
+        pv: Vec::new(),
+        queue_wait: Duration::ZERO,
+    });
+    result.stats = SearchStats { elapsed: started.elapsed(), ..stats };
+    result.iterations = iterations;
+    result.queue_wait = queue_wait;
+    result
+}
+
+// A move from the state's opening book, picked with `seed` or by the clock,
+// as the answer of a search that searched nothing. None when the book has no
+// move here.
+fn book_move(game: &MicaState, seed: Option<u64>) -> Option<SearchResult> {
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let book_move = game.book.as_ref()?.pick(game, &mut Rng::new(seed))?;
+    Some(SearchResult {
+        best_move: Some(book_move),
+        score: game.eval(),
+        stats: SearchStats::default(),
+        depth: 0,
+        roots: Vec::new(),
+        iterations: Vec::new(),
+        pv: vec![book_move],
+        queue_wait: Duration::ZERO,
+    })
+}
+
+// Lazy SMP: the calling thread searches the whole position one depth after
+// the other while `helpers` pool tasks search the same position next to it,
+// every other one a ply deeper. They share nothing but the transposition
+// table, which fills up ahead of the main search and lets it skip work.
+// Helpers are stopped as soon as the main search finishes a depth. Limits
+// work as in `get_best_move`, except that the node budget is only checked
+// between depths. The answer has no per root move scores.
+fn lazy_smp(mut game: MicaState, pool: &Arc<Pool<MicaBestMove>>, scope: &TaskScope, limits: &SearchLimits, helpers: usize) -> SearchResult {
+    if let Some(result) = book_move(&game, None) {
+        return result;
+    }
+    if game.tt.is_none() {
+        game.tt = Some(Arc::new(TranspositionTable::new(16)));
+    }
+    let started = Instant::now();
+    let deadlines = limits.deadlines(started);
+    game.deadline = deadlines.map(|(_, hard)| hard);
+    game.cancel = Some(Arc::clone(scope.cancelled()));
+    let side = game.current_player as i32;
+    let mut result: Option<SearchResult> = None;
+    let mut stats = SearchStats::default();
+    let mut iterations = Vec::new();
+
+    for depth in 1..=limits.max_depth {
+        let past_soft_deadline = deadlines.is_some_and(|(soft, _)| Instant::now() >= soft);
+        let out_of_nodes = limits.max_nodes.is_some_and(|max_nodes| stats.nodes >= max_nodes);
+        if result.is_some() && (past_soft_deadline || out_of_nodes || scope.is_cancelled()) {
+            break;
         }
-    };
 
-    let status_line = "HTTP/1.1 200 OK";
-    let contents = result.to_string();
-    let length = contents.len();
+        let depth_started = Instant::now();
+        let stop = Arc::new(AtomicBool::new(false));
+        let running: Vec<_> = (1..=helpers)
+            .map(|i| {
+                let mut helper = game.clone();
+                helper.reset_counters();
+                helper.cancel = Some(Arc::clone(&stop));
+                let stop = Arc::clone(&stop);
+                let task: MicaTask<MicaBestMove> = Box::new(move || {
+                    // a helper that gets a thread only after the main search is done has nothing to add
+                    if stop.load(Ordering::Relaxed) {
+                        return None;
+                    }
+                    let (value, _) = with_worker_arena(&mut helper, |helper| helper.negamax(depth + (i % 2) as u8, -i32::MAX, i32::MAX));
+                    Some((value * side, !helper.aborted, SearchStats::of(&helper, 0), Vec::new()))
+                });
+                Arc::clone(pool).submit_in(scope, task)
+            })
+            .collect();
+
+        game.reset_counters();
+        let (value, best_move) = game.negamax(depth, -i32::MAX, i32::MAX);
+        stop.store(true, Ordering::Relaxed);
+        let mut depth_stats = SearchStats::of(&game, 0);
+        for helper in running {
+            if let Some((_, _, helper_stats, _)) = helper.recv().unwrap() {
+                depth_stats.add(&helper_stats);
+            }
+        }
+        let depth_nodes = depth_stats.nodes;
+        stats.add(&depth_stats);
+        if game.aborted {
+            break;
+        }
+        iterations.push(Iteration { depth, score: value * side, best_move, nodes: depth_nodes, time: depth_started.elapsed() });
+        let pv = game.best_line(depth).to_vec();
+        result = Some(SearchResult { best_move, score: value * side, stats: SearchStats::default(), depth, roots: Vec::new(), iterations: Vec::new(), pv, queue_wait: Duration::ZERO });
+    }
+
+    let mut result = result.unwrap_or_else(|| SearchResult {
+        // out of budget before the first depth finished, any legal move beats none
+        best_move: game.get_ordered_moves().first().copied(),
+        score: game.game_over_value().unwrap_or_else(|| game.eval()),
+        stats: SearchStats::default(),
+        depth: 0,
+        roots: Vec::new(),
+        iterations: Vec::new(),
+        pv: Vec::new(),
+        queue_wait: Duration::ZERO,
+    });
+    result.stats = SearchStats { elapsed: started.elapsed(), ..stats };
+    result.iterations = iterations;
+    result
+}
+
+// `get_best_move` as an `Engine`, the one with per root move scores
+struct AlphaBeta {
+    pool: Arc<Pool<MicaBestMove>>,
+    // what the root tasks are submitted in
+    scope: TaskScope,
+    margin: Option<i32>,
+    multi_pv: usize,
+    deterministic: bool,
+}
+
+impl Engine for AlphaBeta {
+    fn best_move(&mut self, state: &MicaState, limits: &SearchLimits) -> SearchResult {
+        get_best_move(state.clone(), &self.pool, &self.scope, limits, self.margin, self.multi_pv, self.deterministic)
+    }
+}
+
+// `lazy_smp` as an `Engine`
+struct LazySmp {
+    pool: Arc<Pool<MicaBestMove>>,
+    // what the helpers are submitted in
+    scope: TaskScope,
+    helpers: usize,
+}
+
+impl Engine for LazySmp {
+    fn best_move(&mut self, state: &MicaState, limits: &SearchLimits) -> SearchResult {
+        lazy_smp(state.clone(), &self.pool, &self.scope, limits, self.helpers)
+    }
+}
 
-    let response = format!(
-        "{status_line}\r\nContent-Type: applicaton/json\r\nContent-Length: {length}\r\n\r\n{contents}"
-    );
-    stream.write_all(response.as_bytes()).unwrap();
+// value of a `--name value` command line option, or the default when absent,
+// a value that does not parse exits with status 2
+fn option<T: FromStr>(args: &[String], name: &str, default: T) -> T
+where
+    T::Err: fmt::Display,
+{
+    let Some(value) = args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)) else {
+        return default;
+    };
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("Invalid {name} `{value}`: {e}");
+        process::exit(2);
+    })
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("train") => return train::run(&args[1..]),
+        Some("export") => return export::run(&args[1..]),
+        Some("convert") => return convert::run(&args[1..]),
+        Some("match") => return arena::run(&args[1..]),
+        Some("analyze-corpus") => return analyze::run(&args[1..]),
+        Some("analyze") => return analyze::run_position(&args[1..]),
+        Some("difficulty") => return analyze::run_difficulty(&args[1..]),
+        Some("selfcheck") => return selfcheck::run(&args[1..]),
+        Some("client") => return protocol::run(&args[1..]),
+        Some("verify-search") => return verify::run(&args[1..]),
+        Some("audit-eval") => return audit::run(&args[1..]),
+        Some("tablebase") => return endgame::run(&args[1..]),
+        Some("book") => return opening::run(&args[1..]),
+        Some("tune") => return tune::run(&args[1..]),
+        _ => (),
+    }
+
     let pool = Arc::new(Pool::new());
-    let rx = Arc::new(Arc::clone(&pool).init(8));
-    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
+    Arc::clone(&pool).init(SEARCH_THREADS);
+
+    match args.first().map(String::as_str) {
+        Some("ludii") => ludii::run(pool),
+        _ => server::serve(pool, &args),
+    }
+}
 
-    for stream in listener.incoming() {
-        let stream = stream.unwrap();
-        let rx = Arc::clone(&rx);
-        handle_connection(stream, Arc::clone(&pool), rx);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_tasks_search_as_deep_as_iterative_deepening() {
+        // white closes the top of the outer ring and takes black's third stone
+        let mut won = MicaState::new();
+        won.set_stones_to_set(0, 0);
+        for (x, y, z) in [(0, 0, 0), (0, 0, 1), (0, 1, 2), (1, 0, 1)] {
+            won.put_stone(x, y, z, MicaPlayer::White);
+        }
+        for (x, y, z) in [(2, 0, 0), (2, 2, 2), (1, 2, 0)] {
+            won.put_stone(x, y, z, MicaPlayer::Black);
+        }
+        let pool = Arc::new(Pool::new());
+        for state in [MicaState::new(), won.clone()] {
+            for depth in 1..=3 {
+                let limits = SearchLimits { max_depth: depth, ..SearchLimits::default() };
+                let split = get_best_move(state.clone(), &pool, &TaskScope::new(), &limits, None, 1, true);
+                let (score, _, reached) = state.clone().iterative_deepening(depth, Duration::from_secs(60));
+                assert_eq!((split.score, split.depth), (score, reached), "depth {depth}");
+            }
+        }
+        assert_eq!(won.clone().iterative_deepening(3, Duration::from_secs(60)).0, WIN - 1);
     }
 }
@@ -1,8 +1,49 @@
-use std::mem;
-use serde::Deserialize;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use serde::de::{self, Visitor};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "std")]
+use crate::book::OpeningBook;
+#[cfg(feature = "std")]
+use crate::api::RuleOptions;
+use crate::bump::BumpArena;
+use crate::coords::POINTS;
+use crate::eval::{evaluate, PhaseWeights};
+use crate::history::History;
+#[cfg(feature = "nn")]
+use crate::nn::Network;
+use crate::symmetry::Symmetry;
+use crate::tablebase::Tablebase;
+use crate::tt::{Bound, Entry, TranspositionTable};
+use crate::variants::{self, Phase, Rules};
+#[cfg(feature = "std")]
+use crate::validation::ValidationMode;
+use crate::zobrist;
+
+// Score of a game won in the position searched from. A game won further
+// down the search scores a point less for every ply it takes to get there,
+// so the search goes for the quickest win and holds out longest in a lost
+// position, see `MicaState::game_over_at_ply`.
+pub const WIN: i32 = 1000;
+// scores at least this far from zero are won or lost games, nothing eval
+// gives comes close, a search and a tablebase line each add at most 255 plies
+pub const DECISIVE: i32 = WIN - 2 * u8::MAX as i32;
+
+// nodes between two looks at a search's deadline and cancel flag
+const STOP_CHECK_NODES: u64 = 1024;
 
 pub trait MinimaxPlayer {
-    fn into_next_player(self) -> impl MinimaxPlayer;
+    fn into_next_player(self) -> Self;
     fn toggle(&mut self);
 }
 
@@ -13,18 +54,209 @@ pub trait Minimax {
     fn is_end(&self) -> bool;
     fn eval(&self) -> Self::Value;
     fn get_moves(&self) -> Vec<Self::Move>;
+    // score for the side to move searched `depth` plies deep inside the
+    // window (a, b), and the move that gets it
+    fn negamax(&mut self, depth: u8, a: i32, b: i32) -> (Self::Value, Option<Self::Move>);
+    // the same search with the window and score from white's point of view
     fn minimax(&mut self, depth: u8, a: i32, b: i32) -> (Self::Value, Option<Self::Move>);
 }
 
-#[derive(Deserialize, Debug)]
+// The side to move as sent by the client, either `1`/`-1` or `"white"`/`"black"`.
+// Responses echo players back in the same style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerField {
+    Number(i8),
+    Name(MicaPlayer),
+}
+
+impl PlayerField {
+    pub fn value(&self) -> i8 {
+        match self {
+            PlayerField::Number(value) => *value,
+            PlayerField::Name(player) => *player as i8,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Serialize for PlayerField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PlayerField::Number(value) => serializer.serialize_i8(*value),
+            PlayerField::Name(MicaPlayer::White) => serializer.serialize_str("white"),
+            PlayerField::Name(MicaPlayer::Black) => serializer.serialize_str("black"),
+            PlayerField::Name(MicaPlayer::None) => serializer.serialize_str("none"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+struct PlayerFieldVisitor;
+
+#[cfg(feature = "std")]
+impl Visitor<'_> for PlayerFieldVisitor {
+    type Value = PlayerField;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("1, -1, \"white\" or \"black\"")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<PlayerField, E> {
+        i8::try_from(value).map(PlayerField::Number).map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<PlayerField, E> {
+        i8::try_from(value).map(PlayerField::Number).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<PlayerField, E> {
+        match value.to_ascii_lowercase().as_str() {
+            "white" => Ok(PlayerField::Name(MicaPlayer::White)),
+            "black" => Ok(PlayerField::Name(MicaPlayer::Black)),
+            _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for PlayerField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PlayerFieldVisitor)
+    }
+}
+
+// `white_remaining`/`black_remaining` are the stones each player still has to
+// set, `white_count`/`black_count` the stones they have on the board. The
+// camelCase spellings sent by JavaScript clients are accepted as well.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct MicaRequest {
-    difficulty: String,
-    pub player: i8,
-    white_remaining: u8,
-    black_remaining: u8,
-    white_count: u8,
-    black_count: u8,
-    stones: Box<[[[i8; 3]; 3]; 3]>,
+    // `easy`, `medium` or `hard`, empty for the server's own limits
+    pub difficulty: String,
+    pub player: PlayerField,
+    #[serde(alias = "whiteRemaining")]
+    pub(crate) white_remaining: u8,
+    #[serde(alias = "blackRemaining")]
+    pub(crate) black_remaining: u8,
+    #[serde(alias = "whiteCount")]
+    pub(crate) white_count: u8,
+    #[serde(alias = "blackCount")]
+    pub(crate) black_count: u8,
+    pub(crate) stones: Box<[[[i8; 3]; 3]; 3]>,
+    #[serde(default)]
+    pub validation: Option<ValidationMode>,
+    // rule set of the position, see `variants`
+    #[serde(default)]
+    pub variant: Option<String>,
+    // `classic` (the default) or `nn` for the server's network, see `nn`
+    #[serde(default)]
+    pub evaluator: Option<String>,
+    #[serde(default, alias = "requestId")]
+    pub request_id: Option<serde_json::Value>,
+    // a newer move request for the same game cancels the search of this one
+    #[serde(default, alias = "gameId")]
+    pub game_id: Option<String>,
+    // analysis jobs POST their result here when they finish
+    #[serde(default, alias = "callbackUrl")]
+    pub callback_url: Option<String>,
+    // search limits, capped by the server
+    #[serde(default)]
+    pub depth: Option<u8>,
+    #[serde(default, alias = "timeMs")]
+    pub time_ms: Option<u64>,
+    #[serde(default, alias = "maxNodes")]
+    pub max_nodes: Option<u64>,
+    // `random` or `greedy` answer with a baseline agent instead of searching
+    #[serde(default)]
+    pub engine: Option<String>,
+    // analysis jobs keep their search details for `GET /analysis/{id}/details`
+    #[serde(default)]
+    pub details: bool,
+    // answer with one JSON line per search depth over a chunked response
+    #[serde(default)]
+    pub stream: bool,
+    // `minimal` (the default) or `rich`, how much the answer says
+    #[serde(default)]
+    pub profile: Option<String>,
+    // answer with this many best moves and their scores in `lines`
+    #[serde(default, alias = "multiPv")]
+    pub multi_pv: Option<usize>,
+    // play any move at most this much worse than the best one, picked at random
+    #[serde(default, alias = "tieMargin")]
+    pub tie_margin: Option<i32>,
+    // seeds that random choice so it can be replayed, the clock seeds it otherwise
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // stones the side ahead gives up for lines that get it no further ahead,
+    // see `Contempt`, the server default when absent
+    #[serde(default)]
+    pub contempt: Option<i32>,
+    // moves played in a row without a stone set or a mill closed, and the
+    // count of them that draws the game, no draw by quiet moves when absent
+    #[serde(default, alias = "quietMoves")]
+    pub quiet_moves: u16,
+    #[serde(default, alias = "drawAfter")]
+    pub draw_after: Option<u16>,
+    // rule options changed from the variant's, if the server allows them
+    #[serde(default)]
+    pub rules: Option<RuleOptions>,
+    // of the API the client was written for, a newer one than the server's is rejected
+    #[serde(default, alias = "schemaVersion")]
+    pub schema_version: Option<u32>,
+    // Fields the answer depends on, a server that does not know one of them
+    // rejects the request instead of ignoring it, see `FIELDS`.
+    #[serde(default)]
+    pub critical: Vec<String>,
+}
+
+#[cfg(feature = "std")]
+impl MicaRequest {
+    // every field by its snake_case name, as `GET /capabilities` lists them
+    pub const FIELDS: [&'static str; 29] = [
+        "difficulty", "player", "white_remaining", "black_remaining", "white_count", "black_count", "stones", "validation", "variant",
+        "evaluator", "request_id", "game_id", "callback_url", "depth", "time_ms", "max_nodes", "engine", "details", "stream", "profile",
+        "multi_pv", "tie_margin", "seed", "contempt", "quiet_moves", "draw_after", "rules", "schema_version", "critical",
+    ];
+
+    // a move request for the position, with the server's defaults for everything else
+    pub fn from_state(state: &MicaState) -> Self {
+        let (white_to_set, black_to_set) = state.stones_to_set();
+        MicaRequest {
+            difficulty: String::new(),
+            player: PlayerField::Number(state.current_player as i8),
+            white_remaining: white_to_set,
+            black_remaining: black_to_set,
+            white_count: state.white_remaining,
+            black_count: state.black_remaining,
+            stones: Box::new(state.stones.map(|column| column.map(|row| row.map(|player| player as i8)))),
+            validation: None,
+            variant: None,
+            evaluator: None,
+            request_id: None,
+            game_id: None,
+            callback_url: None,
+            depth: None,
+            time_ms: None,
+            max_nodes: None,
+            engine: None,
+            details: false,
+            stream: false,
+            profile: None,
+            multi_pv: None,
+            tie_margin: None,
+            seed: None,
+            contempt: None,
+            quiet_moves: state.quiet_moves,
+            draw_after: state.draw_after,
+            rules: (state.rules != variants::DEFAULT.rules).then_some(RuleOptions {
+                flying: Some(state.rules.flying),
+                mill_fallback: Some(state.rules.mill_fallback),
+                draw_after: None,
+            }),
+            schema_version: None,
+            critical: Vec::new(),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -46,7 +278,7 @@ impl MinimaxPlayer for MicaPlayer {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MicaMove {
     Set {
         x: u8,
@@ -82,39 +314,457 @@ pub enum MicaMove {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalMove(pub MicaMove);
+
+// Most legal moves a position can have, counting every move as closing a
+// mill and taking any of the opponent's stones. Nine stones stepping to four
+// neighbours each leave nine to take. Three flying stones go to any of the
+// 21 - k empty points with k stones to take, most at k = 9. Setting a stone
+// makes fewer, (24 - k) * k.
+const STEPPING_MOVES: usize = 9 * 4 * 9;
+const FLYING_MOVES: usize = 3 * (24 - 3 - 9) * 9;
+pub const MAX_MOVES: usize = if STEPPING_MOVES > FLYING_MOVES { STEPPING_MOVES } else { FLYING_MOVES };
+
+// every point in array order, the order moves are generated in
+const SORTED_POINTS: [(u8, u8, u8); 24] = [
+    (0, 0, 0), (0, 0, 1), (0, 0, 2), (0, 1, 0), (0, 1, 2), (0, 2, 0), (0, 2, 1), (0, 2, 2),
+    (1, 0, 0), (1, 0, 1), (1, 0, 2), (1, 1, 0), (1, 1, 2), (1, 2, 0), (1, 2, 1), (1, 2, 2),
+    (2, 0, 0), (2, 0, 1), (2, 0, 2), (2, 1, 0), (2, 1, 2), (2, 2, 0), (2, 2, 1), (2, 2, 2),
+];
+
+// every cell of the stones array, ring centres included, in generation order
+fn cells() -> impl Iterator<Item = (u8, u8, u8)> + Clone {
+    (0u8..3).flat_map(|x| (0u8..3).flat_map(move |y| (0u8..3).map(move |z| (x, y, z))))
+}
+
+// Makes a search dislike lines in which the side ahead in material gets no
+// further ahead, so it plays for more instead of shuffling stones back and
+// forth. Values are in stones like the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contempt {
+    // the side ahead when the search started
+    pub side: MicaPlayer,
+    // white's stones less black's when the search started
+    pub material: i32,
+    // taken off that side's score at leaves still at that material
+    pub value: i32,
+}
+
+// every symmetry of the board, `MicaState::hashes` follows their order
+const SYMMETRIES: [Symmetry; 16] = Symmetry::all();
+
 #[derive(Debug, Clone)]
 pub struct MicaState {
     pub current_player: MicaPlayer,
-    white_remaining: u8,
-    black_remaining: u8,
-    white_to_set: u8,
-    black_to_set: u8,
-    stones: Box<[[[MicaPlayer; 3]; 3]; 3]>,
+    pub(crate) white_remaining: u8,
+    pub(crate) black_remaining: u8,
+    pub(crate) white_to_set: u8,
+    pub(crate) black_to_set: u8,
+    pub(crate) stones: Box<[[[MicaPlayer; 3]; 3]; 3]>,
+    // Zobrist hashes of the stones and stones to set of every symmetric image
+    // of the position, in the order of `Symmetry::all` so the first is the
+    // position itself. Kept up to date by every change to them, the side to
+    // move is added by `zobrist` and `canonical_zobrist`.
+    pub(crate) hashes: [u64; 16],
+    // positions visited by `minimax` on this state
+    pub nodes: u64,
+    // of those, the ones a transposition table entry decided and the ones
+    // whose moves stopped at one scoring at least beta
+    pub tt_hits: u64,
+    pub beta_cutoffs: u64,
+    // plies below the position the search started from, now and at the deepest
+    ply: u8,
+    pub max_ply: u8,
+    // shared with every state cloned from this one, searches skip positions stored here
+    pub tt: Option<Arc<TranspositionTable>>,
+    // quiet moves that cut off searches on this state, for move ordering
+    pub history: History,
+    // a search gives up once the deadline passes or the flag is raised
+    #[cfg(feature = "std")]
+    pub deadline: Option<Instant>,
+    pub cancel: Option<Arc<AtomicBool>>,
+    // sleeps this long every `STOP_CHECK_NODES` nodes so searches for other
+    // clients get the cores, None never pauses
+    #[cfg(feature = "std")]
+    pub pause: Option<Duration>,
+    // set when the last search gave up, its result means nothing
+    pub aborted: bool,
+    // None scores leaves by the evaluation alone
+    pub contempt: Option<Contempt>,
+    // of the terms of `eval`
+    pub weights: PhaseWeights,
+    // evaluates positions instead of the weights when set
+    #[cfg(feature = "nn")]
+    pub network: Option<Arc<Network>>,
+    // the rule options moves are generated by, see `Rules`
+    pub rules: Rules,
+    // shared with every state cloned from this one, leaves it covers get their exact result
+    pub tablebase: Option<Arc<Tablebase>>,
+    // searches of positions in the book play one of its moves instead, see `book`
+    #[cfg(feature = "std")]
+    pub book: Option<Arc<OpeningBook>>,
+    // Moves in a row without a stone set or a mill closed, and the counts
+    // those moves reset, so `undo_move` can restore them. Not part of the hash.
+    pub quiet_moves: u16,
+    quiet_before: Vec<u16>,
+    // the game is drawn after this many quiet moves, None plays on forever
+    pub draw_after: Option<u16>,
+    // move lists and best lines of searches on this state, kept between
+    // searches so their buffers are only allocated once
+    pub arena: BumpArena,
+}
+
+impl Default for MicaState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MicaState {
     pub fn new() -> Self {
-        MicaState {
+        let mut state = MicaState {
             white_remaining: 0,
             black_remaining: 0,
             white_to_set: 9,
             black_to_set: 9,
             current_player: MicaPlayer::White,
             stones: Box::new([[[MicaPlayer::None; 3]; 3]; 3]),
-        }
+            hashes: [0; 16],
+            nodes: 0,
+            tt_hits: 0,
+            beta_cutoffs: 0,
+            ply: 0,
+            max_ply: 0,
+            tt: None,
+            history: History::new(),
+            #[cfg(feature = "std")]
+            deadline: None,
+            cancel: None,
+            #[cfg(feature = "std")]
+            pause: None,
+            aborted: false,
+            contempt: None,
+            weights: PhaseWeights::default(),
+            #[cfg(feature = "nn")]
+            network: None,
+            rules: variants::DEFAULT.rules,
+            tablebase: None,
+            #[cfg(feature = "std")]
+            book: None,
+            quiet_moves: 0,
+            quiet_before: Vec::new(),
+            draw_after: None,
+            arena: BumpArena::default(),
+        };
+        state.rehash();
+        state
     }
 
+    // The position of a move request, playing by its variant's rules with the
+    // request's rule options. Unknown variants play the default one, the
+    // server rejects them before they get here.
+    #[cfg(feature = "std")]
     pub fn from_request(request: MicaRequest) -> Self {
-        MicaState {
+        let variant = request.variant.as_deref().and_then(variants::find).unwrap_or(variants::DEFAULT);
+        let options = request.rules.unwrap_or_default();
+        let mut state = MicaState {
             white_remaining: request.white_count,
             black_remaining: request.black_count,
             white_to_set: request.white_remaining,
             black_to_set: request.black_remaining,
-            current_player: if request.player == 1 { MicaPlayer::White } else { MicaPlayer::Black },
-            stones: unsafe { mem::transmute(request.stones) },
+            current_player: if request.player.value() == 1 { MicaPlayer::White } else { MicaPlayer::Black },
+            stones: unsafe { mem::transmute::<Box<[[[i8; 3]; 3]; 3]>, Box<[[[MicaPlayer; 3]; 3]; 3]>>(request.stones) },
+            hashes: [0; 16],
+            nodes: 0,
+            tt_hits: 0,
+            beta_cutoffs: 0,
+            ply: 0,
+            max_ply: 0,
+            tt: None,
+            history: History::new(),
+            deadline: None,
+            cancel: None,
+            pause: None,
+            aborted: false,
+            contempt: None,
+            weights: PhaseWeights::default(),
+            #[cfg(feature = "nn")]
+            network: None,
+            rules: options.apply(variant.rules),
+            tablebase: None,
+            #[cfg(feature = "std")]
+            book: None,
+            quiet_moves: request.quiet_moves,
+            quiet_before: Vec::new(),
+            draw_after: request.draw_after.or(options.draw_after),
+            arena: BumpArena::default(),
+        };
+        state.rehash();
+        state
+    }
+
+    // Winner of a game stopped before it ended, whoever the evaluation favours,
+    // None for a draw.
+    pub fn adjudicate(&self) -> MicaPlayer {
+        match self.eval() {
+            0 => MicaPlayer::None,
+            value if value > 0 => MicaPlayer::White,
+            _ => MicaPlayer::Black,
         }
     }
 
+    // white's stones on the board less black's
+    pub fn material(&self) -> i32 {
+        self.white_remaining as i32 - self.black_remaining as i32
+    }
+
+    // the points of white's and black's stones, as flat index bit sets
+    pub fn occupancy(&self) -> (u32, u32) {
+        let (mut white, mut black) = (0u32, 0u32);
+        for (point, &(x, y, z)) in POINTS.iter().enumerate() {
+            match self.stones[x as usize][y as usize][z as usize] {
+                MicaPlayer::White => white |= 1 << point,
+                MicaPlayer::Black => black |= 1 << point,
+                MicaPlayer::None => (),
+            }
+        }
+        (white, black)
+    }
+
+    // Searches from this position on score the side ahead in material
+    // `value` stones worse where it has not got further ahead, nothing
+    // changes while the material is even.
+    pub fn set_contempt(&mut self, value: i32) {
+        let material = self.material();
+        self.contempt = (material != 0 && value != 0).then_some(Contempt {
+            side: if material > 0 { MicaPlayer::White } else { MicaPlayer::Black },
+            material,
+            value,
+        });
+    }
+
+    // where the game stands, by the state's rules
+    pub fn phase(&self) -> Phase {
+        if self.stones_to_set() != (0, 0) {
+            return Phase::Setting;
+        }
+        match self.white_remaining.min(self.black_remaining) {
+            3 if self.rules.flying => Phase::Flying,
+            0..=3 => Phase::Endgame,
+            _ => Phase::Moving,
+        }
+    }
+
+    // the evaluation a search gives a leaf, with contempt applied, or its
+    // result in the tablebase
+    fn leaf_value(&self) -> i32 {
+        if let Some(result) = self.tablebase.as_ref().and_then(|tablebase| tablebase.probe(self)) {
+            return result.score(self.ply) * self.current_player as i32;
+        }
+        let eval = self.eval();
+        match self.contempt {
+            Some(contempt) if self.material() == contempt.material => eval - contempt.value * contempt.side as i32,
+            _ => eval,
+        }
+    }
+
+    // The principal variation of the last search to `depth`, starting with its
+    // best move. It ends early where a transposition table entry decided a node.
+    pub fn best_line(&self, depth: u8) -> &[MicaMove] {
+        self.arena.pv.get(depth as usize).map_or(&[], Vec::as_slice)
+    }
+
+    // `mica_move` followed by the best line found one ply deeper becomes the best line at `depth`
+    fn update_pv(&mut self, depth: u8, mica_move: MicaMove) {
+        let (below, above) = self.arena.pv.split_at_mut(depth as usize);
+        let line = &mut above[0];
+        line.clear();
+        line.push(mica_move);
+        line.extend_from_slice(&below[depth as usize - 1]);
+    }
+
+    // recomputes the hashes from scratch after the stones were changed directly
+    pub(crate) fn rehash(&mut self) {
+        let to_set = zobrist::to_set(MicaPlayer::White, self.white_to_set) ^ zobrist::to_set(MicaPlayer::Black, self.black_to_set);
+        self.hashes = [to_set; 16];
+        for x in 0u8..3 {
+            for y in 0u8..3 {
+                for z in 0u8..3 {
+                    let images = zobrist::stone_images(x, y, z, self.stones[x as usize][y as usize][z as usize]);
+                    for (hash, image) in self.hashes.iter_mut().zip(images) {
+                        *hash ^= image;
+                    }
+                }
+            }
+        }
+    }
+
+    // hash of the whole position, side to move included
+    pub fn zobrist(&self) -> u64 {
+        self.hashes[0] ^ zobrist::side(self.current_player)
+    }
+
+    // The smallest hash among the symmetric images of the position and the
+    // symmetry that maps the position onto that image. Symmetric positions
+    // share it, the transposition table keys on it.
+    pub fn canonical_zobrist(&self) -> (u64, Symmetry) {
+        let (i, hash) = self.hashes.iter().enumerate().min_by_key(|&(_, hash)| *hash).unwrap();
+        (hash ^ zobrist::side(self.current_player), SYMMETRIES[i])
+    }
+
+    fn set_point(&mut self, x: u8, y: u8, z: u8, player: MicaPlayer) {
+        let point = &mut self.stones[x as usize][y as usize][z as usize];
+        let (removed, added) = (zobrist::stone_images(x, y, z, *point), zobrist::stone_images(x, y, z, player));
+        *point = player;
+        for i in 0..16 {
+            self.hashes[i] ^= removed[i] ^ added[i];
+        }
+    }
+
+    pub fn put_stone(&mut self, x: u8, y: u8, z: u8, player: MicaPlayer) {
+        self.set_point(x, y, z, player);
+        match player {
+            MicaPlayer::White => self.white_remaining += 1,
+            MicaPlayer::Black => self.black_remaining += 1,
+            MicaPlayer::None => (),
+        }
+    }
+
+    pub fn stone(&self, x: u8, y: u8, z: u8) -> MicaPlayer {
+        self.stones[x as usize][y as usize][z as usize]
+    }
+
+    // The search this state is part of started `ply` plies above it, as the
+    // search of a root move handed to another thread does, so its won and
+    // lost games are scored by their distance from that search's root.
+    pub fn set_ply(&mut self, ply: u8) {
+        self.ply = ply;
+    }
+
+    // `game_over_value` scored from the root of the search, see `WIN`
+    fn game_over_at_ply(&self) -> Option<i32> {
+        self.game_over_value().map(|value| value - value.signum() * self.ply as i32)
+    }
+
+    // starts counting the nodes, table hits, cutoffs and plies of a search afresh
+    pub fn reset_counters(&mut self) {
+        self.nodes = 0;
+        self.tt_hits = 0;
+        self.beta_cutoffs = 0;
+        self.max_ply = 0;
+    }
+
+    // whether `eval` asks a network instead of the weights, see `nn`
+    #[cfg(feature = "nn")]
+    pub fn has_network(&self) -> bool {
+        self.network.is_some()
+    }
+
+    #[cfg(not(feature = "nn"))]
+    pub fn has_network(&self) -> bool {
+        false
+    }
+
+    // white's and black's stones on the board
+    pub fn stones_on_board(&self) -> (u8, u8) {
+        (self.white_remaining, self.black_remaining)
+    }
+
+    pub fn stones_to_set(&self) -> (u8, u8) {
+        (self.white_to_set, self.black_to_set)
+    }
+
+    pub fn set_stones_to_set(&mut self, white_to_set: u8, black_to_set: u8) {
+        let change = zobrist::to_set(MicaPlayer::White, self.white_to_set) ^ zobrist::to_set(MicaPlayer::White, white_to_set) ^
+            zobrist::to_set(MicaPlayer::Black, self.black_to_set) ^ zobrist::to_set(MicaPlayer::Black, black_to_set);
+        for hash in self.hashes.iter_mut() {
+            *hash ^= change;
+        }
+        self.white_to_set = white_to_set;
+        self.black_to_set = black_to_set;
+    }
+
+    #[cfg(feature = "std")]
+    fn past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // without a clock only the cancel flag stops a search
+    #[cfg(not(feature = "std"))]
+    fn past_deadline(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "std")]
+    fn take_pause(&self) {
+        if let Some(pause) = self.pause {
+            std::thread::sleep(pause);
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn take_pause(&self) {}
+
+    // Searches one ply deeper at a time, up to `max_depth`, until `budget` has
+    // run out. Every depth tries the previous depth's best move first. A depth
+    // the budget cuts short is thrown away, so the answer is the best move of
+    // the deepest finished depth with its score and that depth. Depth 1 always
+    // finishes, deeper ones give up as soon as the budget runs out.
+    #[cfg(feature = "std")]
+    pub fn iterative_deepening(&mut self, max_depth: u8, budget: Duration) -> (i32, Option<MicaMove>, u8) {
+        let deadline = Instant::now() + budget;
+        let mut moves = self.get_ordered_moves();
+        if let Some(value) = self.game_over_value() {
+            return (value, None, 0);
+        }
+        let mut best = (self.eval(), None, 0);
+
+        let side = self.current_player as i32;
+        let outer_deadline = self.deadline;
+        self.aborted = false;
+        for depth in 1..=max_depth {
+            self.deadline = if depth > 1 { Some(deadline) } else { None };
+            let mut a = -i32::MAX;
+            let mut best_value = -i32::MAX;
+            let mut best_move = None;
+            let mut finished = true;
+            for &next_move in &moves {
+                self.apply_move(next_move);
+                self.current_player.toggle();
+                self.ply += 1;
+                let value = -self.negamax(depth - 1, -i32::MAX, -a).0;
+                self.ply -= 1;
+                self.current_player.toggle();
+                self.undo_move(next_move);
+                if self.aborted {
+                    finished = false;
+                    break;
+                }
+
+                if best_move.is_none() || value > best_value {
+                    best_value = value;
+                    best_move = Some(next_move);
+                }
+                a = a.max(value);
+            }
+            if !finished {
+                break;
+            }
+
+            best = (best_value * side, best_move, depth);
+            let best_move = best_move.unwrap();
+            moves.retain(|&mica_move| mica_move != best_move);
+            moves.insert(0, best_move);
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+        self.deadline = outer_deadline;
+        self.aborted = false;
+        best
+    }
+
     fn increment_player(&mut self) {
         match self.current_player {
             MicaPlayer::White => {
@@ -164,184 +814,341 @@ impl MicaState {
     }
 
     fn increment_remaining_to_set(&mut self) {
+        let (white_to_set, black_to_set) = self.stones_to_set();
         match self.current_player {
-            MicaPlayer::White => {
-                self.white_to_set += 1;
-            },
-            MicaPlayer::Black => {
-                self.black_to_set += 1;
-            },
+            MicaPlayer::White => self.set_stones_to_set(white_to_set + 1, black_to_set),
+            MicaPlayer::Black => self.set_stones_to_set(white_to_set, black_to_set + 1),
             MicaPlayer::None => unreachable!(),
         }
     }
 
     fn decrement_remaining_to_set(&mut self) {
+        let (white_to_set, black_to_set) = self.stones_to_set();
         match self.current_player {
-            MicaPlayer::White => {
-                self.white_to_set -= 1;
-            },
-            MicaPlayer::Black => {
-                self.black_to_set -= 1;
-            },
+            MicaPlayer::White => self.set_stones_to_set(white_to_set - 1, black_to_set),
+            MicaPlayer::Black => self.set_stones_to_set(white_to_set, black_to_set - 1),
             MicaPlayer::None => unreachable!(),
         }
     }
 
+    // applies `mica_move` only when it is legal in this position
+    pub fn checked_apply_move(&mut self, mica_move: MicaMove) -> Result<(), IllegalMove> {
+        if self.is_end() || !self.get_moves().contains(&mica_move) {
+            return Err(IllegalMove(mica_move));
+        }
+        self.apply_unchecked(mica_move);
+        Ok(())
+    }
+
+    // With the `checked-moves` feature every move is checked against the
+    // rules first and an illegal one panics instead of corrupting the state.
     pub fn apply_move(&mut self, mica_move: MicaMove) {
+        if cfg!(feature = "checked-moves") {
+            if let Err(IllegalMove(mica_move)) = self.checked_apply_move(mica_move) {
+                panic!("illegal move {mica_move:?}");
+            }
+            return;
+        }
+        self.apply_unchecked(mica_move);
+    }
+
+    fn apply_unchecked(&mut self, mica_move: MicaMove) {
         match mica_move {
             MicaMove::Set { x, y, z } => {
-                self.stones[x as usize][y as usize][z as usize] = self.current_player;
+                self.set_point(x, y, z, self.current_player);
                 self.increment_player();
                 self.decrement_remaining_to_set();
             },
             MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } => {
-                self.stones[from_x as usize][from_y as usize][from_z as usize] = MicaPlayer::None;
-                self.stones[to_x as usize][to_y as usize][to_z as usize] = self.current_player;
+                self.set_point(from_x, from_y, from_z, MicaPlayer::None);
+                self.set_point(to_x, to_y, to_z, self.current_player);
             },
             MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z } => {
-                self.stones[x as usize][y as usize][z as usize] = self.current_player;
-                self.stones[remove_x as usize][remove_y as usize][remove_z as usize] = MicaPlayer::None;
+                self.set_point(x, y, z, self.current_player);
+                self.set_point(remove_x, remove_y, remove_z, MicaPlayer::None);
                 self.increment_player();
                 self.decrement_oponent();
                 self.decrement_remaining_to_set();
             },
             MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z } => {
-                self.stones[from_x as usize][from_y as usize][from_z as usize] = MicaPlayer::None;
-                self.stones[to_x as usize][to_y as usize][to_z as usize] = self.current_player;
-                self.stones[remove_x as usize][remove_y as usize][remove_z as usize] = MicaPlayer::None;
+                self.set_point(from_x, from_y, from_z, MicaPlayer::None);
+                self.set_point(to_x, to_y, to_z, self.current_player);
+                self.set_point(remove_x, remove_y, remove_z, MicaPlayer::None);
                 self.decrement_oponent();
             }
         };
+        match mica_move {
+            MicaMove::Move { .. } => self.quiet_moves = self.quiet_moves.wrapping_add(1),
+            _ => self.quiet_before.push(mem::replace(&mut self.quiet_moves, 0)),
+        }
     }
 
-    fn undo_move(&mut self, mica_move: MicaMove) {
+    pub fn undo_move(&mut self, mica_move: MicaMove) {
         match mica_move {
             MicaMove::Set { x, y, z } => {
-                self.stones[x as usize][y as usize][z as usize] = MicaPlayer::None;
+                self.set_point(x, y, z, MicaPlayer::None);
                 self.decrement_player();
                 self.increment_remaining_to_set();
             },
             MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } => {
-                self.stones[from_x as usize][from_y as usize][from_z as usize] = self.current_player;
-                self.stones[to_x as usize][to_y as usize][to_z as usize] = MicaPlayer::None;
+                self.set_point(from_x, from_y, from_z, self.current_player);
+                self.set_point(to_x, to_y, to_z, MicaPlayer::None);
             },
             MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z } => {
-                self.stones[x as usize][y as usize][z as usize] = MicaPlayer::None;
-                self.stones[remove_x as usize][remove_y as usize][remove_z as usize] = self.current_player.into_next_player();
+                self.set_point(x, y, z, MicaPlayer::None);
+                self.set_point(remove_x, remove_y, remove_z, self.current_player.into_next_player());
                 self.decrement_player();
                 self.increment_oponent();
                 self.increment_remaining_to_set();
             },
             MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z } => {
-                self.stones[from_x as usize][from_y as usize][from_z as usize] = self.current_player;
-                self.stones[to_x as usize][to_y as usize][to_z as usize] = MicaPlayer::None;
-                self.stones[remove_x as usize][remove_y as usize][remove_z as usize] = self.current_player.into_next_player();
+                self.set_point(from_x, from_y, from_z, self.current_player);
+                self.set_point(to_x, to_y, to_z, MicaPlayer::None);
+                self.set_point(remove_x, remove_y, remove_z, self.current_player.into_next_player());
                 self.increment_oponent();
             }
         };
+        match mica_move {
+            MicaMove::Move { .. } => self.quiet_moves = self.quiet_moves.wrapping_sub(1),
+            _ => self.quiet_moves = self.quiet_before.pop().unwrap(),
+        }
+    }
+
+    fn owner_ignoring(&self, x: usize, y: usize, z: usize, ignore: Option<(u8, u8, u8)>) -> MicaPlayer {
+        if ignore == Some((x as u8, y as u8, z as u8)) {
+            MicaPlayer::None
+        } else {
+            self.stones[x][y][z]
+        }
     }
 
-    fn line_check(&self, x: u8, y: u8, z: u8, target_sum: i8) -> bool {
+    fn line_check(&self, x: u8, y: u8, z: u8, player: MicaPlayer, ignore: Option<(u8, u8, u8)>) -> bool {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        // check horizontal line
-        let mut sum = 0;
-        for iz in 0..3 {
-            sum += self.stones[x][y][iz] as i8
-        }
-
-        if sum.abs() == target_sum {
+        // check horizontal line, the middle row goes through the center and is not a line
+        if y != 1 && (0..3).filter(|&iz| iz != z).all(|iz| self.owner_ignoring(x, y, iz, ignore) == player) {
             return true;
         }
 
-        // check vertical line
-        sum = 0;
-        for iy in 0..3 {
-            sum += self.stones[x][iy][z] as i8;
-        }
-
-        if sum.abs() == target_sum {
+        // check vertical line, the middle column goes through the center and is not a line
+        if z != 1 && (0..3).filter(|&iy| iy != y).all(|iy| self.owner_ignoring(x, iy, z, ignore) == player) {
             return true;
         }
 
-        // check cross-square line
-        sum = 0;
-        for ix in 0..3 {
-            sum  += self.stones[ix][y][z] as i8;
-        }
-
-        if sum.abs() == target_sum {
+        // check cross-square line, only middle points are connected across squares
+        if (y == 1) != (z == 1) && (0..3).filter(|&ix| ix != x).all(|ix| self.owner_ignoring(ix, y, z, ignore) == player) {
             return true;
         }
 
         false
     }
 
-    fn is_in_line(&self, x: u8, y: u8, z: u8) -> bool {
-        self.line_check(x, y, z, 3)
+    pub fn is_in_line(&self, x: u8, y: u8, z: u8) -> bool {
+        let player = self.stones[x as usize][y as usize][z as usize];
+        player != MicaPlayer::None && self.line_check(x, y, z, player, None)
     }
 
-    fn will_make_line(&self, x: u8, y: u8, z: u8) -> bool {
-        self.line_check(x, y, z, 2)
+    fn will_make_line(&self, x: u8, y: u8, z: u8, from: Option<(u8, u8, u8)>) -> bool {
+        self.line_check(x, y, z, self.current_player, from)
     }
 
     fn is_setting_phase(&self) -> bool {
-        self.white_to_set > 0 && self.black_to_set > 0
+        match self.current_player {
+            MicaPlayer::White => self.white_to_set > 0,
+            MicaPlayer::Black => self.black_to_set > 0,
+            MicaPlayer::None => false,
+        }
     }
 
-    fn get_neighboaring_empty_spots(&self, x: u8, y: u8, z: u8) -> Vec<(u8, u8, u8)> {
-        let mut spots = Vec::new();
+    fn get_neighboaring_empty_spots(&self, x: u8, y: u8, z: u8) -> impl Iterator<Item = (u8, u8, u8)> + '_ {
+        let mut spots = [None; 6];
 
         // check left spot
         if z > 0 && self.stones[x as usize][y as usize][z as usize - 1] == MicaPlayer::None {
-            spots.push((x, y, z - 1));
+            spots[0] = Some((x, y, z - 1));
         }
 
         // check right spot
         if z < 2 && self.stones[x as usize][y as usize][z as usize + 1] == MicaPlayer::None {
-            spots.push((x, y, z + 1));
+            spots[1] = Some((x, y, z + 1));
         }
 
         // check spot above
         if y > 0 && self.stones[x as usize][y as usize - 1][z as usize] == MicaPlayer::None {
-            spots.push((x, y - 1, z));
+            spots[2] = Some((x, y - 1, z));
         }
 
         // check spot below
         if y < 2 && self.stones[x as usize][y as usize + 1][z as usize] == MicaPlayer::None {
-            spots.push((x, y + 1, z));
+            spots[3] = Some((x, y + 1, z));
         }
 
         // check cross-square neighboaring spots
         if (y == 1 && (z == 0 || z == 2)) || (z == 1 && (y == 0 || y == 2)) {
             if x > 0 && self.stones[x as usize - 1][y as usize][z as usize] == MicaPlayer::None {
-                spots.push((x - 1, y, z));
+                spots[4] = Some((x - 1, y, z));
             }
 
             if x < 2 && self.stones[x as usize + 1][y as usize][z as usize] == MicaPlayer::None {
-                spots.push((x + 1, y, z));
+                spots[5] = Some((x + 1, y, z));
             }
         }
-        
-        spots.into_iter().filter(|(_, y, z)| !(*y == 1 && *z == 1)).collect()
+
+        spots.into_iter().flatten().filter(|(_, y, z)| !(*y == 1 && *z == 1))
     }
 
-    fn get_oponent_stones(&self) -> Vec<(u8, u8, u8)> {
-        let mut opponent_stones = Vec::new();
-        let opponent = self.current_player.into_next_player() as MicaPlayer;
-        for x in 0u8..3 {
-            for y in 0u8..3 {
-                for z in 0u8..3 {
-                    if self.stones[x as usize][y as usize][z as usize] == opponent && !self.is_in_line(x, y, z) {
-                        opponent_stones.push((x, y, z));
+    // whether the quiet moves have reached the draw limit
+    pub fn is_drawn(&self) -> bool {
+        self.draw_after.is_some_and(|draw_after| self.quiet_moves >= draw_after)
+    }
+
+    // score of a finished game, None while it is still in play. The side down
+    // to two stones or without a legal move has lost.
+    pub fn game_over_value(&self) -> Option<i32> {
+        if self.is_drawn() {
+            return Some(0);
+        }
+        if self.is_end() {
+            return Some(if self.white_remaining == 2 { -WIN } else { WIN });
+        }
+        if !self.has_moves() {
+            return Some(-WIN * self.current_player as i32);
+        }
+        None
+    }
+
+    // whether the side to move has a legal move, without generating them: a
+    // mill only ever adds moves to the placement that closes it
+    fn has_moves(&self) -> bool {
+        if self.is_setting_phase() || self.is_flying() {
+            return SORTED_POINTS.iter().any(|&(x, y, z)| self.stone(x, y, z) == MicaPlayer::None);
+        }
+        SORTED_POINTS
+            .iter()
+            .filter(|&&(x, y, z)| self.stone(x, y, z) == self.current_player)
+            .any(|&(x, y, z)| self.get_neighboaring_empty_spots(x, y, z).next().is_some())
+    }
+
+    // whether the side to move may move its stones to any empty point
+    fn is_flying(&self) -> bool {
+        let stones = match self.current_player {
+            MicaPlayer::White => self.white_remaining,
+            _ => self.black_remaining,
+        };
+        self.rules.flying && stones == 3
+    }
+
+    // whether the move puts a stone on a point where the opponent would close a mill
+    pub fn blocks_mill(&self, mica_move: MicaMove) -> bool {
+        let opponent = self.current_player.into_next_player();
+        match mica_move {
+            MicaMove::Set { x, y, z } | MicaMove::SetRemove { x, y, z, .. } => self.line_check(x, y, z, opponent, None),
+            MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } | MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, .. } => {
+                self.line_check(to_x, to_y, to_z, opponent, Some((from_x, from_y, from_z)))
+            },
+        }
+    }
+
+    // legal moves with the ones most likely to cause a cutoff first, see `order_moves`
+    pub fn get_ordered_moves(&self) -> Vec<MicaMove> {
+        let mut arena = BumpArena::new();
+        self.generate_moves(&mut arena);
+        self.order_moves(&mut arena, 0);
+        arena.moves
+    }
+
+    // Sorts the arena's moves from `start` on: mills first, then moves onto
+    // a point that would close an opponent mill, then the rest by their
+    // history score. Moves that tie keep their generation order.
+    pub fn order_moves(&self, arena: &mut BumpArena, start: usize) {
+        // group, inverted history score and generation index packed so plain integer order is the move order
+        arena.keys.clear();
+        for (i, &mica_move) in arena.moves[start..].iter().enumerate() {
+            let group: u64 = match mica_move {
+                MicaMove::SetRemove { .. } | MicaMove::MoveRemove { .. } => 0,
+                _ if self.blocks_mill(mica_move) => 1,
+                _ => 2,
+            };
+            let score = self.history.score(self.current_player, mica_move);
+            arena.keys.push(group << 48 | ((u32::MAX - score) as u64) << 16 | i as u64);
+        }
+        arena.keys.sort_unstable();
+
+        // the sorted moves go on top of the generated ones and then take their place
+        let end = arena.moves.len();
+        for i in 0..arena.keys.len() {
+            let mica_move = arena.moves[start + (arena.keys[i] & 0xffff) as usize];
+            arena.moves.push(mica_move);
+        }
+        arena.moves.copy_within(end.., start);
+        arena.release(end);
+    }
+
+    // Opponent stones a mill may take. Stones in a mill can only be removed
+    // when there is nothing else to remove, and not even then without
+    // `Rules::mill_fallback`.
+    fn get_oponent_stones(&self) -> impl Iterator<Item = (u8, u8, u8)> + '_ {
+        let opponent = self.current_player.into_next_player();
+        let stones = cells().filter(move |&(x, y, z)| self.stone(x, y, z) == opponent);
+        let all_in_mills = self.rules.mill_fallback && stones.clone().all(|(x, y, z)| self.is_in_line(x, y, z));
+        stones.filter(move |&(x, y, z)| all_in_mills || !self.is_in_line(x, y, z))
+    }
+
+    // The stone set or moved to `to`, once for every stone it takes when it
+    // closes a mill, a mill with nothing to take takes nothing. The stones a
+    // mill may take are found for the first mill of a position and kept in
+    // the arena for the others, `removals_found` says whether they are.
+    fn push_placement(&self, arena: &mut BumpArena, removals_found: &mut bool, from: Option<(u8, u8, u8)>, (x, y, z): (u8, u8, u8)) {
+        let mills = self.will_make_line(x, y, z, from);
+        if mills && !*removals_found {
+            arena.removals.clear();
+            arena.removals.extend(self.get_oponent_stones());
+            *removals_found = true;
+        }
+        if mills && !arena.removals.is_empty() {
+            for &(remove_x, remove_y, remove_z) in arena.removals.iter() {
+                arena.moves.push(match from {
+                    None => MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z },
+                    Some((from_x, from_y, from_z)) => MicaMove::MoveRemove { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z, remove_x, remove_y, remove_z },
+                });
+            }
+        } else {
+            arena.moves.push(match from {
+                None => MicaMove::Set { x, y, z },
+                Some((from_x, from_y, from_z)) => MicaMove::Move { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z },
+            });
+        }
+    }
+
+    // every legal move on top of the arena's moves, in the same order as `get_moves`
+    pub fn generate_moves(&self, arena: &mut BumpArena) {
+        let mut removals_found = false;
+        if self.is_setting_phase() {
+            for &(x, y, z) in SORTED_POINTS.iter() {
+                if self.stones[x as usize][y as usize][z as usize] == MicaPlayer::None {
+                    self.push_placement(arena, &mut removals_found, None, (x, y, z));
+                }
+            }
+        } else {
+            let flying = self.is_flying();
+            for &from in SORTED_POINTS.iter() {
+                let (from_x, from_y, from_z) = from;
+                if self.stones[from_x as usize][from_y as usize][from_z as usize] != self.current_player {
+                    continue;
+                }
+                if flying {
+                    for &to in SORTED_POINTS.iter().filter(|&&(x, y, z)| self.stone(x, y, z) == MicaPlayer::None) {
+                        self.push_placement(arena, &mut removals_found, Some(from), to);
+                    }
+                } else {
+                    for to in self.get_neighboaring_empty_spots(from_x, from_y, from_z) {
+                        self.push_placement(arena, &mut removals_found, Some(from), to);
                     }
                 }
             }
         }
-
-        opponent_stones
     }
 }
 
@@ -352,121 +1159,226 @@ impl Minimax for MicaState {
 
     fn is_end(&self) -> bool {
         (self.white_to_set == 0 && self.black_to_set == 0) &&
-        (self.white_remaining == 2 || self.black_remaining == 2)
+        (self.white_remaining == 2 || self.black_remaining == 2) ||
+        self.is_drawn()
     }
 
     fn eval(&self) -> i32 {
-        self.white_remaining as i32 - self.black_remaining as i32
+        #[cfg(feature = "nn")]
+        if let Some(network) = &self.network {
+            return network.evaluate(self);
+        }
+        evaluate(self, &self.weights)
     }
 
     fn get_moves(&self) -> Vec<Self::Move> {
-        let mut moves = Vec::new();
-        if self.is_setting_phase() {
-            for x in 0u8..3 {
-                for y in 0u8..3 {
-                    for z in 0u8..3 {
-                        if y == 1 && z == 1 {
-                            continue;
-                        }
-                        if self.stones[x as usize][y as usize][z as usize] == MicaPlayer::None {
-                            let next_move = MicaMove::Set { x, y, z};
-                            if self.will_make_line(x, y, z) {
-                                let empty_spots = self.get_oponent_stones();
-                                for (remove_x, remove_y, remove_z) in empty_spots {
-                                    moves.push(MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z })
-                                }
-                            } else {
-                                moves.push(next_move);
-                            }
-                        }
-                    }
-                }
-            }
+        let mut arena = BumpArena::new();
+        self.generate_moves(&mut arena);
+        arena.moves
+    }
+
+    fn negamax(&mut self, depth: u8, a: i32, b: i32) -> (Self::Value, Option<Self::Move>) {
+        self.nodes += 1;
+        self.max_ply = self.max_ply.max(self.ply);
+        if self.arena.pv.len() <= depth as usize {
+            self.arena.pv.resize(depth as usize + 1, Vec::new());
+        }
+        self.arena.pv[depth as usize].clear();
+        // looking at the clock is slow next to a node, once in a while is enough
+        if self.nodes.is_multiple_of(STOP_CHECK_NODES) && !self.aborted {
+            self.take_pause();
+            let cancelled = self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+            self.aborted = self.past_deadline() || cancelled;
+        }
+        if self.aborted {
+            return (0, None);
+        }
+        // i32::MIN has no negation, windows stay symmetric around zero
+        let (a, b) = (a.max(-i32::MAX), b.max(-i32::MAX));
+        let side = self.current_player as i32;
+        if self.is_end() {
+            return (self.game_over_at_ply().unwrap() * side, None);
+        }
+        if depth == 0 {
+            return (self.leaf_value() * side, None);
+        }
+        // the node's moves go on top of the arena and are given back however it returns
+        let mut arena = mem::take(&mut self.arena);
+        let start = arena.mark();
+        self.generate_moves(&mut arena);
+        self.order_moves(&mut arena, start);
+        let end = arena.mark();
+        self.arena = arena;
+        let result = self.search_moves(depth, a, b, start, end);
+        self.arena.release(start);
+        result
+    }
+
+    fn minimax(&mut self, depth: u8, a: i32, b: i32) -> (Self::Value, Option<Self::Move>) {
+        self.aborted = false;
+        if self.current_player == MicaPlayer::Black {
+            let (value, best_move) = self.negamax(depth, b.saturating_neg(), a.saturating_neg());
+            (-value, best_move)
         } else {
-            for from_x in 0u8..3 {
-                for from_y in 0u8..3 {
-                    for from_z in 0u8..3 {
-                        if from_y == 1 && from_z == 1 {
-                            continue;
-                        }
-                        if self.stones[from_x as usize][from_y as usize][from_z as usize] == MicaPlayer::None {
-                            let neighboaring_empty_spots = self.get_neighboaring_empty_spots(from_x, from_y, from_z);
-                            for (to_x, to_y, to_z) in neighboaring_empty_spots {
-                                let next_move = MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z };
-                                if self.will_make_line(to_x, to_y, to_z) {
-                                    let empty_spots = self.get_oponent_stones();
-                                    for (remove_x, remove_y, remove_z) in empty_spots {
-                                        moves.push(MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z })
-                                    }
-                                } else {
-                                    moves.push(next_move);
-                                }
-                            }
-                        }
-                    }
-                }
+            self.negamax(depth, a, b)
+        }
+    }
+}
+
+// A won or lost game's score from the root of a search, see `WIN`, turned
+// into its score from a position `ply` plies below the root, as the
+// transposition table keeps it for every search that reaches that position.
+fn to_node(value: i32, ply: u8) -> i32 {
+    match value.abs() >= DECISIVE {
+        true => value + value.signum() * ply as i32,
+        false => value,
+    }
+}
+
+// `to_node` undone
+fn from_node(value: i32, ply: u8) -> i32 {
+    match value.abs() >= DECISIVE {
+        true => value - value.signum() * ply as i32,
+        false => value,
+    }
+}
+
+impl MicaState {
+    // the part of `negamax` past the leaves, over the arena's moves from `start` to `end`
+    fn search_moves(&mut self, depth: u8, mut a: i32, b: i32, start: usize, end: usize) -> (i32, Option<MicaMove>) {
+        // a side without moves has lost, leaves skip this check to save generating them
+        if start == end {
+            return (self.game_over_at_ply().unwrap() * self.current_player as i32, None);
+        }
+
+        // a stored result deep enough to decide this node ends the search here,
+        // any other stored best move is at least searched first. Entries are
+        // shared by symmetric positions, their moves are stored for the
+        // canonical image, won and lost games are stored by their distance
+        // from the entry's own position, see `from_node`.
+        let (key, symmetry) = self.canonical_zobrist();
+        if let Some(mut entry) = self.tt.as_ref().and_then(|tt| tt.probe(key)) {
+            entry.best_move = entry.best_move.map(|mica_move| symmetry.inverse().apply_move(mica_move));
+            entry.value = from_node(entry.value, self.ply);
+            let decides = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.value >= b,
+                Bound::Upper => entry.value <= a,
+            };
+            if entry.depth >= depth && decides {
+                self.tt_hits += 1;
+                self.arena.pv[depth as usize].extend(entry.best_move);
+                return (entry.value, entry.best_move);
+            }
+            let moves = &mut self.arena.moves[start..end];
+            if let Some(i) = moves.iter().position(|&mica_move| Some(mica_move) == entry.best_move) {
+                moves[..=i].rotate_right(1);
+            }
+        }
+        let a0 = a;
+
+        let mut best_value = -i32::MAX;
+        let mut best_move = None;
+        for i in start..end {
+            let next_move = self.arena.moves[i];
+            self.apply_move(next_move);
+            self.current_player.toggle();
+            self.ply += 1;
+            let value = -self.negamax(depth - 1, -b, -a).0;
+            self.ply -= 1;
+            self.current_player.toggle();
+            self.undo_move(next_move);
+            if self.aborted {
+                return (0, None);
+            }
+            if best_move.is_none() || value > best_value {
+                best_value = value;
+                best_move = Some(next_move);
+                self.update_pv(depth, next_move);
+            }
+            if value >= b {
+                self.beta_cutoffs += 1;
+                self.history.record_cutoff(self.current_player, next_move, depth);
+                break;
             }
+            a = a.max(value);
         }
 
-        moves
+        if let Some(tt) = &self.tt {
+            let bound = if best_value <= a0 {
+                Bound::Upper
+            } else if best_value >= b {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            tt.store(key, Entry { depth, bound, value: to_node(best_value, self.ply), best_move: best_move.map(|mica_move| symmetry.apply_move(mica_move)) });
+        }
+        (best_value, best_move)
     }
+}
 
-    fn minimax(&mut self, depth: u8, mut a: i32, mut b: i32) -> (Self::Value, Option<Self::Move>) {
-        if depth == 0 {
-            return (self.eval(), None);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the movement phase with the given stones on the board
+    fn position(white: &[(u8, u8, u8)], black: &[(u8, u8, u8)], to_move: MicaPlayer) -> MicaState {
+        let mut state = MicaState::new();
+        state.set_stones_to_set(0, 0);
+        for &(x, y, z) in white {
+            state.put_stone(x, y, z, MicaPlayer::White);
         }
-        if self.is_end() {
-            return (self.eval(), None);
+        for &(x, y, z) in black {
+            state.put_stone(x, y, z, MicaPlayer::Black);
         }
+        state.current_player = to_move;
+        state
+    }
 
-        match self.current_player {
-            MicaPlayer::White => {
-                let mut best_value = i32::MIN;
-                let mut best_move = None;
-                // TODO: zero iterations needs eval
-                let moves = self.get_moves();
-                for next_move in moves {
-                    self.apply_move(next_move);
-                    self.current_player.toggle();
-                    let new_value = self.minimax(depth - 1, a, b).0;
-                    self.current_player.toggle();
-                    if best_move == None || new_value > best_value {
-                        best_value = new_value;
-                        best_move = Some(next_move);
-                    }
-                    self.undo_move(next_move);
-                    if new_value > b {
-                        break;
-                    }
-                    a = a.max(new_value);
-                }
+    // four white stones in the top-left and bottom-right corners of the outer ring, walled in by black
+    fn blocked(to_move: MicaPlayer) -> MicaState {
+        let white = [(0, 0, 0), (0, 0, 1), (0, 1, 0), (0, 2, 2)];
+        let black = [(0, 0, 2), (1, 0, 1), (0, 2, 0), (1, 1, 0), (0, 2, 1), (0, 1, 2)];
+        match to_move {
+            MicaPlayer::White => position(&white, &black, to_move),
+            _ => position(&black, &white, to_move),
+        }
+    }
 
-                (best_value, best_move)
-            },
-            MicaPlayer::Black => {
-                let mut best_value = i32::MAX;
-                let mut best_move = None;
-                // TODO: zero iterations needs eval
-                let moves = self.get_moves();
-                for next_move in moves {
-                    self.apply_move(next_move);
-                    self.current_player.toggle();
-                    let new_value = self.minimax(depth - 1, a, b).0;
-                    self.current_player.toggle();
-                    if best_move == None || new_value < best_value {
-                        best_value = new_value;
-                        best_move = Some(next_move);
-                    }
-                    self.undo_move(next_move);
-                    if new_value < a {
-                        break;
-                    }
-                    b = b.min(new_value);
-                }
+    #[test]
+    fn a_blocked_side_loses() {
+        let mut state = blocked(MicaPlayer::White);
+        assert!(state.get_moves().is_empty());
+        assert_eq!(state.game_over_value(), Some(-WIN));
+        assert_eq!(state.negamax(3, -i32::MAX, i32::MAX).0, -WIN);
 
-                (best_value, best_move)
-            },
-            MicaPlayer::None => panic!("Reached invalid state of None player"),
+        let mut state = blocked(MicaPlayer::Black);
+        assert_eq!(state.game_over_value(), Some(WIN));
+        // found three plies below the root of a search
+        state.set_ply(3);
+        assert_eq!(state.negamax(3, -i32::MAX, i32::MAX).0, -(WIN - 3));
+    }
+
+    #[test]
+    fn a_side_down_to_two_stones_loses() {
+        let mut state = position(&[(0, 0, 0), (0, 0, 1), (0, 1, 2)], &[(2, 0, 0), (2, 2, 2)], MicaPlayer::Black);
+        assert_eq!(state.game_over_value(), Some(WIN));
+        assert_eq!(state.negamax(3, -i32::MAX, i32::MAX).0, -WIN);
+
+        let mut state = position(&[(2, 0, 0), (2, 2, 2)], &[(0, 0, 0), (0, 0, 1), (0, 1, 2)], MicaPlayer::White);
+        assert_eq!(state.game_over_value(), Some(-WIN));
+        state.set_ply(2);
+        assert_eq!(state.negamax(3, -i32::MAX, i32::MAX).0, -(WIN - 2));
+    }
+
+    #[test]
+    fn a_win_is_scored_by_its_distance() {
+        // white closes the top of the outer ring and takes black's third stone
+        let mut state = position(&[(0, 0, 0), (0, 0, 1), (0, 1, 2), (1, 0, 1)], &[(2, 0, 0), (2, 2, 2), (1, 2, 0)], MicaPlayer::White);
+        assert_eq!(state.game_over_value(), None);
+        for depth in 1..=3 {
+            assert_eq!(state.negamax(depth, -i32::MAX, i32::MAX).0, WIN - 1, "depth {depth}");
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::Deserialize;
+
+use crate::minimax::MicaState;
+use crate::pool::Batcher;
+use crate::variants::STONES_PER_PLAYER;
+
+// A small fully-connected network that evaluates positions instead of
+// `eval::evaluate`, built with the `nn` feature. It sees a position the way
+// `mica export` writes it, from the side to move: 24 inputs for its stones,
+// 24 for the opponent's, one per point in the order of `coords::POINTS`,
+// then the stones each still has to set. One hidden layer of ReLUs feeds a
+// tanh output, the expected result for the side to move from -1 to 1.
+//
+// A weights file is JSON, a row of 50 input weights per hidden unit:
+//
+//     {"hidden": [[...], ...], "hidden_bias": [...], "output": [...], "output_bias": 0.0}
+
+pub const INPUTS: usize = 50;
+
+#[derive(Deserialize)]
+struct WeightsFile {
+    hidden: Vec<Vec<f32>>,
+    hidden_bias: Vec<f32>,
+    output: Vec<f32>,
+    output_bias: f32,
+}
+
+#[derive(Clone)]
+pub struct Network {
+    // INPUTS weights per hidden unit, one unit after the other
+    hidden: Vec<f32>,
+    hidden_bias: Vec<f32>,
+    output: Vec<f32>,
+    output_bias: f32,
+    // evaluates the positions of every thread searching with this network together, see `batched`
+    batcher: Option<Arc<Batcher<[f32; INPUTS], f32>>>,
+}
+
+// states holding a network print its size, not every weight in it
+impl fmt::Debug for Network {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Network({} hidden units)", self.output.len())
+    }
+}
+
+impl Network {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: WeightsFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let units = file.hidden.len();
+        if let Some(i) = file.hidden.iter().position(|row| row.len() != INPUTS) {
+            return Err(format!("hidden unit {i} has {} weights, not {INPUTS}", file.hidden[i].len()));
+        }
+        if file.hidden_bias.len() != units || file.output.len() != units {
+            return Err(format!(
+                "{units} hidden units but {} hidden biases and {} output weights",
+                file.hidden_bias.len(),
+                file.output.len()
+            ));
+        }
+        Ok(Network {
+            hidden: file.hidden.concat(),
+            hidden_bias: file.hidden_bias,
+            output: file.output,
+            output_bias: file.output_bias,
+            batcher: None,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Network::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // The same network evaluating the leaves of every search using it in
+    // batches of up to `max_batch` positions, each waiting at most
+    // `max_wait` for the batch to fill. A batch of one evaluates each leaf
+    // on its own, as the network does without a batcher.
+    pub fn batched(self, max_batch: usize, max_wait: Duration) -> Self {
+        let network = self.clone();
+        let run = Box::new(move |inputs: Vec<[f32; INPUTS]>| inputs.iter().map(|inputs| network.forward(inputs)).collect());
+        Network { batcher: Some(Arc::new(Batcher::new(max_batch, max_wait, run))), ..self }
+    }
+
+    // white's advantage in stones, a sure win for a side worth all of its stones
+    pub fn evaluate(&self, state: &MicaState) -> i32 {
+        let inputs = inputs(state);
+        let value = match &self.batcher {
+            Some(batcher) => batcher.eval(inputs),
+            None => self.forward(&inputs),
+        };
+        (value * STONES_PER_PLAYER as f32).round() as i32 * state.current_player as i32
+    }
+
+    // the expected result for the side to move, from -1 to 1
+    fn forward(&self, inputs: &[f32; INPUTS]) -> f32 {
+        let hidden = self
+            .hidden
+            .chunks_exact(INPUTS)
+            .zip(&self.hidden_bias)
+            .map(|(weights, bias)| (weights.iter().zip(inputs).map(|(w, x)| w * x).sum::<f32>() + bias).max(0.0));
+        (hidden.zip(&self.output).map(|(h, w)| h * w).sum::<f32>() + self.output_bias).tanh()
+    }
+}
+
+// the position from the side to move, see `Network`
+fn inputs(state: &MicaState) -> [f32; INPUTS] {
+    let (white, black) = state.occupancy();
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    let (mine, theirs, to_set) = match state.current_player as i8 {
+        1 => (white, black, [white_to_set, black_to_set]),
+        _ => (black, white, [black_to_set, white_to_set]),
+    };
+    let mut inputs = [0.0; INPUTS];
+    for point in 0..24 {
+        inputs[point] = (mine >> point & 1) as f32;
+        inputs[24 + point] = (theirs >> point & 1) as f32;
+    }
+    inputs[48] = to_set[0] as f32;
+    inputs[49] = to_set[1] as f32;
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_evaluate_like_single_positions() {
+        let row = |weight: f32| format!("[{}]", vec![weight.to_string(); INPUTS].join(","));
+        let text = format!(r#"{{"hidden": [{}, {}], "hidden_bias": [0.1, -0.2], "output": [0.5, -0.3], "output_bias": 0.05}}"#, row(0.2), row(-0.1));
+        let network = Network::parse(&text).unwrap();
+        let batched = network.clone().batched(4, Duration::from_millis(1));
+
+        let mut state = MicaState::new();
+        for (x, y, z) in [(0, 0, 0), (1, 0, 1), (2, 1, 2)] {
+            state.put_stone(x, y, z, crate::minimax::MicaPlayer::White);
+        }
+        assert_eq!(batched.evaluate(&state), network.evaluate(&state));
+        assert_eq!(batched.batched(1, Duration::ZERO).evaluate(&state), network.evaluate(&state));
+    }
+}
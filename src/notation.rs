@@ -0,0 +1,215 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::coords::{from_notation, to_notation, POINTS};
+use crate::gym::{decode_action, encode_action};
+use crate::minimax::*;
+use crate::packed;
+
+// Position encodings understood by `convert`:
+//
+// - json: the server request, stones as a 3x3x3 array
+// - flat: `{"board": [24 values], "player": 1, "white_to_set": 9, "black_to_set": 9}`
+//   with the board in flat point order
+// - fen: `<ring 0>/<ring 1>/<ring 2> <w|b> <white to set> <black to set>` where
+//   every ring is 8 characters of `W`, `B` or `.` in flat point order
+// - packed: the 16 hex digits of the position's `packed` key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionFormat {
+    Json,
+    Flat,
+    Fen,
+    Packed,
+}
+
+// Move list encodings: gym action indices or a1-g7 moves such as `d7`,
+// `a7-a4`, `d7xg7` and `a7-a4xg1`, both separated by whitespace or commas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveFormat {
+    Actions,
+    Notation,
+}
+
+#[derive(Deserialize)]
+struct FlatPosition {
+    board: Vec<i8>,
+    player: i8,
+    white_to_set: u8,
+    black_to_set: u8,
+}
+
+fn player_from_i8(value: i8) -> Result<MicaPlayer, String> {
+    match value {
+        1 => Ok(MicaPlayer::White),
+        -1 => Ok(MicaPlayer::Black),
+        0 => Ok(MicaPlayer::None),
+        _ => Err(format!("invalid stone value {value}")),
+    }
+}
+
+pub fn parse_position(format: PositionFormat, input: &str) -> Result<MicaState, String> {
+    match format {
+        PositionFormat::Json => {
+            let request: MicaRequest = serde_json::from_str(input).map_err(|e| e.to_string())?;
+            Ok(MicaState::from_request(request))
+        },
+        PositionFormat::Flat => {
+            let flat: FlatPosition = serde_json::from_str(input).map_err(|e| e.to_string())?;
+            if flat.board.len() != POINTS.len() {
+                return Err(format!("expected {} board values, got {}", POINTS.len(), flat.board.len()));
+            }
+            let mut state = MicaState::new();
+            for (&(x, y, z), &value) in POINTS.iter().zip(flat.board.iter()) {
+                state.put_stone(x, y, z, player_from_i8(value)?);
+            }
+            state.current_player = player_from_i8(flat.player)?;
+            state.set_stones_to_set(flat.white_to_set, flat.black_to_set);
+            Ok(state)
+        },
+        PositionFormat::Fen => {
+            let parts: Vec<&str> = input.split_whitespace().collect();
+            if parts.len() != 4 {
+                return Err(format!("expected `<rings> <w|b> <white to set> <black to set>`, got `{input}`"));
+            }
+            let board: String = parts[0].split('/').collect();
+            if board.len() != POINTS.len() || parts[0].split('/').count() != 3 {
+                return Err(format!("expected three rings of 8 points, got `{}`", parts[0]));
+            }
+
+            let mut state = MicaState::new();
+            for (&(x, y, z), c) in POINTS.iter().zip(board.chars()) {
+                let player = match c {
+                    'W' | 'w' => MicaPlayer::White,
+                    'B' | 'b' => MicaPlayer::Black,
+                    '.' => MicaPlayer::None,
+                    c => return Err(format!("invalid point `{c}`")),
+                };
+                state.put_stone(x, y, z, player);
+            }
+            state.current_player = match parts[1] {
+                "w" => MicaPlayer::White,
+                "b" => MicaPlayer::Black,
+                side => return Err(format!("invalid side to move `{side}`")),
+            };
+            let white_to_set = parts[2].parse().map_err(|_| format!("invalid white stones to set `{}`", parts[2]))?;
+            let black_to_set = parts[3].parse().map_err(|_| format!("invalid black stones to set `{}`", parts[3]))?;
+            state.set_stones_to_set(white_to_set, black_to_set);
+            Ok(state)
+        },
+        PositionFormat::Packed => {
+            if input.len() != 16 {
+                return Err(format!("expected 16 hex digits, got `{input}`"));
+            }
+            let key = u64::from_str_radix(input, 16).map_err(|_| format!("expected 16 hex digits, got `{input}`"))?;
+            packed::decode(key)
+        },
+    }
+}
+
+pub fn format_position(format: PositionFormat, state: &MicaState) -> String {
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    let board: Vec<i8> = POINTS.iter().map(|&(x, y, z)| state.stone(x, y, z) as i8).collect();
+
+    match format {
+        PositionFormat::Json => {
+            let mut stones = [[[0i8; 3]; 3]; 3];
+            for &(x, y, z) in POINTS.iter() {
+                stones[x as usize][y as usize][z as usize] = state.stone(x, y, z) as i8;
+            }
+            let count = |player: i8| board.iter().filter(|&&value| value == player).count();
+            json!({
+                "difficulty": "",
+                "player": state.current_player as i8,
+                "white_remaining": white_to_set,
+                "black_remaining": black_to_set,
+                "white_count": count(1),
+                "black_count": count(-1),
+                "stones": stones,
+            }).to_string()
+        },
+        PositionFormat::Flat => json!({
+            "board": board,
+            "player": state.current_player as i8,
+            "white_to_set": white_to_set,
+            "black_to_set": black_to_set,
+        }).to_string(),
+        PositionFormat::Fen => {
+            let points: String = board.iter().map(|&value| match value {
+                1 => 'W',
+                -1 => 'B',
+                _ => '.',
+            }).collect();
+            let side = if state.current_player == MicaPlayer::Black { "b" } else { "w" };
+            format!("{}/{}/{} {side} {white_to_set} {black_to_set}", &points[..8], &points[8..16], &points[16..])
+        },
+        PositionFormat::Packed => format!("{:016x}", packed::encode(state)),
+    }
+}
+
+fn point_notation((x, y, z): (u8, u8, u8)) -> String {
+    to_notation(x, y, z).unwrap()
+}
+
+pub fn move_to_notation(mica_move: MicaMove) -> String {
+    match mica_move {
+        MicaMove::Set { x, y, z } => point_notation((x, y, z)),
+        MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } => {
+            format!("{}-{}", point_notation((from_x, from_y, from_z)), point_notation((to_x, to_y, to_z)))
+        },
+        MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z } => {
+            format!("{}x{}", point_notation((x, y, z)), point_notation((remove_x, remove_y, remove_z)))
+        },
+        MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z } => {
+            format!(
+                "{}-{}x{}",
+                point_notation((from_x, from_y, from_z)),
+                point_notation((to_x, to_y, to_z)),
+                point_notation((remove_x, remove_y, remove_z))
+            )
+        },
+    }
+}
+
+pub fn move_from_notation(notation: &str) -> Option<MicaMove> {
+    let (placement, remove) = match notation.split_once('x') {
+        Some((placement, remove)) => (placement, Some(from_notation(remove)?)),
+        None => (notation, None),
+    };
+    let (from, to) = match placement.split_once('-') {
+        Some((from, to)) => (Some(from_notation(from)?), from_notation(to)?),
+        None => (None, from_notation(placement)?),
+    };
+
+    let (x, y, z) = to;
+    let mica_move = match (from, remove) {
+        (None, None) => MicaMove::Set { x, y, z },
+        (None, Some((remove_x, remove_y, remove_z))) => MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z },
+        (Some((from_x, from_y, from_z)), None) => MicaMove::Move { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z },
+        (Some((from_x, from_y, from_z)), Some((remove_x, remove_y, remove_z))) => {
+            MicaMove::MoveRemove { from_x, from_y, from_z, to_x: x, to_y: y, to_z: z, remove_x, remove_y, remove_z }
+        },
+    };
+    Some(mica_move)
+}
+
+pub fn parse_moves(format: MoveFormat, input: &str) -> Result<Vec<MicaMove>, String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let mica_move = match format {
+                MoveFormat::Actions => token.parse().ok().and_then(decode_action),
+                MoveFormat::Notation => move_from_notation(token),
+            };
+            mica_move.ok_or(format!("invalid move `{token}`"))
+        })
+        .collect()
+}
+
+pub fn format_moves(format: MoveFormat, moves: &[MicaMove]) -> String {
+    let tokens: Vec<String> = match format {
+        MoveFormat::Actions => moves.iter().map(|&mica_move| encode_action(mica_move).to_string()).collect(),
+        MoveFormat::Notation => moves.iter().map(|&mica_move| move_to_notation(mica_move)).collect(),
+    };
+    tokens.join(" ")
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use mica::coords::{from_notation, to_notation};
+use mica::packed;
+use mica::symmetry::Symmetry;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// An arrow drawn on the board, both ends in notation like `a1`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Arrow {
+    pub from: String,
+    pub to: String,
+}
+
+// What people have to say about a position, kept by canonical position key
+// so every symmetric image of it shares the notes. Arrows are drawn on the
+// canonical image.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub arrows: Vec<Arrow>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Annotation {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let errors: Vec<String> = self
+            .arrows
+            .iter()
+            .flat_map(|arrow| [&arrow.from, &arrow.to])
+            .filter(|point| from_notation(point).is_none())
+            .map(|point| format!("`{point}` is not a point on the board"))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // the same notes with the arrows drawn on the image of the position under `symmetry`
+    pub fn transformed(&self, symmetry: &Symmetry) -> Annotation {
+        let point = |notation: &str| {
+            let (x, y, z) = symmetry.apply_point(from_notation(notation).unwrap());
+            to_notation(x, y, z).unwrap()
+        };
+        Annotation {
+            text: self.text.clone(),
+            arrows: self.arrows.iter().map(|arrow| Arrow { from: point(&arrow.from), to: point(&arrow.to) }).collect(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+// Annotations in an SQLite database, one row per position.
+pub struct Notes {
+    connection: Mutex<Connection>,
+}
+
+impl Notes {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS notes (
+                position TEXT PRIMARY KEY,
+                text TEXT NOT NULL,
+                arrows TEXT NOT NULL,
+                tags TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            (),
+        )?;
+        Ok(Notes { connection: Mutex::new(connection) })
+    }
+
+    pub fn get(&self, key: u64) -> rusqlite::Result<Option<Annotation>> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row("SELECT text, arrows, tags FROM notes WHERE position = ?1", params![position(key)], |row| {
+                let arrows: String = row.get(1)?;
+                let tags: String = row.get(2)?;
+                Ok(Annotation {
+                    text: row.get(0)?,
+                    arrows: serde_json::from_str(&arrows).unwrap(),
+                    tags: serde_json::from_str(&tags).unwrap(),
+                })
+            })
+            .optional()
+    }
+
+    // replaces whatever was kept for the position
+    pub fn put(&self, key: u64, annotation: &Annotation) -> rusqlite::Result<()> {
+        let updated_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO notes (position, text, arrows, tags, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                position(key),
+                annotation.text,
+                serde_json::to_string(&annotation.arrows).unwrap(),
+                serde_json::to_string(&annotation.tags).unwrap(),
+                updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    // whether there was anything to delete
+    pub fn delete(&self, key: u64) -> rusqlite::Result<bool> {
+        let deleted = self.connection.lock().unwrap().execute("DELETE FROM notes WHERE position = ?1", params![position(key)])?;
+        Ok(deleted > 0)
+    }
+}
+
+// keys are stored the way they appear in urls, SQLite integers are signed
+pub fn position(key: u64) -> String {
+    format!("{key:016x}")
+}
+
+// a hash that packs no position, see `packed`, has no notes either
+pub fn parse_position(hash: &str) -> Option<u64> {
+    if hash.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(hash, 16).ok().filter(|&key| packed::decode(key).is_ok())
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use mica::book::OpeningBook;
+use mica::gym::{decode_action, Env};
+use mica::pool::Pool;
+use mica::selfplay::{self, SelfPlayConfig};
+
+use crate::option;
+
+// Builds an opening book from self-play games.
+//
+//     mica book [--out mica-book.txt] [--games N] [--depth N] [--plies N] [--random-plies N] [--min-games N] [--seed N] [--threads N]
+//
+// Every game counts for the moves of its first --plies plies that set a
+// stone, with the win, draw and loss written next to each move. A move's
+// weight is the points its games scored for the side that played it, two
+// for a win and one for a draw, moves played in fewer than --min-games games
+// get none. The first --random-plies plies of every game are random, the
+// engine alone would play the same game every time.
+pub fn run(args: &[String]) {
+    let out: String = option(args, "--out", "mica-book.txt".to_string());
+    let games: u64 = option(args, "--games", 200);
+    let plies: usize = option(args, "--plies", 8);
+    let min_games: u32 = option(args, "--min-games", 3);
+    let seed: u64 = option(args, "--seed", 0);
+    let config = SelfPlayConfig {
+        depth: option(args, "--depth", 5),
+        random_plies: option(args, "--random-plies", 2),
+        max_plies: option(args, "--max-plies", SelfPlayConfig::default().max_plies),
+        #[cfg(feature = "nn")]
+        network: None,
+    };
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let pool = Arc::new(Pool::new());
+    Arc::clone(&pool).init(option(args, "--threads", cores));
+
+    let mut book = OpeningBook::default();
+    let (mut wins, mut draws) = (0, 0);
+    for record in selfplay::play_games(&pool, config, seed..seed + games) {
+        match record.result {
+            1 => wins += 1,
+            0 => draws += 1,
+            _ => {},
+        }
+        let mut env = Env::new();
+        for &action in record.moves.iter().take(plies) {
+            if env.state().stones_to_set() == (0, 0) {
+                break;
+            }
+            let state = env.state().clone();
+            book.record(&state, decode_action(action).unwrap(), record.result);
+            env.step(action).unwrap();
+        }
+    }
+    book.reweigh(min_games);
+
+    if let Err(e) = fs::write(&out, book.to_text()) {
+        eprintln!("{out}: {e}");
+        std::process::exit(1);
+    }
+    eprintln!("{games} games, white won {wins}, {draws} drawn: wrote {} positions to {out}", book.len());
+}
@@ -0,0 +1,70 @@
+use alloc::format;
+use alloc::string::String;
+
+use crate::coords::POINTS;
+use crate::minimax::{MicaPlayer, MicaState};
+use crate::variants::STONES_PER_PLAYER;
+
+// Positions packed into 57 bits of a u64, for storing and sending millions
+// of them. From the top down: two bits per point in `POINTS` order, 0 empty,
+// 1 white and 2 black, then four bits each for the stones white and black
+// have left to set and one bit set when black is to move. The seven bits
+// above are zero. Stored and sent as the 8 bytes of the key, most
+// significant first, or as their 16 hex digits, which is how book files and
+// position notes already write the keys of canonical images.
+//
+// Only the board, the stones to set and the side to move are packed. Rule
+// options, the quiet move count and everything a search keeps in a state
+// are not, decoded states play by the default rules.
+
+// bits below the first point
+const POINTS_SHIFT: u32 = 9;
+const USED_BITS: u32 = POINTS_SHIFT + 2 * POINTS.len() as u32;
+
+pub fn encode(state: &MicaState) -> u64 {
+    let mut key = 0u64;
+    for &(x, y, z) in POINTS.iter() {
+        let bits = match state.stone(x, y, z) {
+            MicaPlayer::None => 0,
+            MicaPlayer::White => 1,
+            MicaPlayer::Black => 2,
+        };
+        key = key << 2 | bits;
+    }
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    key = key << 4 | (white_to_set & 0xf) as u64;
+    key = key << 4 | (black_to_set & 0xf) as u64;
+    key << 1 | (state.current_player == MicaPlayer::Black) as u64
+}
+
+// the position of a key from `encode`, which keys no position has is checked
+pub fn decode(key: u64) -> Result<MicaState, String> {
+    if key >> USED_BITS != 0 {
+        return Err(format!("{key:016x} has bits set above the position"));
+    }
+    let mut state = MicaState::new();
+    for (i, &(x, y, z)) in POINTS.iter().enumerate() {
+        let player = match key >> (USED_BITS - 2 * (i as u32 + 1)) & 3 {
+            0 => MicaPlayer::None,
+            1 => MicaPlayer::White,
+            2 => MicaPlayer::Black,
+            _ => return Err(format!("{key:016x} has no stone value for point {i}")),
+        };
+        state.put_stone(x, y, z, player);
+    }
+    let (white_to_set, black_to_set) = ((key >> 5 & 0xf) as u8, (key >> 1 & 0xf) as u8);
+    if white_to_set > STONES_PER_PLAYER || black_to_set > STONES_PER_PLAYER {
+        return Err(format!("{key:016x} has {white_to_set} and {black_to_set} stones to set, at most {STONES_PER_PLAYER} are"));
+    }
+    state.set_stones_to_set(white_to_set, black_to_set);
+    state.current_player = if key & 1 == 1 { MicaPlayer::Black } else { MicaPlayer::White };
+    Ok(state)
+}
+
+pub fn to_bytes(state: &MicaState) -> [u8; 8] {
+    encode(state).to_be_bytes()
+}
+
+pub fn from_bytes(bytes: [u8; 8]) -> Result<MicaState, String> {
+    decode(u64::from_be_bytes(bytes))
+}
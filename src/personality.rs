@@ -0,0 +1,94 @@
+use alloc::vec::Vec;
+
+use crate::minimax::*;
+use crate::rng::Rng;
+
+// What a personality likes besides winning material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Balanced,
+    // closing mills is worth a stone extra
+    Aggressive,
+    // blocking the opponent's mills is worth a stone extra
+    Defensive,
+}
+
+// A named engine opponent. Its moves depend only on the position and the
+// random numbers it is given, so a game seeded the same way always meets
+// the same character.
+#[derive(Debug, Clone, Copy)]
+pub struct Personality {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub style: Style,
+    // plies searched, at least one
+    pub depth: u8,
+    // up to this many stones of random preference are added to every move
+    pub noise: u8,
+}
+
+pub const PERSONALITIES: &[Personality] = &[
+    Personality {
+        name: "novice",
+        description: "Looks one move ahead and often plays on a whim",
+        style: Style::Balanced,
+        depth: 1,
+        noise: 2,
+    },
+    Personality {
+        name: "brawler",
+        description: "Goes for mills whenever it can",
+        style: Style::Aggressive,
+        depth: 3,
+        noise: 1,
+    },
+    Personality {
+        name: "turtle",
+        description: "Blocks every mill it sees coming",
+        style: Style::Defensive,
+        depth: 3,
+        noise: 1,
+    },
+    Personality {
+        name: "master",
+        description: "Searches deep and plays the best move it finds",
+        style: Style::Balanced,
+        depth: 5,
+        noise: 0,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Personality> {
+    PERSONALITIES.iter().find(|personality| personality.name == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    PERSONALITIES.iter().map(|personality| personality.name).collect()
+}
+
+impl Personality {
+    // The move with the best search score plus style bonus plus noise. Won
+    // games score far more than the bonus and noise together, so a forced
+    // win within the search depth is never passed up.
+    pub fn choose(&self, state: &MicaState, rng: &mut Rng) -> Option<MicaMove> {
+        let mut best: Option<(i32, MicaMove)> = None;
+        for mica_move in state.get_ordered_moves() {
+            let mut next = state.clone();
+            next.apply_move(mica_move);
+            next.current_player.toggle();
+            let value = -next.negamax(self.depth.max(1) - 1, -i32::MAX, i32::MAX).0;
+
+            let closes_mill = matches!(mica_move, MicaMove::SetRemove { .. } | MicaMove::MoveRemove { .. });
+            let bonus = match self.style {
+                Style::Aggressive if closes_mill => 1,
+                Style::Defensive if state.blocks_mill(mica_move) => 1,
+                _ => 0,
+            };
+            let score = value + bonus + rng.below(self.noise as usize + 1) as i32;
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, mica_move));
+            }
+        }
+        best.map(|(_, mica_move)| mica_move)
+    }
+}
@@ -1,20 +1,36 @@
 use std::thread;
 use std::sync::{Arc, Condvar, Mutex};
-use std::sync::mpsc::{Sender, Receiver};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::mpsc;
 use std::collections::VecDeque;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
 
 pub type MicaTask<T> = Box<dyn FnOnce() -> T + Send + 'static>;
 
+// a task, where its result goes and when it was queued
+type Queued<T> = (MicaTask<T>, Sender<T>, Instant);
+
 pub struct Pool<T>
 where
     T: Send + 'static,
     // F: FnOnce() -> T + Send + 'static
 {
-    queue: Mutex<VecDeque<MicaTask<T>>>,
+    queue: Mutex<VecDeque<Queued<T>>>,
     jobs_available: Condvar,
 }
 
+impl<T> Default for Pool<T>
+where
+    T: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Pool<T>
 where
     T: Send + 'static,
@@ -27,40 +43,240 @@ where
         }
     }
 
-    pub fn submit(self: Arc<Self>, task: MicaTask<T>) {
-        self.queue.lock().unwrap().push_back(task);
+    // queues the task and returns the channel its result will arrive on
+    pub fn submit(self: Arc<Self>, task: MicaTask<T>) -> Receiver<T> {
+        let (tx, rx) = mpsc::channel::<T>();
+        self.queue.lock().unwrap().push_back((task, tx, Instant::now()));
         self.jobs_available.notify_one();
+        rx
     }
 
-    pub fn init(self: Arc<Self>, num_threads: usize) -> Receiver<T> {
-        let (tx, rx) = mpsc::channel::<T>();
+    // Queues the task as one of the scope's, which counts it from here until
+    // it has run, panicked or been dropped unrun, see `TaskScope::close`.
+    pub fn submit_in(self: Arc<Self>, scope: &TaskScope, task: MicaTask<T>) -> Receiver<T> {
+        let running = scope.enter();
+        self.submit(Box::new(move || {
+            let _running = running;
+            task()
+        }))
+    }
 
+    // how long the oldest task still in the queue has waited, zero when every task has a thread
+    pub fn queue_wait(&self) -> Duration {
+        self.queue.lock().unwrap().front().map_or(Duration::ZERO, |(_, _, queued)| queued.elapsed())
+    }
+
+    pub fn init(self: Arc<Self>, num_threads: usize) {
         for _ in 0..num_threads {
             let pool = Arc::clone(&self);
-            let tx = tx.clone();
 
             thread::spawn(move ||{
                 loop {
                     let task = {
                         let mut q = pool.queue.lock().unwrap();
-                        q = pool.jobs_available.wait(q).unwrap();
+                        while q.is_empty() {
+                            q = pool.jobs_available.wait(q).unwrap();
+                        }
                         q.pop_front()
                     };
-                    println!("Thread woken up");
 
-                    if let Some(t) = task {
-                        let result = t();
-                        println!("Sending...");
-                        tx.send(result).unwrap();
+                    if let Some((t, tx, _)) = task {
+                        // a task that panics drops its sender, its submitter sees the channel
+                        // close, and the thread lives on for the next task
+                        if let Ok(result) = panic::catch_unwind(AssertUnwindSafe(t)) {
+                            // the submitter may have stopped waiting for the result
+                            let _ = tx.send(result);
+                        }
                     }
                 }
             });
         }
+    }
+}
 
-        rx
+// The pool tasks started on behalf of one request or job. Its flag is the one
+// the searches of those tasks check, and `close` raises it and waits for
+// every task submitted with `Pool::submit_in` to be done, so nothing the
+// request started is still running once it has been answered. Clones share
+// the flag and the count.
+#[derive(Debug, Clone)]
+pub struct TaskScope {
+    cancelled: Arc<AtomicBool>,
+    // tasks queued or running and the signal that the last of them is done
+    tasks: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Default for TaskScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskScope {
+    pub fn new() -> Self {
+        TaskScope::with_flag(Arc::new(AtomicBool::new(false)))
+    }
+
+    // a scope cancelled by whoever else holds `cancelled` too
+    pub fn with_flag(cancelled: Arc<AtomicBool>) -> Self {
+        TaskScope { cancelled, tasks: Arc::new((Mutex::new(0), Condvar::new())) }
+    }
+
+    pub fn cancelled(&self) -> &Arc<AtomicBool> {
+        &self.cancelled
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // tasks of the scope still queued or running
+    pub fn running(&self) -> usize {
+        *self.tasks.0.lock().unwrap()
+    }
+
+    // Cancels the scope and waits until none of its tasks is queued or running.
+    // Tasks still queued are skipped by the searches once they get a thread.
+    pub fn close(&self) {
+        self.cancel();
+        let (count, done) = &*self.tasks;
+        let mut count = count.lock().unwrap();
+        while *count > 0 {
+            count = done.wait(count).unwrap();
+        }
     }
+
+    fn enter(&self) -> ScopeTask {
+        *self.tasks.0.lock().unwrap() += 1;
+        ScopeTask(Arc::clone(&self.tasks))
+    }
+}
+
+// held by a task of a scope, counts it as done when dropped, unwinding included
+struct ScopeTask(Arc<(Mutex<usize>, Condvar)>);
+
+impl Drop for ScopeTask {
+    fn drop(&mut self) {
+        let (count, done) = &*self.0;
+        let mut count = count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            done.notify_all();
+        }
+    }
+}
+
+pub type BatchFn<I, O> = Box<dyn Fn(Vec<I>) -> Vec<O> + Send + Sync>;
+
+// Rendezvous for workers that want their inputs evaluated together, the leaf
+// positions of a network for one, see `Network::batched`. Callers block in
+// `eval` until the batch is full or `max_wait` passes, then whichever caller
+// gets there first runs the whole batch and hands every other caller its
+// output. A batch of one runs every input right away.
+pub struct Batcher<I, O> {
+    pending: Mutex<Vec<(I, Sender<O>)>>,
+    max_batch: usize,
+    max_wait: Duration,
+    run: BatchFn<I, O>,
 }
 
-fn ex() {
-    let thread = thread::spawn(|| 0);
-}
\ No newline at end of file
+impl<I, O> Batcher<I, O>
+where
+    I: Send,
+    O: Send,
+{
+    pub fn new(max_batch: usize, max_wait: Duration, run: BatchFn<I, O>) -> Self {
+        let max_batch = max_batch.max(1);
+        Batcher {
+            pending: Mutex::new(Vec::with_capacity(max_batch)),
+            max_batch,
+            max_wait,
+            run,
+        }
+    }
+
+    pub fn eval(&self, input: I) -> O {
+        let (tx, rx) = mpsc::channel();
+        let full_batch = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push((input, tx));
+            (pending.len() >= self.max_batch).then(|| mem::take(&mut *pending))
+        };
+        if let Some(batch) = full_batch {
+            self.run_batch(batch);
+        }
+
+        if let Ok(output) = rx.recv_timeout(self.max_wait) {
+            return output;
+        }
+        // nobody filled the batch in time, run whatever is waiting, ours
+        // included unless another caller already took it
+        let batch = mem::take(&mut *self.pending.lock().unwrap());
+        if !batch.is_empty() {
+            self.run_batch(batch);
+        }
+        // the caller running our batch only drops the sender without sending when it panicked
+        rx.recv().expect("the caller running the batch panicked")
+    }
+
+    fn run_batch(&self, batch: Vec<(I, Sender<O>)>) {
+        let (inputs, senders): (Vec<I>, Vec<Sender<O>>) = batch.into_iter().unzip();
+        let outputs = (self.run)(inputs);
+        for (tx, output) in senders.into_iter().zip(outputs) {
+            // a caller that gave up waiting no longer listens
+            let _ = tx.send(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doubler(max_batch: usize, max_wait: Duration, sizes: Arc<Mutex<Vec<usize>>>) -> Batcher<u32, u32> {
+        Batcher::new(max_batch, max_wait, Box::new(move |inputs: Vec<u32>| {
+            sizes.lock().unwrap().push(inputs.len());
+            inputs.into_iter().map(|input| input * 2).collect()
+        }))
+    }
+
+    #[test]
+    fn a_batch_of_one_runs_right_away() {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let batcher = doubler(1, Duration::from_secs(60), Arc::clone(&sizes));
+        let started = Instant::now();
+        assert_eq!(batcher.eval(3), 6);
+        assert_eq!(batcher.eval(4), 8);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(*sizes.lock().unwrap(), [1, 1]);
+    }
+
+    #[test]
+    fn callers_get_their_own_outputs() {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let batcher = Arc::new(doubler(4, Duration::from_secs(60), Arc::clone(&sizes)));
+        let callers: Vec<_> = (0..4)
+            .map(|i| {
+                let batcher = Arc::clone(&batcher);
+                thread::spawn(move || (i, batcher.eval(i)))
+            })
+            .collect();
+        for caller in callers {
+            let (input, output) = caller.join().unwrap();
+            assert_eq!(output, input * 2);
+        }
+        assert_eq!(*sizes.lock().unwrap(), [4]);
+    }
+
+    #[test]
+    fn a_partial_batch_runs_after_the_wait() {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let batcher = doubler(8, Duration::from_millis(10), Arc::clone(&sizes));
+        assert_eq!(batcher.eval(5), 10);
+        assert_eq!(*sizes.lock().unwrap(), [1]);
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::Write;
+use std::net::TcpStream;
+use std::process;
+use std::thread;
+use mica::fixtures;
+use mica::minimax::*;
+use mica::notation::{format_position, move_to_notation, parse_position, PositionFormat};
+use mica::rng::Rng;
+use serde_json::Value;
+
+use crate::http::{self, MAX_BODY};
+use crate::option;
+
+// Runs the protocol checks against a running server and reports each one.
+//
+//     mica client [--url http://127.0.0.1:7878]
+//
+// Meant for validating reimplementations of the server and proxies in front
+// of it. Prints one line per check and exits with status 1 when any of them
+// fails.
+
+type Check = fn(&str) -> Result<(), String>;
+
+// games played at once by the concurrency check and plies the client plays in each
+const CONCURRENT_GAMES: u64 = 4;
+const CLIENT_PLIES: usize = 4;
+
+fn send(address: &str, method: &str, path: &str, body: &str) -> Result<(u16, Value), String> {
+    let (status, body) = http::send(address, method, path, body).map_err(|e| format!("{method} {path}: {e}"))?;
+    let body = serde_json::from_str(&body).map_err(|e| format!("{method} {path}: response is not JSON: {e}"))?;
+    Ok((status, body))
+}
+
+fn expect_status(what: &str, status: u16, expected: &[u16]) -> Result<(), String> {
+    if expected.contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("{what} answered {status}, expected {expected:?}"))
+    }
+}
+
+fn check_move_request(address: &str) -> Result<(), String> {
+    let body = format_position(PositionFormat::Json, &fixtures::setting());
+    let (status, response) = send(address, "POST", "/", &body)?;
+    expect_status("move request", status, &[200])?;
+    match response["move"].as_array() {
+        Some(parts) if !parts.is_empty() => Ok(()),
+        _ => Err(format!("move request answered without a move: {response}")),
+    }
+}
+
+fn check_status(address: &str) -> Result<(), String> {
+    let (status, _) = send(address, "GET", "/status", "")?;
+    expect_status("GET /status", status, &[200])
+}
+
+fn check_malformed_json(address: &str) -> Result<(), String> {
+    let (status, response) = send(address, "POST", "/", "{\"player\": ")?;
+    expect_status("malformed JSON", status, &[400])?;
+    if response["error"].is_string() {
+        Ok(())
+    } else {
+        Err(format!("malformed JSON answered without an error: {response}"))
+    }
+}
+
+// only the head is sent, a server has to turn the body down before reading it
+fn check_oversized_body(address: &str) -> Result<(), String> {
+    let mut stream = TcpStream::connect(address).map_err(|e| e.to_string())?;
+    let head = format!("POST / HTTP/1.1\r\nHost: {address}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{{", MAX_BODY + 1);
+    stream.write_all(head.as_bytes()).map_err(|e| e.to_string())?;
+    let (status, _) = http::read_response(&stream).map_err(|e| e.to_string())?;
+    expect_status("oversized body", status, &[400, 413])
+}
+
+fn check_wrong_method(address: &str) -> Result<(), String> {
+    let (status, _) = send(address, "PUT", "/game", "")?;
+    expect_status("PUT /game", status, &[405])
+}
+
+fn check_unknown_game(address: &str) -> Result<(), String> {
+    let (status, _) = send(address, "GET", "/game/18446744073709551615", "")?;
+    expect_status("unknown game", status, &[404])
+}
+
+// plays random legal moves as white against the engine
+fn play_game(address: &str, seed: u64) -> Result<u64, String> {
+    let (status, mut game) = send(address, "POST", "/game", "{\"engine\": \"black\", \"time_ms\": 10000}")?;
+    expect_status("POST /game", status, &[201])?;
+    let id = game["id"].as_u64().ok_or_else(|| format!("game without an id: {game}"))?;
+    let mut rng = Rng::new(seed);
+
+    for ply in 0..CLIENT_PLIES {
+        if !game["outcome"].is_null() {
+            break;
+        }
+        let position = game["position"].as_str().ok_or_else(|| format!("game {id} without a position: {game}"))?;
+        let state = parse_position(PositionFormat::Fen, position)?;
+        let moves = state.get_moves();
+        if state.current_player != MicaPlayer::White || moves.is_empty() {
+            return Err(format!("game {id} is not waiting for white after {ply} plies: {position}"));
+        }
+        let mica_move = moves[rng.below(moves.len())];
+        let body = serde_json::json!({ "move": move_to_notation(mica_move) }).to_string();
+        let (status, answer) = send(address, "POST", &format!("/game/{id}/move"), &body)?;
+        expect_status(&format!("POST /game/{id}/move"), status, &[200])?;
+        if answer["moves"].as_array().map_or(0, Vec::len) < 2 * ply + 1 {
+            return Err(format!("game {id} lost a move: {answer}"));
+        }
+        game = answer;
+    }
+    Ok(id)
+}
+
+fn check_concurrent_games(address: &str) -> Result<(), String> {
+    let games: Vec<_> = (0..CONCURRENT_GAMES)
+        .map(|seed| {
+            let address = address.to_string();
+            thread::spawn(move || play_game(&address, seed))
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for game in games {
+        ids.push(game.join().unwrap()?);
+    }
+    ids.sort();
+    ids.dedup();
+    if ids.len() as u64 != CONCURRENT_GAMES {
+        return Err(format!("{CONCURRENT_GAMES} games got only {} distinct ids", ids.len()));
+    }
+    Ok(())
+}
+
+pub fn run(args: &[String]) {
+    let url: String = option(args, "--url", "http://127.0.0.1:7878".to_string());
+    let Some((address, _, _)) = http::parse_url(&url) else {
+        eprintln!("Only http:// urls are supported");
+        process::exit(2);
+    };
+    let checks: [(&str, Check); 7] = [
+        ("move request", check_move_request),
+        ("status", check_status),
+        ("malformed json", check_malformed_json),
+        ("oversized body", check_oversized_body),
+        ("wrong method", check_wrong_method),
+        ("unknown game", check_unknown_game),
+        ("concurrent games", check_concurrent_games),
+    ];
+
+    let mut failed = false;
+    for (name, check) in checks {
+        match check(&address) {
+            Ok(()) => println!("ok    {name}"),
+            Err(e) => {
+                println!("FAIL  {name}: {e}");
+                failed = true;
+            },
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
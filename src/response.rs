@@ -0,0 +1,123 @@
+use std::str::FromStr;
+use mica::api::{Degraded, IterationStats, Line, Mills, Stats};
+use mica::diff::Change;
+use serde::Serialize;
+use serde_json::Value;
+
+// How much a move response says, picked per request with `"profile"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    // the move, its board changes and the expected reply, for clients that
+    // only play, and the best lines when more than one was asked for
+    Minimal,
+    // the minimal fields plus score, principal variation, search stats and
+    // the same per depth, the position searched and its phase and the mills
+    // on the board after the move
+    Rich,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(Profile::Minimal),
+            "rich" => Ok(Profile::Rich),
+            _ => Err(format!("unknown profile `{s}`, expected minimal or rich")),
+        }
+    }
+}
+
+// fields echoed for clients that tag their requests with a `request_id`
+#[derive(Debug, Serialize)]
+pub struct RequestEcho {
+    pub request_id: Value,
+    pub received_at: u64,
+    pub computed_in_ms: u64,
+    pub engine_version: &'static str,
+}
+
+// Everything known about an answered move request. Each profile serializes
+// a view of it, so the endpoints never build two kinds of answers.
+#[derive(Debug)]
+pub struct MoveResponse {
+    // the `move` field of `move_json`
+    pub mica_move: Value,
+    pub changes: Vec<Change>,
+    pub echo: Option<RequestEcho>,
+    // The opponent's reply the engine expects after the move, the second ply
+    // of the principal variation, in notation. None when the search collected
+    // no line or the move ends the game.
+    pub expected_reply: Option<String>,
+    // the limits the search was cut to because the pool was saturated
+    pub degraded: Option<Degraded>,
+    // best first, empty unless more than one line was asked for
+    pub lines: Vec<Line>,
+    pub score: i32,
+    // in notation, empty unless the rich profile was asked for
+    pub pv: Vec<String>,
+    pub stats: Stats,
+    // empty for answers that did not search
+    pub iterations: Vec<IterationStats>,
+    // the position searched, as FEN
+    pub position: String,
+    // of the position searched, see `variants::Phase`
+    pub phase: &'static str,
+    pub mills: Mills,
+}
+
+#[derive(Serialize)]
+struct Minimal<'a> {
+    #[serde(rename = "move")]
+    mica_move: &'a Value,
+    changes: &'a [Change],
+    expected_reply: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    degraded: Option<&'a Degraded>,
+    #[serde(flatten)]
+    echo: Option<&'a RequestEcho>,
+    #[serde(skip_serializing_if = "<[Line]>::is_empty")]
+    lines: &'a [Line],
+}
+
+#[derive(Serialize)]
+struct Rich<'a> {
+    #[serde(flatten)]
+    minimal: Minimal<'a>,
+    score: i32,
+    pv: &'a [String],
+    stats: &'a Stats,
+    iterations: &'a [IterationStats],
+    position: &'a str,
+    phase: &'a str,
+    mills: &'a Mills,
+}
+
+impl MoveResponse {
+    fn minimal(&self) -> Minimal<'_> {
+        Minimal {
+            mica_move: &self.mica_move,
+            changes: &self.changes,
+            expected_reply: self.expected_reply.as_deref(),
+            degraded: self.degraded.as_ref(),
+            echo: self.echo.as_ref(),
+            lines: &self.lines,
+        }
+    }
+
+    pub fn to_json(&self, profile: Profile) -> Value {
+        match profile {
+            Profile::Minimal => serde_json::to_value(self.minimal()).unwrap(),
+            Profile::Rich => serde_json::to_value(Rich {
+                minimal: self.minimal(),
+                score: self.score,
+                pv: &self.pv,
+                stats: &self.stats,
+                iterations: &self.iterations,
+                position: &self.position,
+                phase: self.phase,
+                mills: &self.mills,
+            }).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use crate::minimax::*;
+use crate::rng::Rng;
+
+pub const DEFAULT_DEPTH: u8 = 6;
+
+// How much work a single search may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub max_nodes: Option<u64>,
+    pub max_time: Option<Duration>,
+}
+
+impl SearchLimits {
+    // Soft and hard deadlines for a search started at `started`, None without
+    // a time budget. A new depth is not worth starting after the soft one,
+    // the next ply takes several times as long as the last, and a depth still
+    // running at the hard one is abandoned.
+    #[cfg(feature = "std")]
+    pub fn deadlines(&self, started: Instant) -> Option<(Instant, Instant)> {
+        self.max_time.map(|max_time| (started + max_time / 2, started + max_time))
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            max_depth: DEFAULT_DEPTH,
+            max_nodes: None,
+            max_time: None,
+        }
+    }
+}
+
+// How a search uses the threads of the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    // one task per root move
+    Split,
+    // every thread searches the whole position through a shared table
+    LazySmp,
+}
+
+impl SearchMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchMode::Split => "split",
+            SearchMode::LazySmp => "lazy-smp",
+        }
+    }
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "split" => Ok(SearchMode::Split),
+            "lazy-smp" => Ok(SearchMode::LazySmp),
+            _ => Err(format!("unknown search mode `{s}`, expected split or lazy-smp")),
+        }
+    }
+}
+
+// score and nodes of one root move, no score when the move was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootMove {
+    pub mica_move: MicaMove,
+    pub score: Option<i32>,
+    // false when the score is only a bound the move's real score is no better than
+    pub exact: bool,
+    pub nodes: u64,
+    // the move and the line expected after it, empty when the move was skipped
+    pub pv: Vec<MicaMove>,
+}
+
+// What one depth of iterative deepening found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iteration {
+    pub depth: u8,
+    // white's perspective, like `SearchResult::score`
+    pub score: i32,
+    pub best_move: Option<MicaMove>,
+    pub nodes: u64,
+    // spent on this depth alone
+    pub time: Duration,
+}
+
+impl Iteration {
+    // Nodes of this depth per node of the one before, an estimate of the
+    // effective branching factor. Sudden jumps show the search exploding.
+    pub fn branching_factor(&self, previous: &Iteration) -> Option<f64> {
+        (previous.nodes > 0).then(|| self.nodes as f64 / previous.nodes as f64)
+    }
+}
+
+// What a search did, summed over every task that searched for it. Answers
+// that did not search have none of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    // nodes a transposition table entry decided without searching their moves
+    pub tt_hits: u64,
+    // nodes whose moves stopped at one scoring at least beta
+    pub beta_cutoffs: u64,
+    // deepest ply below the root a node was searched at, deeper than the
+    // answer's depth when the depth after it gave up partway
+    pub max_depth: u8,
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    // the counts of the searches on `state` from `plies` below the root, see `MicaState::reset_counters`
+    pub fn of(state: &MicaState, plies: u8) -> Self {
+        SearchStats {
+            nodes: state.nodes,
+            tt_hits: state.tt_hits,
+            beta_cutoffs: state.beta_cutoffs,
+            max_depth: state.max_ply + plies,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    // adds the counts of another task of the same search, the time is the search's own
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.tt_hits += other.tt_hits;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
+// one line for logs, `nodes=812 tt_hits=40 beta_cutoffs=95 max_depth=4 elapsed_ms=3`
+impl fmt::Display for SearchStats {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "nodes={} tt_hits={} beta_cutoffs={} max_depth={} elapsed_ms={}",
+            self.nodes,
+            self.tt_hits,
+            self.beta_cutoffs,
+            self.max_depth,
+            self.elapsed.as_millis()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<MicaMove>,
+    pub score: i32,
+    pub stats: SearchStats,
+    // deepest depth that finished, zero for answers that did not search
+    pub depth: u8,
+    // every root move of that depth in the order searched, empty for answers that did not search
+    pub roots: Vec<RootMove>,
+    // every depth that gave an answer, shallowest first, empty for answers that did not search
+    pub iterations: Vec<Iteration>,
+    // the line expected after the best move, starting with it, empty for
+    // answers that did not search and the ones `principal_variation` is for
+    pub pv: Vec<MicaMove>,
+    // How long the search waited for pool threads before each depth got
+    // going. The main search of lazy SMP runs on the calling thread and never
+    // waits.
+    pub queue_wait: Duration,
+}
+
+impl SearchResult {
+    // root moves with a score, the best for `side` first, exact scores ahead
+    // of bounds they tie with
+    pub fn ranked_roots(&self, side: i32) -> Vec<&RootMove> {
+        let mut ranked: Vec<&RootMove> = self.roots.iter().filter(|root| root.score.is_some()).collect();
+        ranked.sort_by_key(|root| Reverse((root.score.unwrap() * side, root.exact)));
+        ranked
+    }
+
+    // One of the root moves with an exact score at most `margin` below the
+    // best for `side`, every one of them equally likely. Searched with a
+    // margin above `margin`, no move within it is left with a bound. The
+    // same seed picks the same move whatever order the roots were searched in.
+    pub fn pick_within(&self, side: i32, margin: i32, rng: &mut Rng) -> Option<&RootMove> {
+        let ranked = self.ranked_roots(side);
+        let best = ranked.first()?.score? * side;
+        let mut close: Vec<&RootMove> = ranked.into_iter().filter(|root| root.exact && root.score.unwrap() * side >= best - margin).collect();
+        if close.is_empty() {
+            return None;
+        }
+        close.sort_by_key(|root| root.mica_move);
+        Some(close[rng.below(close.len())])
+    }
+}
+
+// A way of searching a position for its best move. The server searches
+// through one, picked with its search mode, so an engine that searches
+// differently, Monte Carlo tree search say, only has to implement this.
+pub trait Engine {
+    fn best_move(&mut self, state: &MicaState, limits: &SearchLimits) -> SearchResult;
+}
+
+// The line the engine expects after `first_move`, the best move of a search
+// to `depth`, found by searching each following position one ply shallower
+// than the one before, the first `depth - 1` deep as the search did. For
+// answers without a `SearchResult::pv`, such as cached ones.
+pub fn principal_variation(state: &MicaState, first_move: MicaMove, depth: u8) -> Vec<MicaMove> {
+    let mut state = state.clone();
+    let mut line = vec![first_move];
+    state.apply_move(first_move);
+    state.current_player.toggle();
+    for depth in (1..depth).rev() {
+        if state.is_end() {
+            break;
+        }
+        let Some(next_move) = state.minimax(depth, i32::MIN, i32::MAX).1 else {
+            break;
+        };
+        line.push(next_move);
+        state.apply_move(next_move);
+        state.current_player.toggle();
+    }
+    line
+}
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use mica::coords::{from_index, from_notation, to_index, to_notation, POINTS};
+use mica::book::OpeningBook;
+use mica::fixtures;
+use mica::minimax::*;
+use mica::pool::{MicaTask, Pool};
+use mica::symmetry::canonical_key;
+use mica::tablebase::{TableResult, Tablebase};
+
+use crate::option;
+
+// Checks that the engine works on this host before it takes traffic.
+//
+//     mica selfcheck [--tablebase FILE] [--book FILE]
+//
+// Runs perft on the reference positions, checks that every move undoes
+// cleanly, checks the coordinate tables and runs work through a pool. With a
+// tablebase, samples of its positions are checked against the results of
+// their moves. With an opening book, every move of it reachable from the
+// start is checked to be legal. Prints one line per check and exits with
+// status 1 when any of them fails.
+
+type Check = fn() -> Result<(), String>;
+
+// leaf counts of `perft(fixture, PERFT_DEPTH)` in the order of `fixtures::all`
+const PERFT_DEPTH: u8 = 3;
+const PERFT: [u64; 4] = [12_144, 4_032, 1_126, 299];
+
+// positions `depth` plies ahead, finished games count as leaves
+fn perft(state: &mut MicaState, depth: u8) -> u64 {
+    if depth == 0 || state.is_end() {
+        return 1;
+    }
+    let moves = state.get_moves();
+    if moves.is_empty() {
+        return 1;
+    }
+
+    let mut leaves = 0;
+    for mica_move in moves {
+        state.apply_move(mica_move);
+        state.current_player.toggle();
+        leaves += perft(state, depth - 1);
+        state.current_player.toggle();
+        state.undo_move(mica_move);
+    }
+    leaves
+}
+
+fn board(state: &MicaState) -> Vec<MicaPlayer> {
+    POINTS.iter().map(|&(x, y, z)| state.stone(x, y, z)).collect()
+}
+
+fn check_perft() -> Result<(), String> {
+    for ((name, mut state), expected) in fixtures::all().into_iter().zip(PERFT) {
+        let leaves = perft(&mut state, PERFT_DEPTH);
+        if leaves != expected {
+            return Err(format!("{name}: perft {PERFT_DEPTH} gave {leaves}, expected {expected}"));
+        }
+    }
+    Ok(())
+}
+
+fn check_apply_undo() -> Result<(), String> {
+    for (name, state) in fixtures::all() {
+        for mica_move in state.get_moves() {
+            let mut after = state.clone();
+            after.apply_move(mica_move);
+            after.undo_move(mica_move);
+            if board(&after) != board(&state) || after.stones_to_set() != state.stones_to_set() {
+                return Err(format!("{name}: undoing {mica_move:?} did not restore the position"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_coords() -> Result<(), String> {
+    for (i, &(x, y, z)) in POINTS.iter().enumerate() {
+        if to_index(x, y, z) != Some(i) || from_index(i) != Some((x, y, z)) {
+            return Err(format!("point {i} does not round-trip through its index"));
+        }
+        let notation = to_notation(x, y, z).ok_or(format!("point {i} has no notation"))?;
+        if from_notation(&notation) != Some((x, y, z)) {
+            return Err(format!("point {i} does not round-trip through `{notation}`"));
+        }
+    }
+    Ok(())
+}
+
+fn check_pool() -> Result<(), String> {
+    let pool = Arc::new(Pool::new());
+    Arc::clone(&pool).init(4);
+
+    let receivers: Vec<_> = (0..64u64)
+        .map(|i| {
+            let task: MicaTask<u64> = Box::new(move || i * i);
+            Arc::clone(&pool).submit(task)
+        })
+        .collect();
+    for (i, rx) in receivers.into_iter().enumerate() {
+        let value = rx.recv().map_err(|_| format!("task {i} never answered"))?;
+        if value != (i * i) as u64 {
+            return Err(format!("task {i} answered {value}"));
+        }
+    }
+    Ok(())
+}
+
+// positions of every table the tablebase check looks at
+const TABLEBASE_SAMPLES: usize = 2000;
+
+// A position is won one ply after its quickest lost successor, lost one ply
+// after its slowest successor when all of them are won and drawn otherwise.
+fn check_tablebase(path: &str) -> Result<(), String> {
+    let tablebase = Tablebase::load(Path::new(path))?;
+    for ((mine, theirs), table) in tablebase.tables() {
+        for index in (0..table.len()).step_by(table.len() / TABLEBASE_SAMPLES + 1) {
+            let state = tablebase.state_at((mine, theirs), index);
+            let mut results = Vec::new();
+            for mica_move in state.get_moves() {
+                let mut after = state.clone();
+                after.apply_move(mica_move);
+                after.current_player.toggle();
+                results.push(match after.is_end() {
+                    true => TableResult::Loss(0),
+                    false => tablebase.probe(&after).ok_or(format!("{mine}v{theirs} position {index}: {mica_move:?} leads out of the tables"))?,
+                });
+            }
+
+            let quickest_loss = results.iter().filter_map(|&result| if let TableResult::Loss(plies) = result { Some(plies) } else { None }).min();
+            let slowest_win = results.iter().map(|&result| if let TableResult::Win(plies) = result { Some(plies) } else { None }).collect::<Option<Vec<u8>>>();
+            let found = match (quickest_loss, slowest_win) {
+                (Some(plies), _) => TableResult::Win(plies + 1),
+                (None, Some(wins)) => TableResult::Loss(wins.into_iter().max().map_or(0, |plies| plies + 1)),
+                (None, None) => TableResult::Draw,
+            };
+            let expected = TableResult::from_byte(table[index]);
+            if found != expected {
+                return Err(format!("{mine}v{theirs} position {index}: the table says {expected:?}, its moves {found:?}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Follows every line of the book from the start position, each position
+// once. Positions the book has but no line reaches are not looked at.
+fn check_book(path: &str) -> Result<(), String> {
+    let book = OpeningBook::load(Path::new(path))?;
+    let mut seen = HashSet::new();
+    let mut lines = vec![(MicaState::new(), Vec::new())];
+    while let Some((state, line)) = lines.pop() {
+        if !seen.insert(canonical_key(&state).0) {
+            continue;
+        }
+        let legal = state.get_moves();
+        for book_move in book.moves(&state) {
+            let mut line = line.clone();
+            line.push(book_move.mica_move);
+            if !legal.contains(&book_move.mica_move) {
+                return Err(format!("{line:?}: the last move is not legal"));
+            }
+            let mut after = state.clone();
+            after.apply_move(book_move.mica_move);
+            after.current_player.toggle();
+            lines.push((after, line));
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: &[String]) {
+    let checks: [(&str, Check); 4] = [
+        ("perft", check_perft),
+        ("apply/undo", check_apply_undo),
+        ("coordinates", check_coords),
+        ("pool", check_pool),
+    ];
+
+    let mut failed = false;
+    for (name, check) in checks {
+        match check() {
+            Ok(()) => println!("ok    {name}"),
+            Err(e) => {
+                println!("FAIL  {name}: {e}");
+                failed = true;
+            },
+        }
+    }
+    let tablebase: String = option(args, "--tablebase", String::new());
+    if !tablebase.is_empty() {
+        match check_tablebase(&tablebase) {
+            Ok(()) => println!("ok    tablebase"),
+            Err(e) => {
+                println!("FAIL  tablebase: {e}");
+                failed = true;
+            },
+        }
+    }
+    let book: String = option(args, "--book", String::new());
+    if !book.is_empty() {
+        match check_book(&book) {
+            Ok(()) => println!("ok    book"),
+            Err(e) => {
+                println!("FAIL  book: {e}");
+                failed = true;
+            },
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,210 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::gym::{encode_action, Env, Observation};
+use crate::minimax::*;
+#[cfg(feature = "nn")]
+use crate::nn::Network;
+use crate::pool::Pool;
+use crate::rng::Rng;
+use crate::variants;
+
+#[derive(Debug, Clone)]
+pub struct SelfPlayConfig {
+    pub depth: u8,
+    // opening plies played at random so games started from the same seed differ
+    pub random_plies: usize,
+    // games longer than this are adjudicated by evaluation
+    pub max_plies: usize,
+    // evaluates the searches' leaves instead of the weights when set
+    #[cfg(feature = "nn")]
+    pub network: Option<Arc<Network>>,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        SelfPlayConfig {
+            depth: 3,
+            random_plies: 4,
+            max_plies: 200,
+            #[cfg(feature = "nn")]
+            network: None,
+        }
+    }
+}
+
+// one finished game: moves as gym action indices and the result for white (1, 0 or -1)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub moves: Vec<usize>,
+    pub result: i8,
+    // records written before there were headers have none
+    #[serde(default)]
+    pub headers: GameHeaders,
+}
+
+// PGN-style tags of a game, so a record shared on its own still says who
+// played it, when and how it ended. They are written with PGN's tag names
+// and left out when unknown.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GameHeaders {
+    // `self-play`, `match` or `session`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    // an agent like `minimax:3` or `client`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub white: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub black: Option<String>,
+    // YYYY.MM.DD as in PGN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    // of the mica that played or recorded the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_version: Option<String>,
+    // seconds per side and the increment after a `+`, `-` for games without a clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    // `stones`, `blocked`, `length`, `quiet` or `time`, left out while the game goes on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<String>,
+}
+
+impl GameHeaders {
+    // the headers of a game this build plays today, of the default variant without a clock
+    pub fn new(event: &str, white: &str, black: &str) -> Self {
+        GameHeaders {
+            event: Some(event.to_string()),
+            white: Some(white.to_string()),
+            black: Some(black.to_string()),
+            date: Some(today()),
+            engine_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            time_control: Some("-".to_string()),
+            variant: Some(variants::DEFAULT.name.to_string()),
+            termination: None,
+        }
+    }
+}
+
+// the UTC date, YYYY.MM.DD
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64 / 86_400;
+    // days since 1970-01-01 to a civil date, counting in 400 year eras from 0000-03-01
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+// How a game over in `state` ended, the side to move lost or it is drawn.
+pub fn termination(state: &MicaState) -> &'static str {
+    if state.is_drawn() {
+        "quiet"
+    } else if state.is_end() {
+        "stones"
+    } else {
+        "blocked"
+    }
+}
+
+// a position, the move played from it and the game result from the side to move's view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    pub obs: Observation,
+    pub action: usize,
+    pub outcome: i8,
+}
+
+pub fn play_game(config: &SelfPlayConfig, seed: u64) -> GameRecord {
+    let mut rng = Rng::new(seed);
+    let mut env = Env::new();
+    let mut moves = Vec::new();
+    let mut result = 0;
+    let player = format!("minimax:{}", config.depth);
+    let mut headers = GameHeaders::new("self-play", &player, &player);
+
+    loop {
+        let legal = env.legal_actions();
+        if legal.is_empty() {
+            headers.termination = Some(termination(env.state()).to_string());
+            break;
+        }
+        if moves.len() >= config.max_plies {
+            // shuffling back and forth would go on forever, the side ahead takes it
+            result = env.state().adjudicate() as i8;
+            headers.termination = Some("length".to_string());
+            break;
+        }
+
+        let action = if moves.len() < config.random_plies {
+            legal[rng.below(legal.len())]
+        } else {
+            let mut state = env.state().clone();
+            #[cfg(feature = "nn")]
+            {
+                state.network = config.network.clone();
+            }
+            match state.minimax(config.depth, i32::MIN, i32::MAX).1 {
+                Some(best_move) => encode_action(best_move),
+                None => legal[rng.below(legal.len())],
+            }
+        };
+
+        let mover = env.state().current_player;
+        let (_, reward, done) = env.step(action).unwrap();
+        moves.push(action);
+        if done {
+            if reward > 0.0 {
+                result = mover as i8;
+            }
+            headers.termination = Some(termination(env.state()).to_string());
+            break;
+        }
+    }
+
+    GameRecord { seed, moves, result, headers }
+}
+
+// Plays one game per seed on the pool and yields the records in seed order,
+// whichever worker finishes first. A game depends only on its seed, so the
+// output is the same for any number of workers.
+pub fn play_games(pool: &Arc<Pool<GameRecord>>, config: SelfPlayConfig, seeds: Range<u64>) -> impl Iterator<Item = GameRecord> {
+    let results: Vec<_> = seeds
+        .map(|seed| {
+            let config = config.clone();
+            Arc::clone(pool).submit(Box::new(move || play_game(&config, seed)))
+        })
+        .collect();
+    results.into_iter().map(|result| result.recv().unwrap())
+}
+
+pub fn samples(record: &GameRecord) -> Vec<Sample> {
+    let mut env = Env::new();
+    let mut samples = Vec::with_capacity(record.moves.len());
+
+    for &action in record.moves.iter() {
+        let mover = env.state().current_player as i8;
+        samples.push(Sample {
+            obs: env.observation(),
+            action,
+            outcome: record.result * mover,
+        });
+        if env.step(action).is_err() {
+            break;
+        }
+    }
+
+    samples
+}
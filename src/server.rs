@@ -0,0 +1,1779 @@
+use std::backtrace::Backtrace;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use mica::agents::Agent;
+use mica::api::{Capabilities, CapabilityLimits, Degraded, DifficultyAnswer, GameMove, GameRequest, GraphRequest, RuleOptions, IterationStats, Latency, Line, Mills, ScoreGraph, Stats, SCHEMA_VERSION};
+use mica::book::OpeningBook;
+use mica::cache::LruCache;
+use mica::clock::{self, Clock};
+use mica::coords::{to_notation, POINTS};
+use mica::diff::move_changes;
+use mica::difficulty::{Difficulty, PositionDifficulty};
+use mica::eval::PhaseWeights;
+use mica::gym::decode_action;
+use mica::minimax::*;
+use mica::notation::{format_position, move_from_notation, move_to_notation, PositionFormat};
+#[cfg(feature = "nn")]
+use mica::nn::Network;
+use mica::personality;
+use mica::pool::{Pool, TaskScope};
+use mica::rng::Rng;
+use mica::search::{principal_variation, Engine, SearchLimits, SearchMode, SearchResult, SearchStats, DEFAULT_DEPTH};
+use mica::symmetry::canonical_key;
+use mica::tablebase::Tablebase;
+use mica::tt::TranspositionTable;
+use mica::tuned;
+use mica::validation::{correct_request, normalize_request, reachability_errors, validate_request, ValidationMode};
+use mica::variants::{self, Variant};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::http::{self, Request, Response};
+use crate::jobs::{JobStatus, Jobs};
+use crate::response::{MoveResponse, Profile, RequestEcho};
+use crate::sessions::{AuditEntry, Session, Sessions};
+use crate::usage::Usage;
+#[cfg(feature = "notes")]
+use crate::notes::{self, position, Annotation, Notes};
+use crate::{option, AlphaBeta, LazySmp, MicaBestMove, SEARCH_THREADS};
+
+type CacheKey = (u64, SearchLimits, &'static str);
+// best move and the line it starts in the canonical frame, score and depth reached
+type CachedSearch = (Option<MicaMove>, i32, u8, Vec<MicaMove>);
+
+// Largest search limits a client may ask for.
+pub struct SafetyCaps {
+    pub depth: u8,
+    pub time_ms: u64,
+    pub nodes: u64,
+}
+
+impl SafetyCaps {
+    fn from_args(args: &[String]) -> Self {
+        SafetyCaps {
+            depth: option(args, "--max-depth", 10),
+            time_ms: option(args, "--max-time-ms", 60_000),
+            nodes: option(args, "--max-nodes", 2_000_000_000),
+        }
+    }
+
+    fn limits(&self, request: &MicaRequest) -> Result<SearchLimits, Response> {
+        let difficulty = difficulty(request)?;
+        let mut errors = Vec::new();
+        if let Some(depth) = request.depth.filter(|&depth| depth > self.depth) {
+            errors.push(format!("depth {depth} exceeds the server maximum of {}", self.depth));
+        }
+        if let Some(time_ms) = request.time_ms.filter(|&time_ms| time_ms > self.time_ms) {
+            errors.push(format!("time_ms {time_ms} exceeds the server maximum of {}", self.time_ms));
+        }
+        if let Some(nodes) = request.max_nodes.filter(|&nodes| nodes > self.nodes) {
+            errors.push(format!("max_nodes {nodes} exceeds the server maximum of {}", self.nodes));
+        }
+        if !errors.is_empty() {
+            return Err(Response::error(400, "limit exceeded", errors));
+        }
+
+        let mut defaults = self.default_limits();
+        if let Some(difficulty) = difficulty {
+            defaults.max_depth = difficulty.depth().min(self.depth);
+            defaults.max_time = Some(difficulty.time().min(Duration::from_millis(self.time_ms)));
+        }
+        Ok(SearchLimits {
+            max_depth: request.depth.unwrap_or(defaults.max_depth),
+            max_nodes: request.max_nodes.or(defaults.max_nodes),
+            max_time: request.time_ms.map(Duration::from_millis).or(defaults.max_time),
+        })
+    }
+
+    // requests that do not ask for limits still get the server maximums
+    fn default_limits(&self) -> SearchLimits {
+        SearchLimits {
+            max_depth: DEFAULT_DEPTH.min(self.depth),
+            max_nodes: Some(self.nodes),
+            max_time: Some(Duration::from_millis(self.time_ms)),
+        }
+    }
+}
+
+// What move requests get while the pool is saturated, that is while the
+// oldest task in its queue has waited longer than the saturation threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaturationPolicy {
+    // search with the asked limits and wait for the pool
+    Queue,
+    // search shallower and shorter, the longer the wait the more, and say so in the answer
+    Degrade,
+    // answer 503 right away
+    Reject,
+}
+
+impl SaturationPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            SaturationPolicy::Queue => "queue",
+            SaturationPolicy::Degrade => "degrade",
+            SaturationPolicy::Reject => "reject",
+        }
+    }
+}
+
+impl FromStr for SaturationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queue" => Ok(SaturationPolicy::Queue),
+            "degrade" => Ok(SaturationPolicy::Degrade),
+            "reject" => Ok(SaturationPolicy::Reject),
+            _ => Err(format!("unknown saturation policy `{s}`, expected queue, degrade or reject")),
+        }
+    }
+}
+
+// Limits cut by one level for every doubling of the queue wait past the
+// threshold: a ply less and half the time and nodes, down to a single ply.
+fn degrade(limits: &SearchLimits, queue_wait: Duration, threshold: Duration) -> SearchLimits {
+    let doublings = (queue_wait.as_secs_f64() / threshold.as_secs_f64().max(1e-6)).log2().floor().max(0.0) as u32;
+    let levels = (doublings + 1).min(16);
+    SearchLimits {
+        max_depth: limits.max_depth.saturating_sub(levels as u8).max(1),
+        max_nodes: limits.max_nodes.map(|nodes| (nodes >> levels).max(1)),
+        max_time: limits.max_time.map(|time| time / (1 << levels)),
+    }
+}
+
+// The body of a request, or for a `multipart/form-data` upload the files in
+// it one after the other, so browsers can post recorded games from a form.
+// Lines are counted across the files. Form fields without a filename are
+// ignored.
+fn upload_body(request: &Request) -> Result<Cow<'_, str>, Response> {
+    let parts = match request.form_parts() {
+        None => return Ok(Cow::Borrowed(&request.body)),
+        Some(parts) => parts.map_err(|e| Response::error(400, "invalid request", vec![e]))?,
+    };
+    let mut files = parts.into_iter().filter(|part| part.filename.is_some()).peekable();
+    if files.peek().is_none() {
+        return Err(Response::error(400, "invalid request", vec!["multipart request without a file".to_string()]));
+    }
+    let mut body = String::new();
+    for file in files {
+        body.push_str(&file.body);
+        if !body.ends_with('\n') {
+            body.push('\n');
+        }
+    }
+    Ok(Cow::Owned(body))
+}
+
+// the requested difficulty, None for the empty string
+fn difficulty(request: &MicaRequest) -> Result<Option<Difficulty>, Response> {
+    if request.difficulty.is_empty() {
+        return Ok(None);
+    }
+    request.difficulty.parse().map(Some).map_err(|e| Response::error(400, "invalid request", vec![e]))
+}
+
+// Scores, from the advised side's point of view, at or below which the engine
+// resigns or accepts a draw. Scores count stones, so -3 means three stones down.
+#[derive(Deserialize)]
+struct AdviceThresholds {
+    #[serde(default = "default_resign_below", alias = "resignBelow")]
+    resign_below: i32,
+    #[serde(default, alias = "acceptDrawBelow")]
+    accept_draw_below: i32,
+}
+
+fn default_resign_below() -> i32 {
+    -3
+}
+
+fn advice_json(score: i32, thresholds: &AdviceThresholds) -> Value {
+    json!({
+        "resign": score <= thresholds.resign_below,
+        "accept_draw": score <= thresholds.accept_draw_below,
+        "score": score,
+        "resign_below": thresholds.resign_below,
+        "accept_draw_below": thresholds.accept_draw_below,
+    })
+}
+
+fn parse_thresholds(body: &str) -> Result<AdviceThresholds, Response> {
+    let body = if body.trim().is_empty() { "{}" } else { body };
+    serde_json::from_str(body).map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))
+}
+
+// A search other requests for the same position and limits wait for.
+#[derive(Default)]
+struct InFlight {
+    // set once the search is over, with None inside when it was cancelled
+    result: Mutex<Option<Option<CachedSearch>>>,
+    finished: Condvar,
+}
+
+impl InFlight {
+    fn finish(&self, result: Option<CachedSearch>) {
+        *self.result.lock().unwrap() = Some(result);
+        self.finished.notify_all();
+    }
+
+    // what the search came to, None when `cancelled` was raised first
+    fn wait(&self, cancelled: &AtomicBool) -> Option<Option<CachedSearch>> {
+        let mut result = self.result.lock().unwrap();
+        loop {
+            if let Some(result) = result.clone() {
+                return Some(result);
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            result = self.finished.wait_timeout(result, DISCONNECT_POLL).unwrap().0;
+        }
+    }
+}
+
+pub struct Server {
+    pool: Arc<Pool<MicaBestMove>>,
+    caps: SafetyCaps,
+    jobs: Jobs,
+    sessions: Sessions,
+    usage: Usage,
+    // what `/admin/usage` asks for in `X-Admin-Token`, without one the endpoint is off
+    admin_token: Option<String>,
+    default_validation: ValidationMode,
+    // rule options requests may change, see `RuleOptions`
+    rule_options: Vec<&'static str>,
+    // contempt of move requests that do not ask for one, see `Contempt`
+    default_contempt: i32,
+    // analysis jobs run in the background and leave the cores to move
+    // requests this long between node batches, see `MicaState::pause`
+    job_pause: Option<Duration>,
+    // finished jobs are evicted once the job store grows past this
+    memory_limit: usize,
+    // transposition table shared by every search
+    tt: Arc<TranspositionTable>,
+    // exact results of small endgames for the leaves of every search, see `mica tablebase`
+    tablebase: Option<Arc<Tablebase>>,
+    // of the evaluation of every search
+    weights: PhaseWeights,
+    // evaluates the positions of requests that ask for the `nn` evaluator, see `--nn-weights`
+    #[cfg(feature = "nn")]
+    network: Option<Arc<Network>>,
+    // moves for the start of the setting phase, played by move requests and games instead of searching
+    book: Option<Arc<OpeningBook>>,
+    // how long the details of a finished job are kept
+    details_ttl: Duration,
+    // searches by canonical position
+    cache: Mutex<LruCache<CacheKey, CachedSearch>>,
+    // how move requests use the pool, searches that report every root move always split
+    search_mode: SearchMode,
+    // what move requests get once the pool's queue wait passes the threshold
+    saturation: SaturationPolicy,
+    saturation_threshold: Duration,
+    // Same request, same answer, for CI and debugging: searches split one
+    // root move after the other on the request's thread, each with a table
+    // of its own, without time budgets or saturation policy, and seeds not
+    // given by the request are 0.
+    deterministic: bool,
+    // annotations shared by everyone studying with this server
+    #[cfg(feature = "notes")]
+    notes: Notes,
+    // cancel flags of the move searches running for each game id
+    game_searches: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    // searches running by cache key, identical requests wait for them instead of searching again
+    in_flight: Mutex<HashMap<CacheKey, Arc<InFlight>>>,
+}
+
+// resident set size of this process, where the platform reports it, from
+// the `VmRSS:  1234 kB` line that does not depend on the page size
+fn resident_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let rss = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kilobytes: u64 = rss.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn api_key(request: &Request) -> String {
+    request.header("X-Api-Key").unwrap_or("anonymous").to_string()
+}
+
+fn move_json(best_move: Option<MicaMove>, player: PlayerField) -> Value {
+    match best_move {
+        None => json!({ "move": null }),
+        Some(MicaMove::Set { x, y, z }) => json!({ "move": [["set", player, x, y, z]] }),
+        Some(MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z }) => json!({ "move": [["move", player, to_x, to_y, to_z, from_x, from_y, from_z]] }),
+        Some(MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z }) => {
+            json!({ "move": [
+                ["set", player, x, y, z],
+                ["remove", player, remove_x, remove_y, remove_z]
+            ]})
+        },
+        Some(MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z }) => {
+            json!({ "move": [
+                ["move",player,  to_x, to_y, to_z, from_x, from_y, from_z],
+                ["remove", player, remove_x, remove_y, remove_z]
+            ]})
+        }
+    }
+}
+
+// how often idle game sessions are looked for
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+const CALLBACK_ATTEMPTS: u32 = 5;
+
+// delivers a finished job to its callback url, backing off exponentially between attempts
+fn notify_callback(url: &str, payload: &Value) {
+    let body = payload.to_string();
+    let mut delay = Duration::from_secs(1);
+    for attempt in 1..=CALLBACK_ATTEMPTS {
+        match http::post_json(url, &body) {
+            Ok(status) if (200..300).contains(&status) => return,
+            Ok(status) => eprintln!("Callback to {url} answered {status} (attempt {attempt})"),
+            Err(e) => eprintln!("Callback to {url} failed: {e} (attempt {attempt})"),
+        }
+        if attempt < CALLBACK_ATTEMPTS {
+            thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+    eprintln!("Giving up on callback to {url}");
+}
+
+fn find_variant(name: Option<&str>) -> Result<&'static Variant, Response> {
+    match name {
+        None => Ok(variants::DEFAULT),
+        Some(name) => variants::find(name).ok_or_else(|| {
+            Response::error(400, "unsupported variant", vec![format!("`{name}` is not one of {}", variants::names().join(", "))])
+        }),
+    }
+}
+
+// the rule options `--rule-options` lets clients change, every one by default
+fn allowed_rule_options(args: &[String]) -> Vec<&'static str> {
+    let names: String = option(args, "--rule-options", RuleOptions::NAMES.join(","));
+    names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            RuleOptions::NAMES
+                .into_iter()
+                .find(|&known| known == name)
+                .unwrap_or_else(|| {
+                    eprintln!("Invalid --rule-options `{names}`: unknown rule option `{name}`, expected one of {}", RuleOptions::NAMES.join(", "));
+                    process::exit(2);
+                })
+        })
+        .collect()
+}
+
+fn variants_json(rule_options: &[&str]) -> Value {
+    let variants: Vec<Value> = variants::VARIANTS
+        .iter()
+        .map(|variant| json!({
+            "name": variant.name,
+            "description": variant.description,
+            "points": variant.points,
+            "stones_per_player": variant.stones_per_player,
+            "flying": variant.rules.flying,
+            "mill_fallback": variant.rules.mill_fallback,
+            "features": ["move", "analysis", "advice", "game"],
+        }))
+        .collect();
+    json!({ "default": variants::DEFAULT.name, "variants": variants, "rule_options": rule_options })
+}
+
+// the line the search expects, searched again for answers that did not keep one
+fn pv(state: &MicaState, search: &SearchResult, depth: u8) -> Vec<MicaMove> {
+    match search.best_move {
+        Some(_) if !search.pv.is_empty() => search.pv.clone(),
+        Some(best_move) if depth > 0 => principal_variation(state, best_move, depth),
+        Some(best_move) => vec![best_move],
+        None => Vec::new(),
+    }
+}
+
+// what a finished search looked at: its principal variation and every root move
+fn details_json(state: &MicaState, search: &SearchResult, depth: u8) -> Value {
+    let pv: Vec<String> = pv(state, search, depth).into_iter().map(move_to_notation).collect();
+    let roots: Vec<Value> = search
+        .roots
+        .iter()
+        .map(|root| {
+            let pv: Vec<String> = root.pv.iter().copied().map(move_to_notation).collect();
+            json!({ "move": move_to_notation(root.mica_move), "score": root.score, "exact": root.exact, "nodes": root.nodes, "pv": pv })
+        })
+        .collect();
+    json!({ "score": search.score, "nodes": search.stats.nodes, "pv": pv, "roots": roots })
+}
+
+// One import line as request bodies for `/analysis`, with the ply of each
+// position for game records.
+fn import_line(line: &str) -> Result<Vec<(Option<usize>, String)>, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let Some(moves) = value.get("moves") else {
+        return Ok(vec![(None, line.to_string())]);
+    };
+    let moves = moves.as_array().ok_or("`moves` is not an array")?;
+
+    let mut state = MicaState::new();
+    let mut bodies = Vec::new();
+    for ply in 0..=moves.len() {
+        if state.game_over_value().is_some() {
+            break;
+        }
+        let mut body: Value = serde_json::from_str(&format_position(PositionFormat::Json, &state)).unwrap();
+        for limit in ["depth", "time_ms", "max_nodes"] {
+            if let Some(limit_value) = value.get(limit) {
+                body[limit] = limit_value.clone();
+            }
+        }
+        bodies.push((Some(ply), body.to_string()));
+
+        let Some(next) = moves.get(ply) else {
+            break;
+        };
+        let mica_move = parse_game_move(ply, next)?;
+        state.checked_apply_move(mica_move).map_err(|_| format!("move {} ({next}) is illegal", ply + 1))?;
+        state.current_player.toggle();
+    }
+    Ok(bodies)
+}
+
+// the move of a game record at `ply`, a gym action or notation
+fn parse_game_move(ply: usize, next: &Value) -> Result<MicaMove, String> {
+    let mica_move = match next {
+        Value::Number(action) => action.as_u64().and_then(|action| decode_action(action as usize)),
+        Value::String(notation) => move_from_notation(notation),
+        _ => None,
+    };
+    mica_move.ok_or_else(|| format!("move {} is not a gym action or notation: {next}", ply + 1))
+}
+
+fn engine_thinking() -> Response {
+    Response::error(409, "engine thinking", vec!["the engine is still searching its move in this game".to_string()])
+}
+
+// Clears the thinking mark of a session whose engine turn ends without
+// playing, a panicking search included.
+struct Thinking<'a> {
+    session: &'a Mutex<Session>,
+    armed: bool,
+}
+
+impl Drop for Thinking<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            let mut session = self.session.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            session.thinking = false;
+        }
+    }
+}
+
+fn parse_id(id: &str, kind: &str) -> Result<u64, Response> {
+    id.parse().map_err(|_| Response::error(404, &format!("unknown {kind}"), vec![format!("`{id}` is not a {kind} id")]))
+}
+
+// canonical position keys are 16 hex digits in urls, as `position_hash` in analysis results
+#[cfg(feature = "notes")]
+fn parse_position_hash(hash: &str) -> Result<u64, Response> {
+    notes::parse_position(hash).ok_or_else(|| Response::error(404, "unknown position", vec![format!("`{hash}` is not a position hash")]))
+}
+
+#[cfg(feature = "notes")]
+fn notes_error(error: rusqlite::Error) -> Response {
+    Response::error(500, "notes unavailable", vec![error.to_string()])
+}
+
+// plies the positions of a score graph are searched to unless a request asks for more
+const GRAPH_DEPTH: u8 = 4;
+
+// positions of the largest batch `POST /batch` answers
+const MAX_BATCH: usize = 100;
+
+// moves of the longest game `POST /games/graph` scores
+const MAX_GRAPH_PLIES: usize = 1000;
+
+// how often a connection waiting for its response is checked for a hang up
+const DISCONNECT_POLL: Duration = Duration::from_millis(100);
+
+// Raises `cancelled` when the client closes the connection before `done` is
+// set. A client that sends more bytes is still there, so watching stops.
+// Joining tells whether the client hung up.
+fn watch_disconnect(stream: &TcpStream, cancelled: &Arc<AtomicBool>, done: &Arc<AtomicBool>) -> Option<thread::JoinHandle<bool>> {
+    let stream = stream.try_clone().ok()?;
+    stream.set_read_timeout(Some(DISCONNECT_POLL)).ok()?;
+    let cancelled = Arc::clone(cancelled);
+    let done = Arc::clone(done);
+    Some(thread::spawn(move || {
+        let mut buf = [0u8; 1];
+        while !done.load(Ordering::Relaxed) {
+            match stream.peek(&mut buf) {
+                Ok(0) => {
+                    cancelled.store(true, Ordering::Relaxed);
+                    return true;
+                },
+                Ok(_) => return false,
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => (),
+                Err(_) => {
+                    cancelled.store(true, Ordering::Relaxed);
+                    return true;
+                },
+            }
+        }
+        false
+    }))
+}
+
+impl Server {
+    fn parse_request(&self, body: &str) -> Result<(MicaRequest, SearchLimits), Response> {
+        let mut mica_request: MicaRequest = serde_json::from_str(body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        if let Some(version) = mica_request.schema_version.filter(|&version| version > SCHEMA_VERSION) {
+            return Err(Response::error(400, "unsupported schema version", vec![format!(
+                "the request is for schema version {version}, this server speaks {SCHEMA_VERSION}, see GET /capabilities"
+            )]));
+        }
+        let unknown: Vec<String> = mica_request
+            .critical
+            .iter()
+            .filter(|field| !MicaRequest::FIELDS.contains(&field.as_str()))
+            .map(|field| format!("critical field `{field}` is not supported by this server, see GET /capabilities"))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(Response::error(400, "unsupported field", unknown));
+        }
+        find_variant(mica_request.variant.as_deref())?;
+        self.check_rules(mica_request.rules.as_ref(), mica_request.draw_after)?;
+        if let Some(evaluator) = mica_request.evaluator.as_deref().filter(|&evaluator| !self.evaluators().contains(&evaluator)) {
+            return Err(Response::error(400, "unsupported evaluator", vec![format!("`{evaluator}` is not one of {}", self.evaluators().join(", "))]));
+        }
+
+        match mica_request.validation.unwrap_or(self.default_validation) {
+            ValidationMode::Trust => (),
+            ValidationMode::Strict => {
+                validate_request(&mica_request).map_err(|errors| Response::error(400, "invalid position", errors))?;
+            },
+            ValidationMode::Lenient => normalize_request(&mut mica_request),
+        }
+
+        let limits = self.caps.limits(&mica_request)?;
+        Ok((mica_request, limits))
+    }
+
+    // what move requests may evaluate positions with, `nn` once a network is loaded
+    fn evaluators(&self) -> Vec<&'static str> {
+        #[cfg(feature = "nn")]
+        if self.network.is_some() {
+            return vec!["classic", "nn"];
+        }
+        vec!["classic"]
+    }
+
+    // the position of a request, evaluated by the evaluator it asks for
+    fn position(&self, mica_request: MicaRequest) -> MicaState {
+        #[cfg(feature = "nn")]
+        if mica_request.evaluator.as_deref() == Some("nn") {
+            let mut state = MicaState::from_request(mica_request);
+            state.network = self.network.clone();
+            return state;
+        }
+        MicaState::from_request(mica_request)
+    }
+
+    // Rule options the server does not allow are rejected, `draw_after`
+    // counts as one whether it is set in the options or on the request.
+    fn check_rules(&self, options: Option<&RuleOptions>, draw_after: Option<u16>) -> Result<(), Response> {
+        let options = options.copied().unwrap_or_default();
+        if let (Some(request), Some(option)) = (draw_after, options.draw_after) {
+            if request != option {
+                return Err(Response::error(400, "invalid request", vec![format!("draw_after is {request} but rules.draw_after is {option}")]));
+            }
+        }
+        let mut names = options.names();
+        if draw_after.is_some() && options.draw_after.is_none() {
+            names.push("draw_after");
+        }
+        let refused: Vec<String> = names
+            .into_iter()
+            .filter(|name| !self.rule_options.contains(name))
+            .map(|name| format!("`{name}` can not be changed on this server, only {}", self.rule_options.join(", ")))
+            .collect();
+        if !refused.is_empty() {
+            return Err(Response::error(400, "rule option not allowed", refused));
+        }
+        Ok(())
+    }
+
+    // The limits a new move request searches with while the pool is as busy
+    // as it is now, and how they were cut, see `SaturationPolicy`.
+    fn admit(&self, limits: SearchLimits) -> Result<(SearchLimits, Option<Degraded>), Response> {
+        let queue_wait = self.pool.queue_wait();
+        if queue_wait <= self.saturation_threshold || self.deterministic {
+            return Ok((limits, None));
+        }
+        match self.saturation {
+            SaturationPolicy::Queue => Ok((limits, None)),
+            SaturationPolicy::Reject => Err(Response::error(503, "server busy", vec![format!(
+                "searches have waited {}ms for a thread, more than the {}ms allowed",
+                queue_wait.as_millis(),
+                self.saturation_threshold.as_millis()
+            )])),
+            SaturationPolicy::Degrade => {
+                let degraded = degrade(&limits, queue_wait, self.saturation_threshold);
+                Ok((degraded, Some(Degraded {
+                    queue_wait_ms: queue_wait.as_millis() as u64,
+                    max_depth: degraded.max_depth,
+                    time_ms: degraded.max_time.map(|time| time.as_millis() as u64),
+                    max_nodes: degraded.max_nodes,
+                })))
+            },
+        }
+    }
+
+    // makes `cancelled` the flag of the search for `game_id`, cancelling the one it replaces
+    fn claim_game(&self, game_id: &str, cancelled: &Arc<AtomicBool>) {
+        let replaced = self.game_searches.lock().unwrap().insert(game_id.to_string(), Arc::clone(cancelled));
+        if let Some(replaced) = replaced {
+            replaced.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // forgets the search for `game_id` unless a newer one has taken its place
+    fn release_game(&self, game_id: &str, cancelled: &Arc<AtomicBool>) {
+        let mut game_searches = self.game_searches.lock().unwrap();
+        if game_searches.get(game_id).is_some_and(|current| Arc::ptr_eq(current, cancelled)) {
+            game_searches.remove(game_id);
+        }
+    }
+
+    // Searches with contempt, a quiet move draw or rule options of their own
+    // score positions differently and get a table of their own, as does every
+    // deterministic search and every one evaluated by the network. Every search
+    // gets the tablebase and the server's evaluation weights.
+    fn with_tt(&self, mut state: MicaState) -> MicaState {
+        state.tt = Some(match (state.contempt, state.draw_after) {
+            (None, None) if state.rules == variants::DEFAULT.rules && !self.deterministic && !state.has_network() => Arc::clone(&self.tt),
+            _ => Arc::new(TranspositionTable::new(16)),
+        });
+        state.tablebase = self.tablebase.clone();
+        state.weights = self.weights;
+        state
+    }
+
+    // the engine of `--search-mode`, answers without per root move scores are enough
+    fn engine(&self, scope: &TaskScope) -> Box<dyn Engine> {
+        let (pool, scope, deterministic) = (Arc::clone(&self.pool), scope.clone(), self.deterministic);
+        match self.search_mode {
+            SearchMode::LazySmp if !deterministic => Box::new(LazySmp { pool, scope, helpers: SEARCH_THREADS }),
+            _ => Box::new(AlphaBeta { pool, scope, margin: Some(0), multi_pv: 1, deterministic }),
+        }
+    }
+
+    // alpha-beta whatever the search mode, for answers that need a score for every
+    // root move within `margin` of the best `multi_pv` ones, see `get_best_move`
+    fn root_engine(&self, scope: &TaskScope, margin: Option<i32>, multi_pv: usize) -> AlphaBeta {
+        AlphaBeta { pool: Arc::clone(&self.pool), scope: scope.clone(), margin, multi_pv, deterministic: self.deterministic }
+    }
+
+    fn search_uncached(&self, state: MicaState, limits: &SearchLimits, scope: &TaskScope) -> SearchResult {
+        self.engine(scope).best_move(&self.with_tt(state), limits)
+    }
+
+    // Searches through the response cache, hits are answered without touching
+    // the pool. A search for a position and limits already being searched
+    // waits for that search and is answered like a cache hit, unless the
+    // search it waits for gets cancelled.
+    fn search(&self, state: MicaState, limits: &SearchLimits, scope: &TaskScope) -> SearchResult {
+        // the key leaves out the quiet moves a draw is counted from, the rule options and the evaluator
+        if state.draw_after.is_some() || state.rules != variants::DEFAULT.rules || state.has_network() {
+            return self.search_uncached(state, limits, scope);
+        }
+        let (key, symmetry) = canonical_key(&state);
+        // MicaState only plays nine men's morris
+        let cache_key = (key, *limits, variants::NINE.name);
+        let from_cache = |(canonical_move, score, depth, canonical_pv): CachedSearch| {
+            let inverse = symmetry.inverse();
+            let pv = canonical_pv.into_iter().map(|m| inverse.apply_move(m)).collect();
+            SearchResult { best_move: canonical_move.map(|m| inverse.apply_move(m)), score, stats: SearchStats::default(), depth, roots: Vec::new(), iterations: Vec::new(), pv, queue_wait: Duration::ZERO }
+        };
+
+        let flight = loop {
+            if let Some(cached) = self.cache.lock().unwrap().get(&cache_key) {
+                return from_cache(cached);
+            }
+            let running = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get(&cache_key) {
+                    Some(flight) => Arc::clone(flight),
+                    None => {
+                        let flight = Arc::new(InFlight::default());
+                        in_flight.insert(cache_key, Arc::clone(&flight));
+                        break flight;
+                    },
+                }
+            };
+            match running.wait(scope.cancelled()) {
+                Some(Some(cached)) => return from_cache(cached),
+                // the search we waited for was cancelled, start over
+                Some(None) => continue,
+                // nobody is waiting for this answer any more
+                None => return from_cache((None, state.eval(), 0, Vec::new())),
+            }
+        };
+
+        // requests waiting for a search that panicked search again themselves
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| self.search_uncached(state, limits, scope))) {
+            Ok(result) => result,
+            Err(panicked) => {
+                self.in_flight.lock().unwrap().remove(&cache_key);
+                flight.finish(None);
+                panic::resume_unwind(panicked);
+            },
+        };
+        // book moves are picked at random per request, answers that searched nothing are not kept
+        let finished = (!scope.is_cancelled() && result.stats.nodes > 0).then(|| {
+            let pv = result.pv.iter().map(|&m| symmetry.apply_move(m)).collect();
+            (result.best_move.map(|m| symmetry.apply_move(m)), result.score, result.depth, pv)
+        });
+        if let Some(cached) = &finished {
+            self.cache.lock().unwrap().insert(cache_key, cached.clone());
+        }
+        self.in_flight.lock().unwrap().remove(&cache_key);
+        flight.finish(finished);
+        result
+    }
+
+    fn best_move(self: &Arc<Self>, request: &Request, received_at: u64, scope: &TaskScope) -> Result<Response, Response> {
+        let parse_started = Instant::now();
+        let (mica_request, limits) = self.parse_request(&request.body)?;
+        // streamed answers have no field to say their limits were cut in
+        let (limits, degraded) = self.admit(limits)?;
+        if mica_request.stream {
+            return Ok(self.stream_best_move(mica_request, limits, api_key(request), scope));
+        }
+        self.answer_move(request, mica_request, (limits, degraded), parse_started, received_at, scope)
+    }
+
+    // Answers a parsed and admitted move request, `parse_started` is when its body was read.
+    fn answer_move(
+        &self,
+        request: &Request,
+        mut mica_request: MicaRequest,
+        (limits, degraded): (SearchLimits, Option<Degraded>),
+        parse_started: Instant,
+        received_at: u64,
+        scope: &TaskScope,
+    ) -> Result<Response, Response> {
+        let player = mica_request.player;
+        let request_id = mica_request.request_id.take();
+        let difficulty = difficulty(&mica_request)?;
+
+        let agent = match mica_request.engine.as_deref() {
+            None => Agent::Minimax(limits.max_depth),
+            Some(engine) => engine.parse().map_err(|e| Response::error(400, "invalid request", vec![e]))?,
+        };
+        let profile = match mica_request.profile.as_deref() {
+            None => Profile::Minimal,
+            Some(profile) => profile.parse().map_err(|e| Response::error(400, "invalid request", vec![e]))?,
+        };
+        let multi_pv = mica_request.multi_pv.unwrap_or(1);
+        if multi_pv == 0 {
+            return Err(Response::error(400, "invalid request", vec!["multi_pv must be at least 1".to_string()]));
+        }
+        let tie_margin = mica_request.tie_margin;
+        if tie_margin.is_some_and(|tie_margin| tie_margin < 0) {
+            return Err(Response::error(400, "invalid request", vec!["tie_margin must not be negative".to_string()]));
+        }
+        let seed = mica_request.seed.unwrap_or_else(|| self.seed());
+        let variant = find_variant(mica_request.variant.as_deref())?;
+        let parsed_in = parse_started.elapsed();
+
+        let started = Instant::now();
+        let game_id = mica_request.game_id.take();
+        if let Some(game_id) = &game_id {
+            self.claim_game(game_id, scope.cancelled());
+        }
+        let contempt = mica_request.contempt.unwrap_or(self.default_contempt);
+        let mut state = self.position(mica_request);
+        state.set_contempt(contempt);
+        state.book = self.book.clone();
+        let noise = difficulty.map_or(0, Difficulty::noise) as i32;
+        let search = match (agent, difficulty) {
+            // random answers change from request to request, the cache keeps a
+            // single line and scores without contempt, all of them bypass it
+            (Agent::Minimax(_), _) if noise > 0 || multi_pv > 1 || tie_margin.is_some() || state.contempt.is_some() => {
+                // moves that cannot be picked or lifted above the best ones need no
+                // exact score, a move tying the lowest one a tie margin allows needs one
+                let margin = noise.max(tie_margin.map_or(0, |tie_margin| tie_margin + 1));
+                let mut search = self.root_engine(scope, Some(margin), multi_pv).best_move(&self.with_tt(state.clone()), &limits);
+                let side = state.current_player as i32;
+                let mut rng = Rng::new(seed);
+                let picked = match tie_margin {
+                    Some(tie_margin) => search.pick_within(side, tie_margin, &mut rng).cloned(),
+                    None => difficulty.and_then(|difficulty| difficulty.pick(&search.roots, side, &mut rng)),
+                };
+                if let Some(root) = picked {
+                    search.best_move = Some(root.mica_move);
+                    search.score = root.score.unwrap();
+                    search.pv = root.pv;
+                }
+                search
+            },
+            (Agent::Minimax(_), _) => self.search(state.clone(), &limits, scope),
+            (baseline, _) => {
+                SearchResult { best_move: baseline.choose(&state, &mut Rng::new(seed)), score: state.eval(), stats: SearchStats::default(), depth: 0, roots: Vec::new(), iterations: Vec::new(), pv: Vec::new(), queue_wait: Duration::ZERO }
+            },
+        };
+        let searched_in = started.elapsed();
+        let searched_at = Instant::now();
+        let computed_in_ms = searched_in.as_millis() as u64;
+        self.usage.record(&api_key(request), search.stats.nodes, computed_in_ms);
+        eprintln!("Move search {}: depth={} {}", format_position(PositionFormat::Fen, &state), search.depth, search.stats);
+        if let Some(game_id) = &game_id {
+            self.release_game(game_id, scope.cancelled());
+            // a client that hung up never sees this, so it is for a newer request
+            if scope.is_cancelled() {
+                return Err(Response::error(409, "superseded", vec![format!("a newer move request for game `{game_id}` arrived")]));
+            }
+        }
+
+        let depth = search.depth;
+        // answers without a collected line would cost another search per ply, so only rich answers get one
+        let pv = match profile {
+            Profile::Rich => pv(&state, &search, depth),
+            Profile::Minimal => Vec::new(),
+        };
+        let mut after = state.clone();
+        if let Some(best_move) = search.best_move {
+            after.apply_move(best_move);
+        }
+        let response = MoveResponse {
+            mica_move: move_json(search.best_move, player)["move"].take(),
+            changes: search.best_move.map_or(Vec::new(), |best_move| move_changes(&state, best_move)),
+            echo: request_id.map(|request_id| RequestEcho {
+                request_id,
+                received_at,
+                computed_in_ms,
+                engine_version: env!("CARGO_PKG_VERSION"),
+            }),
+            expected_reply: search.pv.get(1).copied().map(move_to_notation),
+            degraded,
+            lines: match multi_pv {
+                1 => Vec::new(),
+                _ => search
+                    .ranked_roots(state.current_player as i32)
+                    .into_iter()
+                    .take(multi_pv)
+                    .filter(|root| root.exact)
+                    .map(|root| Line {
+                        mica_move: move_to_notation(root.mica_move),
+                        score: root.score.unwrap(),
+                        pv: root.pv.iter().copied().map(move_to_notation).collect(),
+                    })
+                    .collect(),
+            },
+            score: search.score,
+            pv: pv.into_iter().map(move_to_notation).collect(),
+            stats: Stats {
+                nodes: search.stats.nodes,
+                tt_hits: search.stats.tt_hits,
+                beta_cutoffs: search.stats.beta_cutoffs,
+                max_depth: search.stats.max_depth,
+                depth,
+                time_ms: computed_in_ms,
+            },
+            iterations: IterationStats::from_iterations(&search.iterations),
+            position: format_position(PositionFormat::Fen, &state),
+            phase: variant.phase(&state).name(),
+            mills: Mills {
+                formed: matches!(search.best_move, Some(MicaMove::SetRemove { .. } | MicaMove::MoveRemove { .. })),
+                points: POINTS
+                    .iter()
+                    .filter(|&&(x, y, z)| after.is_in_line(x, y, z))
+                    .map(|&(x, y, z)| to_notation(x, y, z).unwrap())
+                    .collect(),
+            },
+        };
+
+        let mut body = response.to_json(profile);
+        let latency = Latency {
+            parse_us: parsed_in.as_micros() as u64,
+            queue_us: search.queue_wait.as_micros() as u64,
+            search_us: searched_in.saturating_sub(search.queue_wait).as_micros() as u64,
+            serialize_us: searched_at.elapsed().as_micros() as u64,
+        };
+        if profile == Profile::Rich {
+            body["latency"] = serde_json::to_value(latency).unwrap();
+        }
+        let mut response = Response::json(200, body);
+        response.latency = Some(latency);
+        Ok(response)
+    }
+
+    // Answers the move requests of a batch of independent games in one go, a
+    // round of a tournament. Every position is admitted before any is
+    // searched, so the batch is cut to the same limits throughout when the
+    // pool is busy, and then all of them are searched side by side, their
+    // tasks taking turns on the pool. A position that can't be answered gets
+    // its error in its place and leaves the others be.
+    fn batch_moves(&self, request: &Request, received_at: u64, scope: &TaskScope) -> Result<Response, Response> {
+        let body: Value = serde_json::from_str(&request.body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        let positions = body
+            .get("positions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Response::error(400, "invalid request", vec!["`positions` is not an array".to_string()]))?;
+        if positions.len() > MAX_BATCH {
+            return Err(Response::error(400, "batch too large", vec![format!("{} positions, at most {MAX_BATCH} are answered at once", positions.len())]));
+        }
+
+        let admitted: Vec<_> = positions
+            .iter()
+            .map(|position| {
+                let parse_started = Instant::now();
+                let (mica_request, limits) = self.parse_request(&position.to_string())?;
+                if mica_request.stream {
+                    return Err(Response::error(400, "invalid request", vec!["streamed answers can't be batched".to_string()]));
+                }
+                Ok((mica_request, self.admit(limits)?, parse_started))
+            })
+            .collect();
+
+        // every position has a scope of its own, a newer request for its game cancels it alone
+        let scopes: Vec<TaskScope> = positions.iter().map(|_| TaskScope::new()).collect();
+        let done = AtomicBool::new(false);
+        let answers: Vec<Value> = thread::scope(|threads| {
+            threads.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    if scope.is_cancelled() {
+                        scopes.iter().for_each(TaskScope::cancel);
+                        break;
+                    }
+                    thread::sleep(DISCONNECT_POLL);
+                }
+            });
+            let searches: Vec<_> = admitted
+                .into_iter()
+                .zip(&scopes)
+                .map(|(admitted, entry_scope)| {
+                    threads.spawn(move || {
+                        let (mica_request, admitted, parse_started) = admitted?;
+                        self.answer_move(request, mica_request, admitted, parse_started, received_at, entry_scope)
+                    })
+                })
+                .collect();
+            let answers = searches
+                .into_iter()
+                .map(|search| {
+                    let response = search
+                        .join()
+                        .unwrap_or_else(|_| Err(Response::error(500, "internal error", vec!["the server failed to answer this position".to_string()])))
+                        .unwrap_or_else(|error| error);
+                    let body: Value = serde_json::from_str(&response.body).unwrap();
+                    match response.status {
+                        200 => json!({ "status": 200, "answer": body }),
+                        status => json!({ "status": status, "error": body }),
+                    }
+                })
+                .collect();
+            done.store(true, Ordering::Relaxed);
+            answers
+        });
+        // the entries' tasks are the batch's, none of them outlives it
+        scopes.iter().for_each(TaskScope::close);
+        Ok(Response::json(200, json!({ "answers": answers })))
+    }
+
+    // Searches one depth after the other up to the requested depth, sending
+    // `{"depth", "score", "nodes", "move"}` after every depth and the move of
+    // the deepest search with `"final": true` last. A client that stops
+    // reading cancels the search. The searches run in the request's scope,
+    // which is closed once the stream has been written.
+    fn stream_best_move(self: &Arc<Self>, mut mica_request: MicaRequest, limits: SearchLimits, api_key: String, scope: &TaskScope) -> Response {
+        let player = mica_request.player;
+        let request_id = mica_request.request_id.take();
+        let state = self.position(mica_request);
+        let (tx, rx) = mpsc::channel();
+
+        let (server, scope) = (Arc::clone(self), scope.clone());
+        thread::spawn(move || {
+            let started = Instant::now();
+            let mut best_move = None;
+            let mut expected_reply = None;
+            let mut nodes = 0;
+            for depth in 1..=limits.max_depth {
+                let remaining = limits.max_time.map(|max_time| max_time.saturating_sub(started.elapsed()));
+                if remaining == Some(Duration::ZERO) || scope.is_cancelled() {
+                    break;
+                }
+                let depth_limits = SearchLimits { max_depth: depth, max_time: remaining, ..limits };
+                let search = server.search(state.clone(), &depth_limits, &scope);
+                best_move = search.best_move;
+                expected_reply = search.pv.get(1).copied();
+                nodes += search.stats.nodes;
+
+                let mut line = move_json(search.best_move, player);
+                let line_fields = line.as_object_mut().unwrap();
+                line_fields.insert("depth".to_string(), json!(depth));
+                line_fields.insert("score".to_string(), json!(search.score));
+                line_fields.insert("nodes".to_string(), json!(search.stats.nodes));
+                if tx.send(line.to_string()).is_err() {
+                    scope.cancel();
+                    break;
+                }
+            }
+            server.usage.record(&api_key, nodes, started.elapsed().as_millis() as u64);
+
+            let mut result = move_json(best_move, player);
+            let fields = result.as_object_mut().unwrap();
+            fields.insert("final".to_string(), json!(true));
+            fields.insert("expected_reply".to_string(), json!(expected_reply.map(move_to_notation)));
+            if let Some(request_id) = request_id {
+                fields.insert("request_id".to_string(), request_id);
+            }
+            let _ = tx.send(result.to_string());
+        });
+
+        Response::stream(200, rx)
+    }
+
+    fn run_job(self: &Arc<Self>, id: u64, cancelled: Arc<AtomicBool>, parsed: (MicaRequest, SearchLimits), api_key: String) {
+        let server = Arc::clone(self);
+        thread::spawn(move || server.process_job(id, cancelled, parsed, &api_key));
+    }
+
+    // runs queued jobs one after another on a single thread, for batches too
+    // large for a thread each
+    fn run_jobs(self: &Arc<Self>, jobs: Vec<(u64, Arc<AtomicBool>, (MicaRequest, SearchLimits))>, api_key: String) {
+        let server = Arc::clone(self);
+        thread::spawn(move || {
+            for (id, cancelled, parsed) in jobs {
+                server.process_job(id, cancelled, parsed, &api_key);
+            }
+        });
+    }
+
+    // The job's tasks run in a scope cancelled with the job, closed when it is done.
+    fn process_job(&self, id: u64, cancelled: Arc<AtomicBool>, parsed: (MicaRequest, SearchLimits), api_key: &str) {
+        let scope = TaskScope::with_flag(cancelled);
+        self.answer_job(id, &scope, parsed, api_key);
+        scope.close();
+    }
+
+    fn answer_job(&self, id: u64, scope: &TaskScope, (mut mica_request, limits): (MicaRequest, SearchLimits), api_key: &str) {
+        let callback_url = mica_request.callback_url.take();
+        let player = mica_request.player;
+        let keep_details = mica_request.details;
+        let mut state = self.position(mica_request);
+        state.pause = self.job_pause;
+
+        self.jobs.start(id);
+        let started = Instant::now();
+        // cached answers have no per root move data, so detailed jobs always search
+        let search = if keep_details {
+            self.root_engine(scope, None, 1).best_move(&self.with_tt(state.clone()), &limits)
+        } else {
+            self.search(state.clone(), &limits, scope)
+        };
+        self.usage.record(api_key, search.stats.nodes, started.elapsed().as_millis() as u64);
+        eprintln!("Job {id} search: depth={} {}", search.depth, search.stats);
+        if scope.is_cancelled() {
+            return;
+        }
+        let mut result = move_json(search.best_move, player);
+        self.attach_notes(id, &state, &mut result);
+        self.jobs.finish(id, result);
+        if keep_details {
+            self.jobs.keep_details(id, details_json(&state, &search, limits.max_depth), self.details_ttl);
+        }
+        if self.jobs.memory_bytes() > self.memory_limit {
+            let evicted = self.jobs.evict(self.memory_limit);
+            eprintln!("Job store over its memory limit, evicted {evicted} finished jobs");
+        }
+        if let (Some(url), Some(job)) = (callback_url, self.jobs.get(id)) {
+            notify_callback(&url, &job);
+        }
+    }
+
+    fn submit_analysis(self: &Arc<Self>, request: &Request) -> Result<Response, Response> {
+        let body = upload_body(request)?;
+        let parsed = self.parse_request(&body)?;
+        if let Some(url) = &parsed.0.callback_url {
+            if http::parse_url(url).is_none() {
+                return Err(Response::error(400, "invalid callback_url", vec![format!("`{url}` is not an http:// url")]));
+            }
+        }
+
+        let (id, cancelled) = self.jobs.create(body.into_owned());
+        self.run_job(id, cancelled, parsed, api_key(request));
+
+        Ok(Response::json(202, json!({ "id": id, "status": JobStatus::Queued })))
+    }
+
+    // Queues analysis jobs for every position in a JSONL body and answers
+    // with the job ids. A line is either a position as sent to `/analysis` or
+    // a game record with `moves` as gym actions or notation. A game queues
+    // every position of the game that is still in play, with the line's
+    // `depth`, `time_ms` and `max_nodes`. Nothing is queued when any line is
+    // invalid. The jobs run one after another.
+    fn import(self: &Arc<Self>, request: &Request) -> Result<Response, Response> {
+        let body = upload_body(request)?;
+        let mut positions = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in body.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match import_line(line) {
+                Ok(bodies) => positions.extend(bodies.into_iter().map(|(ply, body)| (i + 1, ply, body))),
+                Err(e) => errors.push(format!("line {}: {e}", i + 1)),
+            }
+        }
+        let mut parsed = Vec::with_capacity(positions.len());
+        for (line, _, body) in &positions {
+            match self.parse_request(body) {
+                Ok(request) => parsed.push(request),
+                Err(response) => {
+                    let error: Value = serde_json::from_str(&response.body).unwrap();
+                    errors.push(format!("line {line}: {}: {}", error["error"].as_str().unwrap(), error["details"]));
+                },
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Response::error(400, "invalid import", errors));
+        }
+        if positions.is_empty() {
+            return Err(Response::error(400, "invalid import", vec!["no positions to analyse".to_string()]));
+        }
+
+        let mut manifest = Vec::with_capacity(positions.len());
+        let mut jobs = Vec::with_capacity(positions.len());
+        for ((line, ply, body), parsed) in positions.into_iter().zip(parsed) {
+            let (id, cancelled) = self.jobs.create(body);
+            manifest.push(json!({ "line": line, "ply": ply, "id": id }));
+            jobs.push((id, cancelled, parsed));
+        }
+        self.run_jobs(jobs, api_key(request));
+        Ok(Response::json(202, json!({ "jobs": manifest })))
+    }
+
+    // restarts the jobs that were still pending when the previous process stopped
+    fn resume_jobs(self: &Arc<Self>) {
+        let mut resumed = Vec::new();
+        for (id, body, cancelled) in self.jobs.queued() {
+            match self.parse_request(&body) {
+                Ok(parsed) => resumed.push((id, cancelled, parsed)),
+                Err(_) => {
+                    eprintln!("Dropping persisted job {id}, its request no longer parses");
+                    self.jobs.cancel(id);
+                },
+            }
+        }
+        // an interrupted import can leave thousands of these
+        self.run_jobs(resumed, "resumed".to_string());
+    }
+
+    fn analysis_status(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "job")?;
+        let job = self.jobs.get(id).ok_or_else(|| Response::error(404, "unknown job", vec![format!("no job with id {id}")]))?;
+        Ok(Response::json(200, job))
+    }
+
+    fn analysis_details(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "job")?;
+        let details = self.jobs.details(id).ok_or_else(|| {
+            Response::error(404, "no details", vec![format!("job {id} kept no details, or they have expired")])
+        })?;
+        Ok(Response::json(200, json!({ "id": id, "details": details })))
+    }
+
+    fn cancel_analysis(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "job")?;
+        let status = self.jobs.cancel(id).ok_or_else(|| Response::error(404, "unknown job", vec![format!("no job with id {id}")]))?;
+        Ok(Response::json(200, json!({ "id": id, "status": status })))
+    }
+
+    // notes are kept for the canonical image, arrows are turned back to the analysed position
+    #[cfg(feature = "notes")]
+    fn attach_notes(&self, id: u64, state: &MicaState, result: &mut Value) {
+        let (key, symmetry) = canonical_key(state);
+        result["position_hash"] = json!(position(key));
+        match self.notes.get(key) {
+            Ok(Some(annotation)) => result["notes"] = json!(annotation.transformed(&symmetry.inverse())),
+            Ok(None) => (),
+            Err(e) => eprintln!("Failed to read the notes of job {id}: {e}"),
+        }
+    }
+
+    #[cfg(not(feature = "notes"))]
+    fn attach_notes(&self, _id: u64, _state: &MicaState, _result: &mut Value) {}
+
+    #[cfg(feature = "notes")]
+    fn position_notes(&self, hash: &str) -> Result<Response, Response> {
+        let key = parse_position_hash(hash)?;
+        let annotation = self.notes.get(key).map_err(notes_error)?;
+        let annotation = annotation.ok_or_else(|| Response::error(404, "no notes", vec![format!("position {hash} has no notes")]))?;
+        Ok(Response::json(200, json!({ "position": position(key), "notes": annotation })))
+    }
+
+    // replaces the notes of a position, arrows are drawn on its canonical image
+    #[cfg(feature = "notes")]
+    fn put_position_notes(&self, hash: &str, request: &Request) -> Result<Response, Response> {
+        let key = parse_position_hash(hash)?;
+        let annotation: Annotation = serde_json::from_str(&request.body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        annotation.validate().map_err(|errors| Response::error(400, "invalid notes", errors))?;
+        self.notes.put(key, &annotation).map_err(notes_error)?;
+        Ok(Response::json(200, json!({ "position": position(key), "notes": annotation })))
+    }
+
+    #[cfg(feature = "notes")]
+    fn delete_position_notes(&self, hash: &str) -> Result<Response, Response> {
+        let key = parse_position_hash(hash)?;
+        if !self.notes.delete(key).map_err(notes_error)? {
+            return Err(Response::error(404, "no notes", vec![format!("position {hash} has no notes")]));
+        }
+        Ok(Response::json(200, json!({ "position": position(key), "deleted": true })))
+    }
+
+    // for requests and games that bring no seed of their own
+    fn seed(&self) -> u64 {
+        match self.deterministic {
+            true => 0,
+            false => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+        }
+    }
+
+    // One iterative-deepening search that stops at the budget, see
+    // `SearchLimits::deadlines`, or once the request's `scope` is cancelled.
+    // A deterministic server searches to its maximum depth instead.
+    fn timed_search(&self, state: &MicaState, budget: Duration, scope: &TaskScope) -> SearchResult {
+        let mut state = state.clone();
+        state.book = self.book.clone();
+        let max_time = (!self.deterministic).then_some(budget);
+        let limits = SearchLimits { max_depth: self.caps.depth, max_nodes: Some(self.caps.nodes), max_time };
+        self.search(state, &limits, scope)
+    }
+
+    // Lets the engine move when it is its turn. The session is unlocked while
+    // it thinks and marked as thinking, other requests for the game get a
+    // 409 until the move is played.
+    fn engine_turn(&self, session: &Mutex<Session>, scope: &TaskScope) -> Result<Option<MicaMove>, Response> {
+        let (state, budget, personality, mut rng, ply) = {
+            let mut session = session.lock().unwrap();
+            if session.thinking {
+                return Err(engine_thinking());
+            }
+            if session.outcome.is_some() || session.state.current_player != session.engine {
+                return Ok(None);
+            }
+            session.thinking = true;
+            let remaining = session.clock.remaining(session.engine);
+            let budget = clock::allocate(&session.state, remaining, session.clock.increment);
+            (session.state.clone(), budget, session.personality, session.move_rng(), session.moves.len())
+        };
+        let mut thinking = Thinking { session, armed: true };
+
+        let started = Instant::now();
+        let (best_move, search) = match personality {
+            Some(personality) => (personality.choose(&state, &mut rng), None),
+            None => {
+                let search = self.timed_search(&state, budget, scope);
+                (search.best_move, Some(search))
+            },
+        };
+        let mut session = session.lock().unwrap();
+        session.thinking = false;
+        thinking.armed = false;
+        // a client that hung up does not get a move cut short, the game waits for its next request
+        let Some(best_move) = best_move.filter(|_| !scope.is_cancelled()) else {
+            return Ok(None);
+        };
+        // nothing else plays while the engine thinks, a game that moved on anyway keeps its moves
+        if session.moves.len() != ply || session.state.current_player != session.engine || session.outcome.is_some() {
+            return Ok(None);
+        }
+        session.play(best_move).map_err(|IllegalMove(mica_move)| {
+            Response::error(500, "internal error", vec![format!("the engine chose `{}`, which is not legal here", move_to_notation(mica_move))])
+        })?;
+        // a move that lost on time was never played
+        if session.moves.len() > ply {
+            session.audit.push(AuditEntry {
+                ply,
+                mica_move: move_to_notation(best_move),
+                source: if personality.is_some() { "personality" } else { "search" }.to_string(),
+                personality: personality.map(|personality| personality.name.to_string()),
+                budget_ms: budget.as_millis() as u64,
+                max_depth: self.caps.depth,
+                max_nodes: self.caps.nodes,
+                depth: search.as_ref().map(|search| search.depth),
+                score: search.as_ref().map(|search| search.score),
+                nodes: search.as_ref().map(|search| search.stats.nodes),
+                time_ms: started.elapsed().as_millis() as u64,
+                engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            });
+        }
+        Ok(Some(best_move))
+    }
+
+    fn game_json(&self, id: u64, session: &Mutex<Session>, engine_move: Option<MicaMove>) -> Value {
+        let mut game = session.lock().unwrap().to_json(id);
+        game.as_object_mut().unwrap().insert("engine_move".to_string(), json!(engine_move.map(move_to_notation)));
+        game
+    }
+
+    fn create_game(&self, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let body = if request.body.trim().is_empty() { "{}" } else { &request.body };
+        let game: GameRequest = serde_json::from_str(body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        let engine = match game.engine.value() {
+            1 => MicaPlayer::White,
+            -1 => MicaPlayer::Black,
+            value => return Err(Response::error(400, "invalid request", vec![format!("invalid engine side {value}")])),
+        };
+
+        let variant = find_variant(game.variant.as_deref())?;
+        let personality = match game.personality.as_deref() {
+            None => None,
+            Some(name) => Some(personality::find(name).ok_or_else(|| {
+                Response::error(400, "unknown personality", vec![format!("`{name}` is not one of {}", personality::names().join(", "))])
+            })?),
+        };
+        self.check_rules(game.rules.as_ref(), None)?;
+        let seed = game.seed.unwrap_or_else(|| self.seed());
+        let clock = Clock::new(Duration::from_millis(game.time_ms), Duration::from_millis(game.increment_ms));
+        let id = self.sessions.create(Session::new(variant, game.rules.unwrap_or_default(), engine, personality, seed, clock));
+        let session = self.sessions.get(id).unwrap();
+        let engine_move = self.engine_turn(&session, scope)?;
+        self.sessions.save();
+        Ok(Response::json(201, self.game_json(id, &session, engine_move)))
+    }
+
+    fn game_status(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let game = session.lock().unwrap().to_json(id);
+        Ok(Response::json(200, game))
+    }
+
+    // how the engine came to each of its moves in the game
+    fn game_audit(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let audit = session.lock().unwrap().audit.clone();
+        Ok(Response::json(200, json!({ "id": id, "moves": audit })))
+    }
+
+    // the game as a game record with its headers, as `mica train` and `mica match --out` write them
+    fn game_record(&self, id: &str) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let record = session.lock().unwrap().record();
+        Ok(Response::json(200, serde_json::to_value(record).unwrap()))
+    }
+
+    // the score graph of the game so far, see `score_graph`
+    fn game_graph(&self, id: &str, scope: &TaskScope) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let (start, moves) = {
+            let session = session.lock().unwrap();
+            (Session::start(session.variant, session.rules), session.moves.clone())
+        };
+        let graph = self.score_graph(start, &moves, GRAPH_DEPTH.min(self.caps.depth), scope);
+        Ok(Response::json(200, serde_json::to_value(graph).unwrap()))
+    }
+
+    // the score graph of a game played elsewhere, its moves checked as they are replayed
+    fn submitted_graph(&self, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let graph: GraphRequest = serde_json::from_str(&request.body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        if let Some(depth) = graph.depth.filter(|&depth| depth > self.caps.depth) {
+            return Err(Response::error(400, "limit exceeded", vec![format!("depth {depth} exceeds the server maximum of {}", self.caps.depth)]));
+        }
+        if graph.moves.len() > MAX_GRAPH_PLIES {
+            return Err(Response::error(400, "game too long", vec![format!("{} moves, at most {MAX_GRAPH_PLIES} are scored", graph.moves.len())]));
+        }
+        let variant = find_variant(graph.variant.as_deref())?;
+        self.check_rules(graph.rules.as_ref(), None)?;
+
+        let start = Session::start(variant, graph.rules.unwrap_or_default());
+        let mut state = start.clone();
+        let mut moves = Vec::with_capacity(graph.moves.len());
+        for (ply, next) in graph.moves.iter().enumerate() {
+            let invalid = |e: String| Response::error(400, "invalid game", vec![e]);
+            if state.game_over_value().is_some() {
+                return Err(invalid(format!("move {} ({next}) comes after the game is over", ply + 1)));
+            }
+            let mica_move = parse_game_move(ply, next).map_err(invalid)?;
+            state.checked_apply_move(mica_move).map_err(|_| invalid(format!("move {} ({next}) is illegal", ply + 1)))?;
+            state.current_player.toggle();
+            moves.push(mica_move);
+        }
+        let graph = self.score_graph(start, &moves, graph.depth.unwrap_or(GRAPH_DEPTH.min(self.caps.depth)), scope);
+        Ok(Response::json(200, serde_json::to_value(graph).unwrap()))
+    }
+
+    // Scores every position of a game from `start`, the moves legal in the
+    // position before them, with a search of `depth` plies each. Finished
+    // positions score as the game ended.
+    fn score_graph(&self, start: MicaState, moves: &[MicaMove], depth: u8, scope: &TaskScope) -> ScoreGraph {
+        let limits = SearchLimits { max_depth: depth, max_nodes: Some(self.caps.nodes), max_time: None };
+        let mut graph = ScoreGraph {
+            moves: moves.iter().copied().map(move_to_notation).collect(),
+            scores: Vec::with_capacity(moves.len() + 1),
+            white_stones: Vec::with_capacity(moves.len() + 1),
+            black_stones: Vec::with_capacity(moves.len() + 1),
+            depth,
+        };
+        let mut state = start;
+        for ply in 0..=moves.len() {
+            let score = match state.game_over_value() {
+                Some(value) => value,
+                None => self.search(state.clone(), &limits, scope).score,
+            };
+            let ((white_to_set, black_to_set), (white_on_board, black_on_board)) = (state.stones_to_set(), state.stones_on_board());
+            graph.scores.push(score);
+            graph.white_stones.push(white_on_board + white_to_set);
+            graph.black_stones.push(black_on_board + black_to_set);
+            let Some(&mica_move) = moves.get(ply) else {
+                break;
+            };
+            state.apply_move(mica_move);
+            state.current_player.toggle();
+        }
+        graph
+    }
+
+    fn game_move(&self, id: &str, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let body = if request.body.trim().is_empty() { "{}" } else { &request.body };
+        let game_move: GameMove = serde_json::from_str(body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+
+        {
+            let mut session = session.lock().unwrap();
+            if session.outcome.is_some() {
+                return Err(Response::error(409, "game over", vec![format!("game {id} has finished")]));
+            }
+            if session.thinking {
+                return Err(engine_thinking());
+            }
+            if session.state.current_player != session.engine {
+                let notation = game_move.mica_move.ok_or_else(|| Response::error(400, "invalid request", vec!["missing move".to_string()]))?;
+                let illegal = || Response::error(400, "illegal move", vec![format!("`{notation}` is not legal here")]);
+                let mica_move = move_from_notation(&notation).ok_or_else(illegal)?;
+                session.play(mica_move).map_err(|_| illegal())?;
+            }
+        }
+
+        let engine_move = self.engine_turn(&session, scope)?;
+        self.sessions.save();
+        Ok(Response::json(200, self.game_json(id, &session, engine_move)))
+    }
+
+    // whether the side to move should resign or take a draw
+    fn advice(&self, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let thresholds = parse_thresholds(&request.body)?;
+        let (mica_request, limits) = self.parse_request(&request.body)?;
+        let state = self.position(mica_request);
+        let side = state.current_player as i32;
+        let search = self.search(state, &limits, scope);
+        Ok(Response::json(200, advice_json(search.score * side, &thresholds)))
+    }
+
+    // the same advice for the engine's side of a game session
+    fn game_advice(&self, id: &str, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let id = parse_id(id, "game")?;
+        let session = self.sessions.get(id).ok_or_else(|| Response::error(404, "unknown game", vec![format!("no game with id {id}")]))?;
+        let thresholds = parse_thresholds(&request.body)?;
+        let (state, engine) = {
+            let session = session.lock().unwrap();
+            (session.state.clone(), session.engine)
+        };
+        let search = self.search(state, &self.caps.default_limits(), scope);
+        Ok(Response::json(200, advice_json(search.score * engine as i32, &thresholds)))
+    }
+
+    // How hard the position of a move request is for a human to find the
+    // best move in, searched with the request's limits.
+    fn position_difficulty(&self, request: &Request, scope: &TaskScope) -> Result<Response, Response> {
+        let (mica_request, limits) = self.parse_request(&request.body)?;
+        let state = self.position(mica_request);
+        let side = state.current_player as i32;
+        // cached answers have no per root move scores and the estimate needs every one exact
+        let search = self.root_engine(scope, None, 1).best_move(&self.with_tt(state), &limits);
+        let difficulty = PositionDifficulty::estimate(&search, side);
+        Ok(Response::json(200, serde_json::to_value(DifficultyAnswer::new(&difficulty, search.depth)).unwrap()))
+    }
+
+    // Whether a position from a board editor could come up in a game, and the
+    // closest legal position when it could not. The correction is a move
+    // request body, with the problems it still has.
+    fn validate_position(&self, request: &Request) -> Result<Response, Response> {
+        let mut mica_request: MicaRequest = serde_json::from_str(&request.body)
+            .map_err(|e| Response::error(400, "invalid request", vec![e.to_string()]))?;
+        let errors = |mica_request: &MicaRequest| {
+            let mut errors = validate_request(mica_request).err().unwrap_or_default();
+            errors.extend(reachability_errors(mica_request));
+            errors
+        };
+
+        let position_errors = errors(&mica_request);
+        if position_errors.is_empty() {
+            return Ok(Response::json(200, json!({ "legal": true, "errors": position_errors, "correction": null })));
+        }
+        correct_request(&mut mica_request);
+        let correction_errors = errors(&mica_request);
+        let correction: Value = serde_json::from_str(&format_position(PositionFormat::Json, &MicaState::from_request(mica_request))).unwrap();
+        Ok(Response::json(200, json!({
+            "legal": false,
+            "errors": position_errors,
+            "correction": correction,
+            "correction_errors": correction_errors,
+        })))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Capabilities {
+            schema_version: SCHEMA_VERSION,
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            request_fields: strings(&MicaRequest::FIELDS),
+            variants: strings(&variants::names()),
+            default_variant: variants::DEFAULT.name.to_string(),
+            rule_options: strings(&self.rule_options),
+            validation_modes: strings(&["trust", "strict", "lenient"]),
+            profiles: strings(&["minimal", "rich"]),
+            difficulties: strings(&["easy", "medium", "hard"]),
+            engines: strings(&["minimax", "minimax:<depth>", "random", "greedy"]),
+            evaluators: strings(&self.evaluators()),
+            position_format: "fen".to_string(),
+            move_formats: strings(&["notation", "actions"]),
+            limits: CapabilityLimits {
+                depth: self.caps.depth,
+                time_ms: self.caps.time_ms,
+                max_nodes: self.caps.nodes,
+                batch: MAX_BATCH,
+                graph_plies: MAX_GRAPH_PLIES,
+            },
+        }
+    }
+
+    fn status(&self) -> Response {
+        Response::json(200, json!({
+            "resident_bytes": resident_bytes(),
+            "memory_limit_bytes": self.memory_limit,
+            "jobs": {
+                "count": self.jobs.len(),
+                "bytes": self.jobs.memory_bytes(),
+            },
+            "sessions": self.sessions.len(),
+            "usage_clients": self.usage.clients(),
+            "cache_entries": self.cache.lock().unwrap().len(),
+            "search_mode": self.search_mode.name(),
+            "queue_wait_ms": self.pool.queue_wait().as_millis() as u64,
+            "saturation": self.saturation.name(),
+            "deterministic": self.deterministic,
+            "evaluators": self.evaluators(),
+            // the version of the default weights, see `tuned`, or `custom` for `--eval-weights`
+            "eval_weights": if self.weights == tuned::WEIGHTS { format!("v{}", tuned::VERSION) } else { "custom".to_string() },
+        }))
+    }
+
+    fn usage_report(&self, request: &Request) -> Result<Response, Response> {
+        let Some(token) = &self.admin_token else {
+            return Err(Response::error(403, "forbidden", vec!["the server was started without --admin-token".to_string()]));
+        };
+        if request.header("X-Admin-Token") != Some(token.as_str()) {
+            return Err(Response::error(401, "unauthorized", vec!["X-Admin-Token is missing or wrong".to_string()]));
+        }
+        Ok(Response::json(200, self.usage.report()))
+    }
+
+    // `scope` is cancelled when the client hangs up before the response is ready
+    fn route(self: &Arc<Self>, request: &Request, received_at: u64, scope: &TaskScope) -> Result<Response, Response> {
+        #[cfg(feature = "web-ui")]
+        if request.method == "GET" {
+            if let Some(file) = crate::web::file(&request.segments()) {
+                return Ok(file);
+            }
+        }
+        match (request.method.as_str(), request.segments().as_slice()) {
+            ("POST", ["analysis"]) => self.submit_analysis(request),
+            ("GET", ["analysis", id]) => self.analysis_status(id),
+            ("GET", ["analysis", id, "details"]) => self.analysis_details(id),
+            ("DELETE", ["analysis", id]) => self.cancel_analysis(id),
+            ("POST", ["import"]) => self.import(request),
+            ("POST", ["game"]) => self.create_game(request, scope),
+            ("GET", ["game", id]) => self.game_status(id),
+            ("GET", ["game", id, "audit"]) => self.game_audit(id),
+            ("GET", ["game", id, "record"]) => self.game_record(id),
+            ("GET", ["game", id, "graph"]) => self.game_graph(id, scope),
+            ("POST", ["games", "graph"]) => self.submitted_graph(request, scope),
+            ("POST", ["game", id, "advice"]) => self.game_advice(id, request, scope),
+            ("POST", ["advice"]) => self.advice(request, scope),
+            ("POST", ["batch"]) => self.batch_moves(request, received_at, scope),
+            ("POST", ["positions", "validate"]) => self.validate_position(request),
+            ("POST", ["positions", "difficulty"]) => self.position_difficulty(request, scope),
+            ("POST", ["game", id, "move"]) => self.game_move(id, request, scope),
+            ("GET", ["admin", "usage"]) => self.usage_report(request),
+            ("GET", ["status"]) => Ok(self.status()),
+            ("GET", ["capabilities"]) => Ok(Response::json(200, serde_json::to_value(self.capabilities()).unwrap())),
+            ("GET", ["variants"]) => Ok(Response::json(200, variants_json(&self.rule_options))),
+            #[cfg(feature = "notes")]
+            ("GET", ["positions", hash, "notes"]) => self.position_notes(hash),
+            #[cfg(feature = "notes")]
+            ("PUT", ["positions", hash, "notes"]) => self.put_position_notes(hash, request),
+            #[cfg(feature = "notes")]
+            ("DELETE", ["positions", hash, "notes"]) => self.delete_position_notes(hash),
+            #[cfg(not(feature = "notes"))]
+            ("GET" | "PUT" | "DELETE", ["positions", _, "notes"]) => Err(Response::error(404, "notes unavailable", vec!["the server was built without the notes feature".to_string()])),
+            (method, ["analysis" | "batch" | "capabilities" | "game" | "games" | "import" | "positions", ..]) => Err(Response::error(405, "method not allowed", vec![format!("{method} {}", request.path)])),
+            // every other request asks for a move, whatever its path
+            _ => self.best_move(request, received_at, scope),
+        }
+    }
+
+    fn handle_connection(self: &Arc<Self>, mut stream: TcpStream) {
+        let received_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+        let reading = Instant::now();
+        let mut described = None;
+        // every pool task started for the request, closed once it has been answered
+        let scope = TaskScope::new();
+        // the watcher of a streamed answer, it keeps watching while the stream is written
+        let mut streaming = None;
+        let response = match http::read_request(&stream) {
+            Ok(request) => {
+                let read_in = reading.elapsed();
+                described = Some((format!("{} {}", request.method, request.path), read_in));
+                let done = Arc::new(AtomicBool::new(false));
+                let watcher = watch_disconnect(&stream, scope.cancelled(), &done);
+                // the last line of defense, a panic answers its request with a 500 and
+                // leaves the server running, the panic hook of `serve` logged it
+                let response = panic::catch_unwind(AssertUnwindSafe(|| self.route(&request, received_at, &scope)))
+                    .unwrap_or_else(|_| {
+                        let details = vec![format!("{} {} failed, see the server log", request.method, request.path)];
+                        Err(Response::error(500, "internal error", details))
+                    })
+                    .unwrap_or_else(|error| error);
+                if response.chunks.is_some() {
+                    streaming = Some((done, watcher));
+                    response
+                } else {
+                    done.store(true, Ordering::Relaxed);
+                    // a search cancelled by a newer request still answers
+                    let hung_up = watcher.is_some_and(|watcher| watcher.join().unwrap());
+                    if hung_up {
+                        eprintln!("Client hung up during {} {}, dropping the response", request.method, request.path);
+                        scope.close();
+                        return;
+                    }
+                    response
+                }
+            },
+            Err(e) => Response::error(http::error_status(&e), "invalid request", vec![e.to_string()]),
+        };
+
+        let writing = Instant::now();
+        if let Err(e) = http::write_response(&mut stream, &response) {
+            eprintln!("Failed to write response: {e}");
+        }
+        // a client hanging up mid-stream shows as the failed write above
+        if let Some((done, watcher)) = streaming {
+            done.store(true, Ordering::Relaxed);
+            if let Some(watcher) = watcher {
+                watcher.join().unwrap();
+            }
+        }
+        // a streamed answer is only over once written, whatever it still searches is not needed
+        scope.close();
+        // where the time of every answered move request went
+        if let (Some((request, read_in)), Some(latency)) = (described, response.latency) {
+            eprintln!(
+                "{request} {}: read {}us, parse {}us, queue {}us, search {}us, serialize {}us, write {}us",
+                response.status,
+                read_in.as_micros(),
+                latency.parse_us,
+                latency.queue_us,
+                latency.search_us,
+                latency.serialize_us,
+                writing.elapsed().as_micros()
+            );
+        }
+    }
+}
+
+pub fn serve(pool: Arc<Pool<MicaBestMove>>, args: &[String]) {
+    let jobs_file: String = option(args, "--jobs-file", "mica-jobs.json".to_string());
+    let sessions_file: String = option(args, "--sessions-file", "mica-sessions.json".to_string());
+    #[cfg(feature = "notes")]
+    let notes_db: String = option(args, "--notes-db", "mica-notes.sqlite".to_string());
+    // panics are caught per request, see `handle_connection`, and logged here with where they came from
+    panic::set_hook(Box::new(|info| eprintln!("{info}\n{}", Backtrace::force_capture())));
+    let server = Arc::new(Server {
+        pool,
+        caps: SafetyCaps::from_args(args),
+        jobs: Jobs::restore(PathBuf::from(jobs_file)),
+        sessions: Sessions::restore(PathBuf::from(sessions_file), option(args, "--max-game-plies", 600)),
+        usage: Usage::new(option(args, "--usage-clients", 1024)),
+        admin_token: Some(option(args, "--admin-token", String::new())).filter(|token| !token.is_empty()),
+        default_validation: option(args, "--validation", ValidationMode::Trust),
+        rule_options: allowed_rule_options(args),
+        default_contempt: option(args, "--contempt", 0),
+        job_pause: Some(Duration::from_micros(option(args, "--job-yield-us", 0))).filter(|pause| !pause.is_zero()),
+        memory_limit: option::<usize>(args, "--memory-limit-mb", 256) << 20,
+        tt: Arc::new(TranspositionTable::new(option(args, "--tt-mb", 64))),
+        tablebase: Some(option(args, "--tablebase", String::new())).filter(|path| !path.is_empty()).map(|path| Arc::new(Tablebase::load(Path::new(&path)).unwrap())),
+        weights: option(args, "--eval-weights", PhaseWeights::default()),
+        #[cfg(feature = "nn")]
+        network: Some(option(args, "--nn-weights", String::new())).filter(|path| !path.is_empty()).map(|path| {
+            let network = Network::load(Path::new(&path)).unwrap();
+            // the leaves of concurrent searches are evaluated together, one at a time by default
+            let max_wait = Duration::from_micros(option(args, "--nn-batch-wait-us", 100));
+            Arc::new(network.batched(option(args, "--nn-batch", 1), max_wait))
+        }),
+        book: Some(option(args, "--book", String::new())).filter(|path| !path.is_empty()).map(|path| Arc::new(OpeningBook::load(Path::new(&path)).unwrap())),
+        details_ttl: Duration::from_secs(option(args, "--details-ttl-secs", 600)),
+        cache: Mutex::new(LruCache::new(option(args, "--cache-size", 4096))),
+        search_mode: option(args, "--search-mode", SearchMode::Split),
+        saturation: option(args, "--saturation", SaturationPolicy::Queue),
+        saturation_threshold: Duration::from_millis(option(args, "--saturation-wait-ms", 250)),
+        deterministic: args.iter().any(|arg| arg == "--deterministic"),
+        #[cfg(feature = "notes")]
+        notes: Notes::open(Path::new(&notes_db)).unwrap(),
+        game_searches: Mutex::new(HashMap::new()),
+        in_flight: Mutex::new(HashMap::new()),
+    });
+    server.resume_jobs();
+
+    let session_idle = Duration::from_secs(option(args, "--session-idle-secs", 30 * 60));
+    let reaper = Arc::clone(&server);
+    thread::spawn(move || loop {
+        thread::sleep(SESSION_SWEEP_INTERVAL.min(session_idle));
+        for id in reaper.sessions.expire(session_idle) {
+            eprintln!("Session {id} expired after {}s without activity", session_idle.as_secs());
+        }
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
+
+    for stream in listener.incoming() {
+        let stream = stream.unwrap();
+        let server = Arc::clone(&server);
+        thread::spawn(move || server.handle_connection(stream));
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use mica::api::{Game, GameClock, GameOutcome, RuleOptions};
+use mica::clock::Clock;
+use mica::gym::encode_action;
+use mica::minimax::*;
+use mica::notation::{format_position, move_from_notation, move_to_notation, PositionFormat};
+use mica::personality::{self, Personality};
+use mica::rng::Rng;
+use mica::selfplay::{GameHeaders, GameRecord};
+use mica::variants::{self, Variant};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// How a finished game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub winner: MicaPlayer,
+    pub reason: &'static str,
+}
+
+// How the engine came to one of its moves in a game, to look into a
+// reported blunder after the fact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    // index of the move in `Session::moves`
+    pub ply: usize,
+    #[serde(rename = "move")]
+    pub mica_move: String,
+    // `search` or `personality`
+    pub source: String,
+    pub personality: Option<String>,
+    // what the clock allowed for the move and the limits the search ran under
+    pub budget_ms: u64,
+    pub max_depth: u8,
+    pub max_nodes: u64,
+    // what the search came to, null for moves a personality chose
+    pub depth: Option<u8>,
+    pub score: Option<i32>,
+    pub nodes: Option<u64>,
+    pub time_ms: u64,
+    pub engine_version: String,
+}
+
+const REASONS: [&str; 5] = ["time", "stones", "blocked", "length", "quiet"];
+
+// A game played against the engine, one side is played by the client.
+pub struct Session {
+    pub variant: &'static Variant,
+    // the options changed from the variant's rules, the state plays by them
+    pub rules: RuleOptions,
+    pub state: MicaState,
+    pub engine: MicaPlayer,
+    // plays the engine's moves instead of the timed search when set
+    pub personality: Option<&'static Personality>,
+    // the personality's random choices in this game derive from it
+    pub seed: u64,
+    pub clock: Clock,
+    // when the side to move started thinking
+    pub turn_started: Instant,
+    pub moves: Vec<MicaMove>,
+    pub outcome: Option<Outcome>,
+    // last time a client looked at or played in this game
+    pub last_active: Instant,
+    // a game still going after this many moves is adjudicated by evaluation
+    pub max_plies: usize,
+    // one entry per move the engine played, oldest first
+    pub audit: Vec<AuditEntry>,
+    // of the game's record, the termination is filled in from the outcome
+    pub headers: GameHeaders,
+    // the engine is searching its move, the session is unlocked meanwhile
+    pub thinking: bool,
+}
+
+impl Session {
+    pub fn new(variant: &'static Variant, rules: RuleOptions, engine: MicaPlayer, personality: Option<&'static Personality>, seed: u64, clock: Clock) -> Self {
+        let state = Session::start(variant, rules);
+        let engine_name = personality.map_or("mica".to_string(), |personality| format!("mica:{}", personality.name));
+        let (white, black) = match engine {
+            MicaPlayer::White => (engine_name.as_str(), "client"),
+            _ => ("client", engine_name.as_str()),
+        };
+        let mut headers = GameHeaders::new("session", white, black);
+        headers.variant = Some(variant.name.to_string());
+        headers.time_control = Some(format!("{}+{}", clock.white.as_secs_f64(), clock.increment.as_secs_f64()));
+        Session {
+            variant,
+            rules,
+            state,
+            engine,
+            personality,
+            seed,
+            clock,
+            turn_started: Instant::now(),
+            moves: Vec::new(),
+            outcome: None,
+            last_active: Instant::now(),
+            max_plies: usize::MAX,
+            audit: Vec::new(),
+            headers,
+            thinking: false,
+        }
+    }
+
+    // the position a game of the variant with the rule options starts from
+    pub fn start(variant: &Variant, rules: RuleOptions) -> MicaState {
+        let mut state = (variant.new_game)();
+        state.rules = rules.apply(variant.rules);
+        state.draw_after = rules.draw_after;
+        state
+    }
+
+    // plays a move for the side to move and charges its clock
+    pub fn play(&mut self, mica_move: MicaMove) -> Result<(), IllegalMove> {
+        let player = self.state.current_player;
+        let mut next = self.state.clone();
+        next.checked_apply_move(mica_move)?;
+        if !self.clock.charge(player, self.turn_started.elapsed()) {
+            self.outcome = Some(Outcome { winner: player.into_next_player(), reason: "time" });
+            return Ok(());
+        }
+
+        self.state = next;
+        self.state.current_player.toggle();
+        self.moves.push(mica_move);
+        self.turn_started = Instant::now();
+
+        // the side to move loses when it is down to two stones or cannot move
+        if self.state.is_drawn() {
+            self.outcome = Some(Outcome { winner: MicaPlayer::None, reason: "quiet" });
+        } else if self.state.is_end() {
+            self.outcome = Some(Outcome { winner: player, reason: "stones" });
+        } else if self.state.get_moves().is_empty() {
+            self.outcome = Some(Outcome { winner: player, reason: "blocked" });
+        } else if self.moves.len() >= self.max_plies {
+            // None when it is even, a draw
+            self.outcome = Some(Outcome { winner: self.state.adjudicate(), reason: "length" });
+        }
+        Ok(())
+    }
+
+    // random numbers for the personality's next move, seeded by the game seed
+    // and the ply so a restored game carries on exactly as it would have
+    pub fn move_rng(&self) -> Rng {
+        Rng::new(self.seed.wrapping_add(self.moves.len() as u64))
+    }
+
+    // the game so far as a game record, seeded by the game seed
+    pub fn record(&self) -> GameRecord {
+        let mut headers = self.headers.clone();
+        headers.termination = self.outcome.map(|outcome| outcome.reason.to_string());
+        GameRecord {
+            seed: self.seed,
+            moves: self.moves.iter().map(|&mica_move| encode_action(mica_move)).collect(),
+            result: self.outcome.map_or(0, |outcome| outcome.winner as i8),
+            headers,
+        }
+    }
+
+    pub fn to_json(&self, id: u64) -> Value {
+        serde_json::to_value(Game {
+            id,
+            variant: self.variant.name.to_string(),
+            engine: PlayerField::Name(self.engine),
+            personality: self.personality.map(|personality| personality.name.to_string()),
+            rules: self.rules,
+            to_move: PlayerField::Name(self.state.current_player),
+            position: format_position(PositionFormat::Fen, &self.state),
+            phase: self.variant.phase(&self.state).name().to_string(),
+            moves: self.moves.iter().map(|&mica_move| move_to_notation(mica_move)).collect(),
+            clock: GameClock {
+                white_ms: self.clock.white.as_millis() as u64,
+                black_ms: self.clock.black.as_millis() as u64,
+                increment_ms: self.clock.increment.as_millis() as u64,
+            },
+            outcome: self.outcome.map(|outcome| GameOutcome {
+                winner: PlayerField::Name(outcome.winner),
+                reason: outcome.reason.to_string(),
+            }),
+            engine_move: None,
+        }).unwrap()
+    }
+}
+
+// A session as written to the sessions file, the position is rebuilt by
+// replaying the moves.
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    id: u64,
+    variant: String,
+    engine: i8,
+    personality: Option<String>,
+    seed: u64,
+    moves: Vec<String>,
+    white_ms: u64,
+    black_ms: u64,
+    increment_ms: u64,
+    // winner and reason
+    outcome: Option<(i8, String)>,
+    #[serde(default)]
+    audit: Vec<AuditEntry>,
+    #[serde(default)]
+    rules: RuleOptions,
+    #[serde(default)]
+    headers: GameHeaders,
+}
+
+// nobody for 0, the winner of a drawn game
+fn player(value: i8) -> MicaPlayer {
+    match value {
+        1 => MicaPlayer::White,
+        0 => MicaPlayer::None,
+        _ => MicaPlayer::Black,
+    }
+}
+
+impl SavedSession {
+    fn new(id: u64, session: &Session) -> Self {
+        SavedSession {
+            id,
+            variant: session.variant.name.to_string(),
+            engine: session.engine as i8,
+            personality: session.personality.map(|personality| personality.name.to_string()),
+            seed: session.seed,
+            moves: session.moves.iter().map(|&mica_move| move_to_notation(mica_move)).collect(),
+            white_ms: session.clock.white.as_millis() as u64,
+            black_ms: session.clock.black.as_millis() as u64,
+            increment_ms: session.clock.increment.as_millis() as u64,
+            outcome: session.outcome.map(|outcome| (outcome.winner as i8, outcome.reason.to_string())),
+            audit: session.audit.clone(),
+            rules: session.rules,
+            headers: session.headers.clone(),
+        }
+    }
+
+    // None when the file names a variant or personality this build does not
+    // have or its moves no longer replay
+    fn restore(self) -> Option<(u64, Session)> {
+        let variant = variants::find(&self.variant)?;
+        let personality = match self.personality {
+            Some(name) => Some(personality::find(&name)?),
+            None => None,
+        };
+        let clock = Clock {
+            white: Duration::from_millis(self.white_ms),
+            black: Duration::from_millis(self.black_ms),
+            increment: Duration::from_millis(self.increment_ms),
+        };
+        let mut session = Session::new(variant, self.rules, player(self.engine), personality, self.seed, clock);
+        for notation in &self.moves {
+            let mica_move = move_from_notation(notation)?;
+            session.state.checked_apply_move(mica_move).ok()?;
+            session.state.current_player.toggle();
+            session.moves.push(mica_move);
+        }
+        session.outcome = match self.outcome {
+            Some((winner, reason)) => Some(Outcome {
+                winner: player(winner),
+                reason: REASONS.into_iter().find(|&known| known == reason)?,
+            }),
+            None => None,
+        };
+        session.audit = self.audit;
+        // the clock of a restored game is what was left of it, its time control is in the headers
+        if self.headers != GameHeaders::default() {
+            session.headers = self.headers;
+        }
+        Some((self.id, session))
+    }
+}
+
+// Games in progress, keyed by the id handed out when they were created.
+// Every session has its own lock so the engine can think in one game while
+// others are being played. With a path, the games are mirrored to it so a
+// restart can pick them up again.
+pub struct Sessions {
+    next_id: AtomicU64,
+    sessions: Mutex<HashMap<u64, Arc<Mutex<Session>>>>,
+    path: Option<PathBuf>,
+    // move ceiling of every game, see `Session::max_plies`
+    max_plies: usize,
+}
+
+impl Sessions {
+    pub fn new(path: Option<PathBuf>, max_plies: usize) -> Self {
+        Sessions {
+            next_id: AtomicU64::new(1),
+            sessions: Mutex::new(HashMap::new()),
+            path,
+            max_plies,
+        }
+    }
+
+    // loads the games saved by the previous run, their clocks restart on the next move
+    pub fn restore(path: PathBuf, max_plies: usize) -> Self {
+        let saved: Vec<SavedSession> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        let sessions = Sessions::new(Some(path), max_plies);
+        let next_id = saved.iter().map(|session| session.id + 1).max().unwrap_or(1);
+        sessions.next_id.store(next_id, Ordering::Relaxed);
+        {
+            let mut map = sessions.sessions.lock().unwrap();
+            for saved in saved {
+                let id = saved.id;
+                match saved.restore() {
+                    Some((id, mut session)) => {
+                        session.max_plies = max_plies;
+                        map.insert(id, Arc::new(Mutex::new(session)));
+                    },
+                    None => eprintln!("Dropping saved game {id}, it no longer replays"),
+                }
+            }
+        }
+        sessions
+    }
+
+    // writes every game to the sessions file, callers must not hold a session lock
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut saved: Vec<SavedSession> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, session)| SavedSession::new(id, &session.lock().unwrap()))
+            .collect();
+        saved.sort_by_key(|session| session.id);
+
+        // write to a temporary file first so a crash never leaves a torn file behind
+        let tmp = path.with_extension("tmp");
+        let written = fs::write(&tmp, serde_json::to_string(&saved).unwrap()).and_then(|_| fs::rename(&tmp, path));
+        if let Err(e) = written {
+            eprintln!("Failed to save games to {}: {e}", path.display());
+        }
+    }
+
+    pub fn create(&self, mut session: Session) -> u64 {
+        session.max_plies = self.max_plies;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.sessions.lock().unwrap().insert(id, Arc::new(Mutex::new(session)));
+        id
+    }
+
+    // the session, marked as active
+    pub fn get(&self, id: u64) -> Option<Arc<Mutex<Session>>> {
+        let session = self.sessions.lock().unwrap().get(&id).cloned()?;
+        session.lock().unwrap().last_active = Instant::now();
+        Some(session)
+    }
+
+    // drops the sessions nobody has touched for `idle`, returning their ids
+    pub fn expire(&self, idle: Duration) -> Vec<u64> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut expired: Vec<u64> = sessions
+            .iter()
+            .filter(|(_, session)| session.lock().unwrap().last_active.elapsed() >= idle)
+            .map(|(&id, _)| id)
+            .collect();
+        expired.sort();
+        for id in &expired {
+            sessions.remove(id);
+        }
+        drop(sessions);
+        if !expired.is_empty() {
+            self.save();
+        }
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,95 @@
+use crate::coords::POINTS;
+use crate::minimax::*;
+use crate::packed;
+
+// The board has 16 symmetries: the 8 symmetries of a square applied to every
+// ring at once, each optionally combined with swapping the inner and outer
+// rings. All of them map lines to lines and neighbours to neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    pub swap_rings: bool,
+    pub transpose: bool,
+    pub flip_y: bool,
+    pub flip_z: bool,
+}
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry { swap_rings: false, transpose: false, flip_y: false, flip_z: false };
+
+    pub const fn all() -> [Symmetry; 16] {
+        let mut symmetries = [Symmetry::IDENTITY; 16];
+        let mut i = 0;
+        while i < 16 {
+            symmetries[i] = Symmetry {
+                swap_rings: i & 8 != 0,
+                transpose: i & 4 != 0,
+                flip_y: i & 2 != 0,
+                flip_z: i & 1 != 0,
+            };
+            i += 1;
+        }
+        symmetries
+    }
+
+    pub const fn apply_point(&self, (x, y, z): (u8, u8, u8)) -> (u8, u8, u8) {
+        let x = if self.swap_rings { 2 - x } else { x };
+        let (y, z) = if self.transpose { (z, y) } else { (y, z) };
+        let y = if self.flip_y { 2 - y } else { y };
+        let z = if self.flip_z { 2 - z } else { z };
+        (x, y, z)
+    }
+
+    // Every part but the transpose undoes itself. Flipping back before
+    // transposing back is transposing first and flipping the other axis.
+    pub fn inverse(&self) -> Symmetry {
+        match self.transpose {
+            false => *self,
+            true => Symmetry { flip_y: self.flip_z, flip_z: self.flip_y, ..*self },
+        }
+    }
+
+    pub fn apply_move(&self, mica_move: MicaMove) -> MicaMove {
+        match mica_move {
+            MicaMove::Set { x, y, z } => {
+                let (x, y, z) = self.apply_point((x, y, z));
+                MicaMove::Set { x, y, z }
+            },
+            MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z } => {
+                let (from_x, from_y, from_z) = self.apply_point((from_x, from_y, from_z));
+                let (to_x, to_y, to_z) = self.apply_point((to_x, to_y, to_z));
+                MicaMove::Move { from_x, from_y, from_z, to_x, to_y, to_z }
+            },
+            MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z } => {
+                let (x, y, z) = self.apply_point((x, y, z));
+                let (remove_x, remove_y, remove_z) = self.apply_point((remove_x, remove_y, remove_z));
+                MicaMove::SetRemove { x, y, z, remove_x, remove_y, remove_z }
+            },
+            MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z } => {
+                let (from_x, from_y, from_z) = self.apply_point((from_x, from_y, from_z));
+                let (to_x, to_y, to_z) = self.apply_point((to_x, to_y, to_z));
+                let (remove_x, remove_y, remove_z) = self.apply_point((remove_x, remove_y, remove_z));
+                MicaMove::MoveRemove { from_x, from_y, from_z, to_x, to_y, to_z, remove_x, remove_y, remove_z }
+            },
+        }
+    }
+
+    pub fn apply_state(&self, state: &MicaState) -> MicaState {
+        let mut transformed = state.clone();
+        for &point in POINTS.iter() {
+            let (x, y, z) = self.apply_point(point);
+            transformed.stones[x as usize][y as usize][z as usize] = state.stone(point.0, point.1, point.2);
+        }
+        transformed.rehash();
+        transformed
+    }
+}
+
+// The smallest packed key, see `packed`, among all symmetric images of the
+// position, together with the symmetry that maps the position onto that image.
+pub fn canonical_key(state: &MicaState) -> (u64, Symmetry) {
+    Symmetry::all()
+        .into_iter()
+        .map(|symmetry| (packed::encode(&symmetry.apply_state(state)), symmetry))
+        .min_by_key(|&(key, _)| key)
+        .unwrap()
+}
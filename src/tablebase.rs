@@ -0,0 +1,364 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::coords::{bits, MILLS, NEIGHBOURS, POINTS};
+use crate::minimax::{MicaPlayer, MicaState, WIN};
+use crate::variants;
+
+// Exact results of moving phase endgames with few stones, found by
+// retrograde analysis. A table holds every position with a given number of
+// stones for the side to move and for the other side, one byte per
+// position: 0 for a draw, otherwise one more than the plies left in the game
+// with best play. The side to move wins when those plies are odd, it makes
+// the last move, and loses when they are even.
+//
+// Points are numbered like `coords::POINTS`, a set of stones is a bit mask
+// of them.
+
+const MAGIC: &[u8; 8] = b"MICATB1\n";
+const ALL_POINTS: u32 = (1 << 24) - 1;
+// a side down to this many stones has lost
+const LOST_AT: u8 = 2;
+
+// result of a position for the side to move, with the plies to the end of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableResult {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl TableResult {
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => TableResult::Draw,
+            _ if (byte - 1) % 2 == 1 => TableResult::Win(byte - 1),
+            _ => TableResult::Loss(byte - 1),
+        }
+    }
+
+    // For the search, from the side to move in a position `ply` plies below
+    // the search's root: the won game at the end of the table's line, scored
+    // by its distance from the root like every other one, see `WIN`.
+    pub fn score(self, ply: u8) -> i32 {
+        match self {
+            TableResult::Win(plies) => WIN - ply as i32 - plies as i32,
+            TableResult::Loss(plies) => -(WIN - ply as i32 - plies as i32),
+            TableResult::Draw => 0,
+        }
+    }
+}
+
+// `BINOMIALS[n][k]` is n choose k
+const fn binomials() -> [[u32; 25]; 25] {
+    let mut binomials = [[0; 25]; 25];
+    let mut n = 0;
+    while n < 25 {
+        binomials[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            binomials[n][k] = binomials[n - 1][k - 1] + binomials[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    binomials
+}
+
+const BINOMIALS: [[u32; 25]; 25] = binomials();
+
+fn closes_mill(stones: u32, point: usize) -> bool {
+    MILLS.iter().any(|&mill| mill & 1 << point != 0 && stones & mill == mill)
+}
+
+// Stones a mill may take. Stones in a mill can only be removed when there is
+// nothing else to remove.
+fn removable(stones: u32) -> u32 {
+    let in_mills = MILLS.iter().filter(|&&mill| stones & mill == mill).fold(0, |in_mills, mill| in_mills | mill);
+    match stones & !in_mills {
+        0 => stones,
+        free => free,
+    }
+}
+
+// Every position a move leads to, from the side to move then, and whether
+// the move took a stone.
+fn successors(mine: u32, theirs: u32, mut each: impl FnMut(u32, u32, bool)) {
+    let empty = ALL_POINTS & !(mine | theirs);
+    for from in bits(mine) {
+        for to in bits(NEIGHBOURS[from] & empty) {
+            let moved = mine & !(1 << from) | 1 << to;
+            if closes_mill(moved, to) {
+                for taken in bits(removable(theirs)) {
+                    each(theirs & !(1 << taken), moved, true);
+                }
+            } else {
+                each(theirs, moved, false);
+            }
+        }
+    }
+}
+
+// every position a move that took no stone leads here from, from the side to move then
+fn predecessors(mine: u32, theirs: u32, mut each: impl FnMut(u32, u32)) {
+    let empty = ALL_POINTS & !(mine | theirs);
+    for to in bits(theirs) {
+        if closes_mill(theirs, to) {
+            continue;
+        }
+        for from in bits(NEIGHBOURS[to] & empty) {
+            each(theirs & !(1 << to) | 1 << from, mine);
+        }
+    }
+}
+
+// Rank of `set` among the sets of as many points, counting only the points
+// outside `skip`.
+fn rank(set: u32, skip: u32) -> u32 {
+    bits(set)
+        .enumerate()
+        .map(|(i, point)| BINOMIALS[point - (skip & ((1 << point) - 1)).count_ones() as usize][i + 1])
+        .sum()
+}
+
+fn unrank(mut rank: u32, count: usize, skip: u32) -> u32 {
+    let mut set = 0;
+    for k in (1..=count).rev() {
+        let mut n = k - 1;
+        while BINOMIALS[n + 1][k] <= rank {
+            n += 1;
+        }
+        rank -= BINOMIALS[n][k];
+        // the nth point outside `skip`
+        set |= 1 << bits(ALL_POINTS & !skip).nth(n).unwrap();
+    }
+    set
+}
+
+// positions with `mine` stones for the side to move and `theirs` for the other side
+fn table_len(mine: u8, theirs: u8) -> usize {
+    BINOMIALS[24][mine as usize] as usize * BINOMIALS[24 - mine as usize][theirs as usize] as usize
+}
+
+fn index(mine: u32, theirs: u32) -> usize {
+    let per_mine = BINOMIALS[24 - mine.count_ones() as usize][theirs.count_ones() as usize] as usize;
+    rank(mine, 0) as usize * per_mine + rank(theirs, mine) as usize
+}
+
+fn position(index: usize, mine: u8, theirs: u8) -> (u32, u32) {
+    let per_mine = BINOMIALS[24 - mine as usize][theirs as usize] as usize;
+    let mine_set = unrank((index / per_mine) as u32, mine as usize, 0);
+    (mine_set, unrank((index % per_mine) as u32, theirs as usize, mine_set))
+}
+
+#[derive(Default)]
+pub struct Tablebase {
+    // by stones of the side to move and of the other side
+    tables: BTreeMap<(u8, u8), Vec<u8>>,
+}
+
+// the tables are megabytes, states holding a tablebase print the stone counts it covers
+impl fmt::Debug for Tablebase {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_list().entries(self.tables.keys().map(|(mine, theirs)| format!("{mine}v{theirs}"))).finish()
+    }
+}
+
+impl Tablebase {
+    // Solves every endgame with at most `max_stones` on the board and three
+    // or more a side. Smaller endgames are solved first, a move that takes a
+    // stone leads into one of them.
+    pub fn generate(max_stones: u8, mut progress: impl FnMut(u8, u8)) -> Self {
+        let mut tablebase = Tablebase::default();
+        for total in 2 * (LOST_AT + 1)..=max_stones {
+            for more in (total - total / 2)..=total - (LOST_AT + 1) {
+                let fewer = total - more;
+                tablebase.solve(more, fewer);
+                progress(more, fewer);
+            }
+        }
+        tablebase
+    }
+
+    // result of a position taken from the side to move, through a table or as a finished game
+    fn lookup(&self, mine: u32, theirs: u32) -> u8 {
+        if mine.count_ones() as u8 <= LOST_AT {
+            return 1;
+        }
+        self.tables[&(mine.count_ones() as u8, theirs.count_ones() as u8)][index(mine, theirs)]
+    }
+
+    // Solves the positions with `more` stones for one side and `fewer` for
+    // the other, both sides to move at once since every move that takes no
+    // stone leads from one to the other. Positions are resolved in the order
+    // of their plies to the end: a position with a lost successor is won one
+    // ply later, one whose successors are all won is lost one ply after the
+    // longest of them, and whatever is left at the end is a draw.
+    fn solve(&mut self, more: u8, fewer: u8) {
+        let classes = if more == fewer { vec![(more, fewer)] } else { vec![(more, fewer), (fewer, more)] };
+        // the other class, the one a move that takes no stone leads to
+        let next = |class: usize| (class + 1) % classes.len();
+        let mut values: Vec<Vec<u8>> = classes.iter().map(|&(mine, theirs)| vec![0; table_len(mine, theirs)]).collect();
+        // successors not yet known to be won, and the longest won one among those taking a stone
+        let mut open: Vec<Vec<u16>> = values.iter().map(|table| vec![0; table.len()]).collect();
+        let mut longest: Vec<Vec<u8>> = values.clone();
+        let mut done: Vec<Vec<bool>> = values.iter().map(|table| vec![false; table.len()]).collect();
+        // positions by the plies to the end they were found at, tagged with their class in the top bit
+        let mut found: Vec<Vec<u32>> = Vec::new();
+        let push = |found: &mut Vec<Vec<u32>>, plies: u8, class: usize, index: usize| {
+            if found.len() <= plies as usize {
+                found.resize(plies as usize + 1, Vec::new());
+            }
+            found[plies as usize].push((class as u32) << 31 | index as u32);
+        };
+
+        for (class, &(mine_count, theirs_count)) in classes.iter().enumerate() {
+            for index in 0..values[class].len() {
+                let (mine, theirs) = position(index, mine_count, theirs_count);
+                let (mut moves, mut won, mut quickest_win) = (0u16, 0u16, None);
+                successors(mine, theirs, |after_mine, after_theirs, took| {
+                    moves += 1;
+                    if !took {
+                        return;
+                    }
+                    match TableResult::from_byte(self.lookup(after_mine, after_theirs)) {
+                        TableResult::Loss(plies) => quickest_win = Some(quickest_win.unwrap_or(u8::MAX).min(plies + 1)),
+                        TableResult::Win(plies) => {
+                            won += 1;
+                            longest[class][index] = longest[class][index].max(plies);
+                        },
+                        TableResult::Draw => (),
+                    }
+                });
+                open[class][index] = moves - won;
+                if moves == 0 {
+                    push(&mut found, 0, class, index);
+                } else if let Some(plies) = quickest_win {
+                    push(&mut found, plies, class, index);
+                } else if moves == won {
+                    push(&mut found, longest[class][index] + 1, class, index);
+                }
+            }
+        }
+
+        let mut plies = 0;
+        while plies < found.len() {
+            let positions = core::mem::take(&mut found[plies]);
+            let byte = u8::try_from(plies + 1).expect("an endgame longer than a table byte holds");
+            for tagged in positions {
+                let (class, at) = ((tagged >> 31) as usize, (tagged & !(1 << 31)) as usize);
+                if done[class][at] || (values[class][at] != 0 && values[class][at] != byte) {
+                    continue;
+                }
+                values[class][at] = byte;
+                done[class][at] = true;
+
+                let (mine_count, theirs_count) = classes[class];
+                let (mine, theirs) = position(at, mine_count, theirs_count);
+                let before = next(class);
+                predecessors(mine, theirs, |before_mine, before_theirs| {
+                    let before_index = index(before_mine, before_theirs);
+                    if done[before][before_index] || values[before][before_index] != 0 {
+                        return;
+                    }
+                    if plies % 2 == 0 {
+                        // a lost position makes every way into it a win
+                        values[before][before_index] = byte + 1;
+                        push(&mut found, plies as u8 + 1, before, before_index);
+                    } else {
+                        open[before][before_index] -= 1;
+                        if open[before][before_index] == 0 {
+                            push(&mut found, (plies as u8).max(longest[before][before_index]) + 1, before, before_index);
+                        }
+                    }
+                });
+            }
+            plies += 1;
+        }
+
+        for (class, table) in classes.into_iter().zip(values) {
+            self.tables.insert(class, table);
+        }
+    }
+
+    // The result for the side to move, None in the setting phase, for stone
+    // counts no table covers and for rules other than the ones the tables
+    // were solved for.
+    pub fn probe(&self, state: &MicaState) -> Option<TableResult> {
+        if state.stones_to_set() != (0, 0) || state.rules != variants::NINE.rules {
+            return None;
+        }
+        let (white, black) = state.occupancy();
+        let (mine, theirs) = if state.current_player == MicaPlayer::White { (white, black) } else { (black, white) };
+        let table = self.tables.get(&(mine.count_ones() as u8, theirs.count_ones() as u8))?;
+        Some(TableResult::from_byte(table[index(mine, theirs)]))
+    }
+
+    // every table by the stones of the side to move and of the other side,
+    // one result byte per position, see `TableResult::from_byte`
+    pub fn tables(&self) -> impl Iterator<Item = ((u8, u8), &[u8])> + '_ {
+        self.tables.iter().map(|(&counts, table)| (counts, table.as_slice()))
+    }
+
+    // A position of a table as a state with white to move, to check the
+    // tables against the engine's own rules.
+    pub fn state_at(&self, (mine_count, theirs_count): (u8, u8), index: usize) -> MicaState {
+        let (mine, theirs) = position(index, mine_count, theirs_count);
+        let mut state = MicaState::new();
+        state.set_stones_to_set(0, 0);
+        for (point, &(x, y, z)) in POINTS.iter().enumerate() {
+            if mine & 1 << point != 0 {
+                state.put_stone(x, y, z, MicaPlayer::White);
+            } else if theirs & 1 << point != 0 {
+                state.put_stone(x, y, z, MicaPlayer::Black);
+            }
+        }
+        state
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for (&(mine, theirs), table) in &self.tables {
+            bytes.extend([mine, theirs]);
+            bytes.extend((table.len() as u32).to_le_bytes());
+            bytes.extend(table);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, String> {
+        bytes = bytes.strip_prefix(MAGIC).ok_or("not a mica tablebase")?;
+        let mut tablebase = Tablebase::default();
+        while !bytes.is_empty() {
+            let (header, rest) = bytes.split_at_checked(6).ok_or("truncated table header")?;
+            let (mine, theirs) = (header[0], header[1]);
+            let len = u32::from_le_bytes(header[2..6].try_into().unwrap()) as usize;
+            if mine <= LOST_AT || theirs <= LOST_AT || mine as usize + theirs as usize > 24 || len != table_len(mine, theirs) {
+                return Err(format!("table {mine}v{theirs} has {len} positions"));
+            }
+            let (table, rest) = rest.split_at_checked(len).ok_or(format!("table {mine}v{theirs} is truncated"))?;
+            tablebase.tables.insert((mine, theirs), table.to_vec());
+            bytes = rest;
+        }
+        Ok(tablebase)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Tablebase::from_bytes(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
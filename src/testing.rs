@@ -0,0 +1,87 @@
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::coords::POINTS;
+use crate::fixtures;
+use crate::minimax::*;
+use crate::variants::STONES_PER_PLAYER;
+
+// Property-testing support, enabled with the `testing` feature.
+//
+// `arbitrary_state` produces positions reachable from the start by legal
+// moves, `arbitrary_legal_move` one of the moves of such a position, and the
+// `check_*` functions are the invariants every reachable position keeps.
+
+// longest random game, long enough to reach the moving phase
+const MAX_PLIES: usize = 60;
+
+// position after up to `plies` random legal moves from `seed`, see `fixtures::random`
+pub fn random_state(seed: u64, plies: usize) -> MicaState {
+    fixtures::random(seed, plies)
+}
+
+// shrinks towards fewer plies, so failures are reported close to the start
+pub fn arbitrary_state() -> impl Strategy<Value = MicaState> {
+    (any::<u64>(), 0..=MAX_PLIES).prop_map(|(seed, plies)| random_state(seed, plies))
+}
+
+// panics on positions without legal moves, which `arbitrary_state` never yields
+pub fn arbitrary_legal_move(state: &MicaState) -> impl Strategy<Value = MicaMove> {
+    select(state.get_moves())
+}
+
+pub fn arbitrary_state_and_move() -> impl Strategy<Value = (MicaState, MicaMove)> {
+    arbitrary_state().prop_flat_map(|state| {
+        let mica_move = arbitrary_legal_move(&state);
+        (Just(state), mica_move)
+    })
+}
+
+// the stone counters agree with the board and nobody has more than nine stones
+pub fn check_invariants(state: &MicaState) -> Result<(), String> {
+    let mut errors = Vec::new();
+    if state.current_player == MicaPlayer::None {
+        errors.push("nobody is to move".to_string());
+    }
+
+    let (white_to_set, black_to_set) = state.stones_to_set();
+    for (player, name, count, to_set) in [
+        (MicaPlayer::White, "white", state.white_remaining, white_to_set),
+        (MicaPlayer::Black, "black", state.black_remaining, black_to_set),
+    ] {
+        let on_board = POINTS.iter().filter(|&&(x, y, z)| state.stone(x, y, z) == player).count() as u8;
+        if on_board != count {
+            errors.push(format!("{name} has {on_board} stones on the board but counts {count}"));
+        }
+        if on_board + to_set > STONES_PER_PLAYER {
+            errors.push(format!("{name} has {on_board} stones on the board and {to_set} to set"));
+        }
+    }
+    if state.stone(0, 1, 1) != MicaPlayer::None || state.stone(1, 1, 1) != MicaPlayer::None || state.stone(2, 1, 1) != MicaPlayer::None {
+        errors.push("a stone sits in the middle of a ring".to_string());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+// applying and undoing a legal move gives back the same position
+pub fn check_apply_undo(state: &MicaState, mica_move: MicaMove) -> Result<(), String> {
+    let mut after = state.clone();
+    after.apply_move(mica_move);
+    check_invariants(&after).map_err(|e| format!("after {mica_move:?}: {e}"))?;
+    after.undo_move(mica_move);
+
+    let same = after.current_player == state.current_player
+        && after.stones_to_set() == state.stones_to_set()
+        && (after.white_remaining, after.black_remaining) == (state.white_remaining, state.black_remaining)
+        && after.stones == state.stones;
+    if same {
+        Ok(())
+    } else {
+        Err(format!("undoing {mica_move:?} did not restore the position"))
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+#[cfg(feature = "nn")]
+use mica::nn::Network;
+use mica::pool::Pool;
+use mica::selfplay::{self, SelfPlayConfig};
+
+use crate::option;
+
+// Self-play training loop: every round plays a batch of games, appends them to
+// `games.jsonl` and writes the round's training samples to `samples-<round>.jsonl`.
+//
+//     mica train [--out DIR] [--rounds N] [--games N] [--depth N] [--max-plies N] [--seed N] [--threads N]
+//
+// Games are spread over --threads workers, all cores by default. Game `i` of
+// round `r` is seeded with `seed + r * games + i` and written in that order,
+// so the output only depends on the seed. Games still going after
+// --max-plies plies are adjudicated by evaluation.
+//
+// With the `nn` feature every round starts by reloading the network in
+// `model.json` and plays its games with it, the weights evaluation until one
+// is there. A trainer fitting the samples writes the next network to
+// `candidate.json`, the round after promotes it to `model.json`.
+pub fn run(args: &[String]) {
+    let out_dir: String = option(args, "--out", "training".to_string());
+    let out_dir = Path::new(&out_dir);
+    let rounds: usize = option(args, "--rounds", 1);
+    let games: usize = option(args, "--games", 10);
+    let seed: u64 = option(args, "--seed", 0);
+    let config = SelfPlayConfig {
+        depth: option(args, "--depth", SelfPlayConfig::default().depth),
+        max_plies: option(args, "--max-plies", SelfPlayConfig::default().max_plies),
+        ..SelfPlayConfig::default()
+    };
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let pool = Arc::new(Pool::new());
+    Arc::clone(&pool).init(option(args, "--threads", cores));
+
+    fs::create_dir_all(out_dir).unwrap();
+    let games_file = OpenOptions::new().create(true).append(true).open(out_dir.join("games.jsonl")).unwrap();
+    let mut games_out = BufWriter::new(games_file);
+
+    for round in 0..rounds {
+        #[cfg(feature = "nn")]
+        let config = SelfPlayConfig { network: reload_model(out_dir), ..config.clone() };
+        let samples_file = File::create(out_dir.join(format!("samples-{round}.jsonl"))).unwrap();
+        let mut samples_out = BufWriter::new(samples_file);
+        let mut sample_count = 0;
+
+        let first_seed = seed + (round * games) as u64;
+        for record in selfplay::play_games(&pool, config.clone(), first_seed..first_seed + games as u64) {
+            for sample in selfplay::samples(&record) {
+                writeln!(samples_out, "{}", serde_json::to_string(&sample).unwrap()).unwrap();
+                sample_count += 1;
+            }
+            writeln!(games_out, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+        }
+
+        samples_out.flush().unwrap();
+        games_out.flush().unwrap();
+        eprintln!("Round {round}: {games} games, {sample_count} samples");
+    }
+}
+
+// promotes a waiting candidate and loads the model, None while there is none
+#[cfg(feature = "nn")]
+fn reload_model(out_dir: &Path) -> Option<Arc<Network>> {
+    let (candidate, model) = (out_dir.join("candidate.json"), out_dir.join("model.json"));
+    if candidate.exists() {
+        fs::rename(&candidate, &model).unwrap();
+        eprintln!("Promoted {} to {}", candidate.display(), model.display());
+    }
+    if !model.exists() {
+        return None;
+    }
+    Some(Arc::new(Network::load(&model).unwrap()))
+}
@@ -0,0 +1,107 @@
+use core::fmt;
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::gym::{decode_action, encode_action};
+use crate::minimax::MicaMove;
+
+// What a stored value says about the real one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the real value is at least this, the search failed high
+    Lower,
+    // the real value is at most this, the search failed low
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub depth: u8,
+    pub bound: Bound,
+    pub value: i32,
+    pub best_move: Option<MicaMove>,
+}
+
+impl Entry {
+    // value in bits 0-31, depth in 32-39, bound in 40-41 and the gym action
+    // index of the best move plus one in 42-56, zero when there is none
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let best_move = self.best_move.map_or(0, |best_move| encode_action(best_move) as u64 + 1);
+        self.value as u32 as u64 | (self.depth as u64) << 32 | bound << 40 | best_move << 42
+    }
+
+    fn unpack(data: u64) -> Self {
+        let bound = match (data >> 40) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let best_move = match (data >> 42) as usize {
+            0 => None,
+            action => decode_action(action - 1),
+        };
+        Entry {
+            depth: (data >> 32) as u8,
+            bound,
+            value: data as u32 as i32,
+            best_move,
+        }
+    }
+}
+
+// Transposition table shared by all search threads.
+//
+// Every slot holds the position key xored with the packed entry next to the
+// packed entry itself, so a slot torn by two threads writing at once no
+// longer matches its key and reads as empty instead of as a wrong entry.
+pub struct TranspositionTable {
+    slots: Box<[(AtomicU64, AtomicU64)]>,
+    mask: u64,
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TranspositionTable({} slots)", self.slots.len())
+    }
+}
+
+impl TranspositionTable {
+    // table of at most `megabytes`, rounded down to a power of two slots
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = (megabytes << 20) / 16;
+        let slots = if wanted < 2 { 1 } else { 1 << wanted.ilog2() };
+        TranspositionTable {
+            slots: (0..slots).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect(),
+            mask: slots as u64 - 1,
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let (check, data) = &self.slots[(key & self.mask) as usize];
+        let data = data.load(Ordering::Relaxed);
+        if data == 0 || check.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        Some(Entry::unpack(data))
+    }
+
+    pub fn store(&self, key: u64, entry: Entry) {
+        let (check, data) = &self.slots[(key & self.mask) as usize];
+        let packed = entry.pack();
+        check.store(key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for (check, data) in self.slots.iter() {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::process;
+use mica::eval::{evaluate_hundredths, EvalWeights, PhaseWeights};
+use mica::gym::{decode_action, Env};
+use mica::minimax::MicaState;
+use mica::selfplay::GameRecord;
+use mica::tuned;
+
+use crate::option;
+
+// Tunes the evaluation weights on the positions of self-play games, the way
+// Texel tuning does it.
+//
+//     mica tune [--games FILE] [--out FILE] [--weights W] [--skip-plies N] [--passes N]
+//
+// Every position of the games in --games, as `mica train` writes them, is
+// labeled with its game's result for white: 1, 1/2 or 0. A position's
+// evaluation in hundredths of a stone is turned into an expected result by a
+// logistic curve, `1 / (1 + 10^(-k * eval / 400))`, and the tuner looks for
+// the weights with the smallest mean squared difference between expected
+// and actual results. `k` is fitted to the starting weights first, then
+// every weight in turn is moved up and down by a step as long as that
+// lowers the error. Once no weight moves the step is halved, down to one.
+//
+// The first --skip-plies plies of every game are left out, self-play plays
+// them at random. The weights start from --weights, the table the engine
+// plays with by default. The result is written to --out as a new version of
+// that table, see `tuned`, and takes over once the engine is built again.
+pub fn run(args: &[String]) {
+    let games_path: String = option(args, "--games", "training/games.jsonl".to_string());
+    let out: String = option(args, "--out", "src/tuned.rs".to_string());
+    let skip_plies: usize = option(args, "--skip-plies", 4);
+    let passes: usize = option(args, "--passes", 50);
+    let mut weights: PhaseWeights = option(args, "--weights", PhaseWeights::default());
+
+    let text = fs::read_to_string(&games_path).unwrap_or_else(|e| fail(&format!("{games_path}: {e}")));
+    let mut positions = Vec::new();
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let record: GameRecord = serde_json::from_str(line).unwrap_or_else(|e| fail(&format!("{games_path}:{}: {e}", i + 1)));
+        positions.extend(labeled_positions(&record, skip_plies));
+    }
+    if positions.is_empty() {
+        fail(&format!("{games_path}: no positions to tune on"));
+    }
+
+    let k = fit_k(&positions, &weights);
+    let mut best = error(&positions, &weights, k);
+    eprintln!("{} positions, k = {k:.3}, error {best:.6}", positions.len());
+
+    let mut step = 16;
+    for pass in 1..=passes {
+        let mut moved = false;
+        for i in 0..PhaseWeights::names().len() {
+            for change in [step, -step] {
+                let mut candidate = weights;
+                *candidate.values_mut()[i] += change;
+                let candidate_error = error(&positions, &candidate, k);
+                if candidate_error < best {
+                    (weights, best, moved) = (candidate, candidate_error, true);
+                    break;
+                }
+            }
+        }
+        eprintln!("Pass {pass}: step {step}, error {best:.6}");
+        if !moved {
+            if step == 1 {
+                break;
+            }
+            step /= 2;
+        }
+    }
+
+    let version = tuned::VERSION + 1;
+    if let Err(e) = fs::write(&out, table_source(version, &weights)) {
+        fail(&format!("{out}: {e}"));
+    }
+    eprintln!("Wrote version {version} to {out}: {weights}");
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+// the positions of a game after the first `skip_plies`, each with the game's result for white from 0 to 1
+fn labeled_positions(record: &GameRecord, skip_plies: usize) -> Vec<(MicaState, f64)> {
+    let label = (record.result as f64 + 1.0) / 2.0;
+    let mut env = Env::new();
+    let mut positions = Vec::new();
+    for (ply, &action) in record.moves.iter().enumerate() {
+        if ply >= skip_plies {
+            positions.push((env.state().clone(), label));
+        }
+        if decode_action(action).is_none() || env.step(action).is_err() {
+            break;
+        }
+    }
+    positions
+}
+
+// the result for white a position evaluated as `eval` hundredths is expected to end in
+fn expected(eval: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+fn error(positions: &[(MicaState, f64)], weights: &PhaseWeights, k: f64) -> f64 {
+    let total: f64 = positions
+        .iter()
+        .map(|(state, label)| (label - expected(evaluate_hundredths(state, weights), k)).powi(2))
+        .sum();
+    total / positions.len() as f64
+}
+
+// the `k` with the smallest error for the weights, narrowed down a digit at a time
+fn fit_k(positions: &[(MicaState, f64)], weights: &PhaseWeights) -> f64 {
+    let mut k = 1.0;
+    let mut step = 1.0;
+    for _ in 0..4 {
+        let candidates = (-9..=9).map(|i| k + i as f64 * step).filter(|&candidate| candidate > 0.0);
+        k = candidates
+            .map(|candidate| (candidate, error(positions, weights, candidate)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(k, |(candidate, _)| candidate);
+        step /= 10.0;
+    }
+    k
+}
+
+// `tuned.rs` with the weights as version `version`
+fn table_source(version: u32, weights: &PhaseWeights) -> String {
+    let phase = |name: &str, weights: &EvalWeights| {
+        let mut weights = *weights;
+        let fields: Vec<String> = EvalWeights::NAMES.iter().zip(weights.values_mut()).map(|(name, value)| format!("{name}: {value}")).collect();
+        format!("    {name}: EvalWeights {{ {} }},\n", fields.join(", "))
+    };
+    // the imports and comment of the table this build plays with
+    let (header, _) = include_str!("tuned.rs").split_once("pub const VERSION").unwrap();
+    format!(
+        "{header}pub const VERSION: u32 = {version};\n\npub const WEIGHTS: PhaseWeights = PhaseWeights {{\n{}{}{}}};\n",
+        phase("setting", &weights.setting),
+        phase("moving", &weights.moving),
+        phase("flying", &weights.flying),
+    )
+}
@@ -0,0 +1,14 @@
+use crate::eval::{EvalWeights, PhaseWeights};
+
+// The evaluation weights the engine plays with unless told otherwise, see
+// `eval::PhaseWeights`. `mica tune` writes this file, every table it writes
+// is one version past the one it started from. Version 1 is the hand-picked
+// set the weights were before there was a tuner.
+
+pub const VERSION: u32 = 1;
+
+pub const WEIGHTS: PhaseWeights = PhaseWeights {
+    setting: EvalWeights { mobility: 0, mills: 30, open_twos: 20, blocked_twos: 25, blocked: 0, windmills: 0 },
+    moving: EvalWeights { mobility: 10, mills: 25, open_twos: 35, blocked_twos: 20, blocked: 15, windmills: 200 },
+    flying: EvalWeights { mobility: 5, mills: 10, open_twos: 60, blocked_twos: 30, blocked: 10, windmills: 200 },
+};
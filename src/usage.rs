@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use serde_json::{json, Value};
+
+struct ClientUsage {
+    requests: u64,
+    nodes: u64,
+    total_latency_ms: u64,
+    last_seen: Instant,
+}
+
+// Search work done on behalf of each API key, for at most `capacity` keys,
+// the key seen least recently makes room for a new one.
+pub struct Usage {
+    clients: Mutex<HashMap<String, ClientUsage>>,
+    capacity: usize,
+}
+
+// FNV-1a of the key, what the report shows instead of the key itself
+fn key_id(api_key: &str) -> String {
+    let hash = api_key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{hash:016x}")
+}
+
+impl Usage {
+    pub fn new(capacity: usize) -> Self {
+        Usage {
+            clients: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(&self, api_key: &str, nodes: u64, latency_ms: u64) {
+        let mut clients = self.clients.lock().unwrap();
+        if !clients.contains_key(api_key) && clients.len() >= self.capacity {
+            let oldest = clients.iter().min_by_key(|(_, client)| client.last_seen).map(|(api_key, _)| api_key.clone());
+            if let Some(oldest) = oldest {
+                clients.remove(&oldest);
+            }
+        }
+        let client = clients.entry(api_key.to_string()).or_insert_with(|| ClientUsage {
+            requests: 0,
+            nodes: 0,
+            total_latency_ms: 0,
+            last_seen: Instant::now(),
+        });
+        client.requests += 1;
+        client.nodes += nodes;
+        client.total_latency_ms += latency_ms;
+        client.last_seen = Instant::now();
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // keyed by `key_id`, the keys themselves are credentials
+    pub fn report(&self) -> Value {
+        let clients = self.clients.lock().unwrap();
+        let report: serde_json::Map<String, Value> = clients
+            .iter()
+            .map(|(api_key, client)| {
+                let average_latency_ms = client.total_latency_ms as f64 / client.requests.max(1) as f64;
+                (key_id(api_key), json!({
+                    "requests": client.requests,
+                    "nodes": client.nodes,
+                    "average_latency_ms": average_latency_ms,
+                }))
+            })
+            .collect();
+        json!({ "clients": report })
+    }
+}
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+use crate::coords::POINTS;
+use crate::minimax::{MicaRequest, PlayerField};
+use crate::variants::STONES_PER_PLAYER;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    // take the request as is
+    Trust,
+    // reject requests whose counters disagree with the board
+    Strict,
+    // recompute the counters from the board
+    Lenient,
+}
+
+impl FromStr for ValidationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trust" => Ok(ValidationMode::Trust),
+            "strict" => Ok(ValidationMode::Strict),
+            "lenient" => Ok(ValidationMode::Lenient),
+            _ => Err(format!("unknown validation mode `{s}`")),
+        }
+    }
+}
+
+// Every problem found in the request, so the client can fix them all at once.
+pub fn validate_request(request: &MicaRequest) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    let player = request.player.value();
+    if player != 1 && player != -1 {
+        errors.push(format!("player must be 1 (white) or -1 (black), got {player}"));
+    }
+
+    let mut white_on_board = 0;
+    let mut black_on_board = 0;
+    for x in 0..3 {
+        for y in 0..3 {
+            for z in 0..3 {
+                let value = request.stones[x][y][z];
+                if !(-1..=1).contains(&value) {
+                    errors.push(format!("stones[{x}][{y}][{z}] must be -1, 0 or 1, got {value}"));
+                } else if y == 1 && z == 1 && value != 0 {
+                    errors.push(format!("stones[{x}][1][1] is the center of a ring and must be empty"));
+                }
+            }
+        }
+    }
+    for &(x, y, z) in POINTS.iter() {
+        match request.stones[x as usize][y as usize][z as usize] {
+            1 => white_on_board += 1,
+            -1 => black_on_board += 1,
+            _ => (),
+        }
+    }
+
+    for (color, count, on_board, to_set) in [
+        ("white", request.white_count, white_on_board, request.white_remaining),
+        ("black", request.black_count, black_on_board, request.black_remaining),
+    ] {
+        if count != on_board {
+            errors.push(format!("{color}_count is {count} but the board has {on_board} {color} stones"));
+        }
+        if to_set > STONES_PER_PLAYER {
+            errors.push(format!("{color}_remaining is {to_set} but a player only has {STONES_PER_PLAYER} stones"));
+        }
+        // widened, a request may claim up to 255 stones to set
+        if on_board as u16 + to_set as u16 > STONES_PER_PLAYER as u16 {
+            errors.push(format!(
+                "{color} has {on_board} stones on the board and {to_set} to set, more than {STONES_PER_PLAYER} in total"
+            ));
+        }
+    }
+
+    // white sets first and the players alternate, so the stones left to set
+    // only ever differ by one, in black's favour, while black is to move
+    let (white_to_set, black_to_set) = (request.white_remaining, request.black_remaining);
+    if white_to_set > 0 || black_to_set > 0 {
+        match player {
+            1 if white_to_set != black_to_set => errors.push(format!(
+                "with white to move both players must have set the same number of stones, got {white_to_set} and {black_to_set} left"
+            )),
+            -1 if black_to_set as u16 != white_to_set as u16 + 1 => errors.push(format!(
+                "with black to move black must have one stone more left to set than white, got {black_to_set} and {white_to_set}"
+            )),
+            _ => (),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+// Rewrites the request so it describes a position the engine can play: stones
+// that are out of range or on a ring center are dropped, as are a player's
+// stones past the ninth in the order of `POINTS`, the on-board counts are
+// taken from the board and the stones left to set are capped so nobody has
+// more than nine stones in total.
+pub fn normalize_request(request: &mut MicaRequest) {
+    for x in 0..3 {
+        for y in 0..3 {
+            for z in 0..3 {
+                let value = request.stones[x][y][z];
+                if !(-1..=1).contains(&value) || (y == 1 && z == 1) {
+                    request.stones[x][y][z] = 0;
+                }
+            }
+        }
+    }
+
+    let (mut white_count, mut black_count) = (0, 0);
+    for &(x, y, z) in POINTS.iter() {
+        let stone = &mut request.stones[x as usize][y as usize][z as usize];
+        let count = match *stone {
+            1 => &mut white_count,
+            -1 => &mut black_count,
+            _ => continue,
+        };
+        if *count == STONES_PER_PLAYER {
+            *stone = 0;
+        } else {
+            *count += 1;
+        }
+    }
+    request.white_count = white_count;
+    request.black_count = black_count;
+    request.white_remaining = request.white_remaining.min(STONES_PER_PLAYER.saturating_sub(white_count));
+    request.black_remaining = request.black_remaining.min(STONES_PER_PLAYER.saturating_sub(black_count));
+}
+
+// the sixteen lines of the board, as indices into `MicaRequest::stones`
+fn lines() -> impl Iterator<Item = [(usize, usize, usize); 3]> {
+    (0..3)
+        .flat_map(|x| [0, 2].into_iter().flat_map(move |edge| [[(x, edge, 0), (x, edge, 1), (x, edge, 2)], [(x, 0, edge), (x, 1, edge), (x, 2, edge)]]))
+        .chain([(0, 1), (1, 0), (1, 2), (2, 1)].into_iter().map(|(y, z)| [(0, y, z), (1, y, z), (2, y, z)]))
+}
+
+// Problems with how a game could have come to the position, which
+// `validate_request` does not look for: mills nobody captured with and more
+// captures than the stones set so far could have made.
+pub fn reachability_errors(request: &MicaRequest) -> Vec<String> {
+    let mut errors = Vec::new();
+    // no stone moves before every stone is set
+    let setting = request.white_remaining > 0 || request.black_remaining > 0;
+    for (color, opponent, player, to_set, opponent_count, opponent_to_set) in [
+        ("white", "black", 1, request.white_remaining, request.black_count, request.black_remaining),
+        ("black", "white", -1, request.black_remaining, request.white_count, request.white_remaining),
+    ] {
+        let opponent_lost = STONES_PER_PLAYER.saturating_sub(opponent_count.saturating_add(opponent_to_set));
+        let has_mill = lines().any(|line| line.iter().all(|&(x, y, z)| request.stones[x][y][z] == player));
+        if has_mill && opponent_lost == 0 {
+            errors.push(format!("{color} has a mill on the board but {opponent} has not lost a stone"));
+        }
+        // a player closes at most one mill per stone set from their third on
+        let set = STONES_PER_PLAYER.saturating_sub(to_set);
+        if setting && opponent_lost > set.saturating_sub(2) {
+            errors.push(format!("{opponent} has lost {opponent_lost} stones but {color} has only set {set}, too few to close that many mills"));
+        }
+    }
+    errors
+}
+
+// Rewrites the request into the legal position closest to it: what
+// `normalize_request` does, plus a player to move and stones left to set
+// that alternate the way the players set them. Captures no game could have
+// made are left alone, `reachability_errors` still reports them.
+pub fn correct_request(request: &mut MicaRequest) {
+    normalize_request(request);
+    let (white_to_set, black_to_set) = (request.white_remaining, request.black_remaining);
+    let player = match request.player.value() {
+        player @ (1 | -1) => player,
+        // whoever has set fewer stones is to move
+        _ if black_to_set > white_to_set => -1,
+        _ => 1,
+    };
+    if player != request.player.value() {
+        request.player = PlayerField::Number(player);
+    }
+
+    if white_to_set > 0 || black_to_set > 0 {
+        match player {
+            1 => {
+                request.white_remaining = white_to_set.min(black_to_set);
+                request.black_remaining = white_to_set.min(black_to_set);
+            },
+            _ if black_to_set > white_to_set + 1 => request.black_remaining = white_to_set + 1,
+            _ if black_to_set <= white_to_set => request.white_remaining = black_to_set.saturating_sub(1),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minimax::MicaState;
+
+    // white on every point of the board
+    fn full_board() -> MicaRequest {
+        let mut request = MicaRequest::from_state(&MicaState::new());
+        for &(x, y, z) in POINTS.iter() {
+            request.stones[x as usize][y as usize][z as usize] = 1;
+        }
+        request.white_count = 24;
+        request
+    }
+
+    #[test]
+    fn huge_counts_are_errors() {
+        let mut request = full_board();
+        request.white_remaining = 250;
+        let errors = validate_request(&request).unwrap_err();
+        assert!(errors.iter().any(|error| error.contains("24 stones on the board and 250 to set")), "{errors:?}");
+
+        let mut request = MicaRequest::from_state(&MicaState::new());
+        request.player = PlayerField::Number(-1);
+        request.white_remaining = 255;
+        assert!(validate_request(&request).is_err());
+    }
+
+    #[test]
+    fn stones_past_the_ninth_are_dropped() {
+        let mut request = full_board();
+        request.white_remaining = 250;
+        request.black_remaining = 0;
+        normalize_request(&mut request);
+        assert_eq!((request.white_count, request.white_remaining), (9, 0));
+        let kept: Vec<_> = POINTS.iter().filter(|&&(x, y, z)| request.stones[x as usize][y as usize][z as usize] == 1).collect();
+        assert_eq!(kept, POINTS[..9].iter().collect::<Vec<_>>());
+        assert_eq!(validate_request(&request), Ok(()));
+    }
+}
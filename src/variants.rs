@@ -0,0 +1,82 @@
+use alloc::vec::Vec;
+
+use crate::coords::POINTS;
+use crate::minimax::MicaState;
+
+pub const STONES_PER_PLAYER: u8 = 9;
+
+// Rule options a game may change within its variant, the variant's own are
+// in `Variant::rules`. A state plays by the options in `MicaState::rules`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rules {
+    // whether a player down to three stones may jump to any empty point
+    pub flying: bool,
+    // whether a mill may take a stone out of a mill when every stone of the
+    // opponent is in one, without it such a mill takes nothing
+    pub mill_fallback: bool,
+}
+
+// A rule set this build can play.
+#[derive(Debug, Clone, Copy)]
+pub struct Variant {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub points: usize,
+    pub stones_per_player: u8,
+    pub rules: Rules,
+    pub new_game: fn() -> MicaState,
+}
+
+// Where a game stands, as far as the rules that apply to it go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    // stones are still being set
+    Setting,
+    Moving,
+    // a side is down to three stones and may jump to any empty point
+    Flying,
+    // a side is down to three stones without flying, one more mill decides
+    Endgame,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Setting => "setting",
+            Phase::Moving => "moving",
+            Phase::Flying => "flying",
+            Phase::Endgame => "endgame",
+        }
+    }
+}
+
+impl Variant {
+    // by the rules of the state, which may differ from the variant's
+    pub fn phase(&self, state: &MicaState) -> Phase {
+        state.phase()
+    }
+}
+
+pub const NINE: Variant = Variant {
+    name: "nine",
+    description: "Nine men's morris on three rings without diagonals, flying is not allowed",
+    points: POINTS.len(),
+    stones_per_player: STONES_PER_PLAYER,
+    rules: Rules { flying: false, mill_fallback: true },
+    new_game: MicaState::new,
+};
+
+// Every variant compiled into this build. Twelve men's morris, Lasker morris
+// and the connect-four style variant need their own rules engines and are not
+// listed until one exists.
+pub const VARIANTS: &[Variant] = &[NINE];
+
+pub const DEFAULT: &Variant = &NINE;
+
+pub fn find(name: &str) -> Option<&'static Variant> {
+    VARIANTS.iter().find(|variant| variant.name == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    VARIANTS.iter().map(|variant| variant.name).collect()
+}
@@ -0,0 +1,145 @@
+use std::process;
+use std::sync::Arc;
+use mica::fixtures;
+use mica::minimax::*;
+use mica::notation::{format_position, PositionFormat};
+use mica::rng::Rng;
+use mica::tt::TranspositionTable;
+
+use crate::option;
+
+// Compares the pruned search against plain minimax on random positions.
+//
+//     mica verify-search [--positions 2000] [--depth 3] [--seed 0]
+//
+// Every position is searched with the full window, with a transposition
+// table and with narrow windows around the true value. The full-window
+// searches have to find the minimax value and a move that reaches it, the
+// narrow ones a value on the right side of the window. Prints the positions
+// that disagree and exits with status 1 when there are any.
+
+// longest random game played to reach a position, enough for the moving phase
+const MAX_PLIES: u64 = 70;
+// narrow windows tried on every position
+const WINDOWS: usize = 4;
+// mismatches printed before the rest are only counted
+const MAX_REPORTED: usize = 10;
+
+// A finished game `ply` plies below the root, its winner scores less the
+// further away it is, as in the search.
+fn game_over(state: &MicaState, ply: u8) -> i32 {
+    let value = state.game_over_value().unwrap();
+    (value - value.signum() * ply as i32) * state.current_player as i32
+}
+
+// negamax without pruning, tables or move ordering, the reference everything
+// is held to, `ply` plies below the root
+fn plain_negamax(state: &mut MicaState, depth: u8, ply: u8, nodes: &mut u64) -> i32 {
+    *nodes += 1;
+    let side = state.current_player as i32;
+    if state.is_end() {
+        return game_over(state, ply);
+    }
+    if depth == 0 {
+        return state.eval() * side;
+    }
+    let moves = state.get_moves();
+    if moves.is_empty() {
+        return game_over(state, ply);
+    }
+
+    let mut best = -i32::MAX;
+    for mica_move in moves {
+        state.apply_move(mica_move);
+        state.current_player.toggle();
+        best = best.max(-plain_negamax(state, depth - 1, ply + 1, nodes));
+        state.current_player.toggle();
+        state.undo_move(mica_move);
+    }
+    best
+}
+
+// value of `mica_move` for the side to move, searched without pruning
+fn plain_move_value(state: &MicaState, mica_move: MicaMove, depth: u8) -> i32 {
+    let mut next = state.clone();
+    next.apply_move(mica_move);
+    next.current_player.toggle();
+    -plain_negamax(&mut next, depth - 1, 1, &mut 0)
+}
+
+// what is wrong with a full-window result, if anything
+fn check_full_window(state: &MicaState, depth: u8, expected: i32, (value, best_move): (i32, Option<MicaMove>)) -> Option<String> {
+    if value != expected {
+        return Some(format!("value {value}, minimax gives {expected}"));
+    }
+    let best_move = best_move?;
+    let move_value = plain_move_value(state, best_move, depth);
+    if move_value != expected {
+        return Some(format!("best move {best_move:?} is worth {move_value}, minimax gives {expected}"));
+    }
+    None
+}
+
+// fail-soft bounds: at most `a` below the window, at least `b` above it, exact inside
+fn check_window(expected: i32, a: i32, b: i32, value: i32) -> Option<String> {
+    let fine = if expected <= a {
+        value <= a
+    } else if expected >= b {
+        value >= b
+    } else {
+        value == expected
+    };
+    (!fine).then(|| format!("window ({a}, {b}) gave {value}, minimax gives {expected}"))
+}
+
+pub fn run(args: &[String]) {
+    let positions: u64 = option(args, "--positions", 2000);
+    let depth: u8 = option(args, "--depth", 3).max(1);
+    let seed: u64 = option(args, "--seed", 0);
+
+    let tt = Arc::new(TranspositionTable::new(16));
+    let mut rng = Rng::new(seed);
+    let (mut plain_nodes, mut pruned_nodes) = (0, 0);
+    let mut mismatches = 0;
+
+    for position_seed in seed..seed + positions {
+        let state = fixtures::random(position_seed, (position_seed % MAX_PLIES) as usize);
+        let expected = plain_negamax(&mut state.clone(), depth, 0, &mut plain_nodes);
+
+        let mut problems = Vec::new();
+        let mut pruned = state.clone();
+        let result = pruned.negamax(depth, -i32::MAX, i32::MAX);
+        pruned_nodes += pruned.nodes;
+        problems.extend(check_full_window(&state, depth, expected, result));
+
+        // a result kept from another position may have been searched deeper
+        // than plain minimax looks, every position starts from an empty table
+        tt.clear();
+        let mut with_tt = state.clone();
+        with_tt.tt = Some(Arc::clone(&tt));
+        let result = with_tt.negamax(depth, -i32::MAX, i32::MAX);
+        problems.extend(check_full_window(&state, depth, expected, result).map(|problem| format!("with tt: {problem}")));
+
+        for _ in 0..WINDOWS {
+            let a = expected - 3 + rng.below(7) as i32;
+            let b = a + 1 + rng.below(4) as i32;
+            let (value, _) = state.clone().negamax(depth, a, b);
+            problems.extend(check_window(expected, a, b, value));
+        }
+
+        if !problems.is_empty() {
+            if mismatches < MAX_REPORTED {
+                println!("FAIL  {} (seed {position_seed}): {}", format_position(PositionFormat::Fen, &state), problems.join(", "));
+            }
+            mismatches += 1;
+        }
+    }
+
+    println!(
+        "{positions} positions at depth {depth}, {mismatches} mismatches, pruning searched {:.1}% of the nodes",
+        pruned_nodes as f64 * 100.0 / plain_nodes.max(1) as f64
+    );
+    if mismatches > 0 {
+        process::exit(1);
+    }
+}
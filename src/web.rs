@@ -0,0 +1,23 @@
+use crate::http::Response;
+
+// The board UI of the `web-ui` feature: the files in `web/`, built into the
+// binary so running it is all it takes to play. The page plays through the
+// game endpoints like any other client.
+const FILES: &[(&str, &str, &str)] = &[
+    ("index.html", "text/html; charset=utf-8", include_str!("../web/index.html")),
+    ("app.js", "text/javascript; charset=utf-8", include_str!("../web/app.js")),
+    ("style.css", "text/css; charset=utf-8", include_str!("../web/style.css")),
+];
+
+// the file at the request path, `/` for the page itself, None for paths that are no file
+pub fn file(segments: &[&str]) -> Option<Response> {
+    let name = match segments {
+        [] => "index.html",
+        [name] => name,
+        _ => return None,
+    };
+    FILES
+        .iter()
+        .find(|(file, _, _)| *file == name)
+        .map(|(_, content_type, body)| Response::file(content_type, body))
+}
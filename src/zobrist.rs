@@ -0,0 +1,86 @@
+use crate::minimax::MicaPlayer;
+use crate::symmetry::Symmetry;
+
+// Random keys for Zobrist hashing. A position's hash is the xor of the keys
+// of its stones, of both players' stones to set and of the side to move, so
+// a move only has to xor the keys of what it changed.
+
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn keys<const N: usize>(offset: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix64(offset + i as u64);
+        i += 1;
+    }
+    keys
+}
+
+// one key per (x, y, z) cell, the unused ring centres included, for each player
+const WHITE_STONES: [u64; 27] = keys(0);
+const BLACK_STONES: [u64; 27] = keys(100);
+// stones to set above 15 share the last key
+const WHITE_TO_SET: [u64; 16] = keys(200);
+const BLACK_TO_SET: [u64; 16] = keys(300);
+// for every cell, the keys of the cells each symmetry moves it to, in the order of `Symmetry::all`
+const fn images(keys: [u64; 27]) -> [[u64; 16]; 27] {
+    let symmetries = Symmetry::all();
+    let mut images = [[0; 16]; 27];
+    let mut cell = 0;
+    while cell < 27 {
+        let mut i = 0;
+        while i < 16 {
+            let (x, y, z) = symmetries[i].apply_point(((cell / 9) as u8, (cell / 3 % 3) as u8, (cell % 3) as u8));
+            images[cell][i] = keys[(x * 9 + y * 3 + z) as usize];
+            i += 1;
+        }
+        cell += 1;
+    }
+    images
+}
+
+const WHITE_IMAGES: [[u64; 16]; 27] = images(WHITE_STONES);
+const BLACK_IMAGES: [[u64; 16]; 27] = images(BLACK_STONES);
+pub const BLACK_TO_MOVE: u64 = 0x5bd1_e995_7f4a_7c15;
+
+pub fn stone(x: u8, y: u8, z: u8, player: MicaPlayer) -> u64 {
+    let cell = (x * 9 + y * 3 + z) as usize;
+    match player {
+        MicaPlayer::White => WHITE_STONES[cell],
+        MicaPlayer::Black => BLACK_STONES[cell],
+        MicaPlayer::None => 0,
+    }
+}
+
+// the key of the stone in each symmetric image of the board, see `MicaState::hashes`
+pub fn stone_images(x: u8, y: u8, z: u8, player: MicaPlayer) -> &'static [u64; 16] {
+    let cell = (x * 9 + y * 3 + z) as usize;
+    match player {
+        MicaPlayer::White => &WHITE_IMAGES[cell],
+        MicaPlayer::Black => &BLACK_IMAGES[cell],
+        MicaPlayer::None => &[0; 16],
+    }
+}
+
+pub fn to_set(player: MicaPlayer, count: u8) -> u64 {
+    let count = (count as usize).min(15);
+    match player {
+        MicaPlayer::White => WHITE_TO_SET[count],
+        MicaPlayer::Black => BLACK_TO_SET[count],
+        MicaPlayer::None => 0,
+    }
+}
+
+pub fn side(player: MicaPlayer) -> u64 {
+    if player == MicaPlayer::Black {
+        BLACK_TO_MOVE
+    } else {
+        0
+    }
+}
@@ -0,0 +1,175 @@
+// The board UI: plays a game session through `POST /game` and
+// `POST /game/{id}/move`, with moves in notation such as `d7`, `a1-a4` and
+// `d7xg1`. The server checks every move, the page only works out whether a
+// move closes a mill so it knows to ask for a stone to remove.
+
+// every ring of a position in FEN, in order, outermost first
+const RINGS = [
+  ["a7", "d7", "g7", "g4", "g1", "d1", "a1", "a4"],
+  ["b6", "d6", "f6", "f4", "f2", "d2", "b2", "b4"],
+  ["c5", "d5", "e5", "e4", "e3", "d3", "c3", "c4"],
+];
+
+const MILLS = [
+  ["a7", "d7", "g7"], ["b6", "d6", "f6"], ["c5", "d5", "e5"], ["a4", "b4", "c4"],
+  ["e4", "f4", "g4"], ["c3", "d3", "e3"], ["b2", "d2", "f2"], ["a1", "d1", "g1"],
+  ["a7", "a4", "a1"], ["b6", "b4", "b2"], ["c5", "c4", "c3"], ["d7", "d6", "d5"],
+  ["d3", "d2", "d1"], ["e5", "e4", "e3"], ["f6", "f4", "f2"], ["g7", "g4", "g1"],
+];
+
+const SVG = "http://www.w3.org/2000/svg";
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+
+let game = null;
+let human = "white";
+// the part of a move picked so far: the stone to move and where it goes
+let from = null;
+let to = null;
+
+function coordinates(point) {
+  return [point.charCodeAt(0) - "a".charCodeAt(0), 7 - Number(point[1])];
+}
+
+// the stones of a position in FEN by point and the stones each side has left to set
+function parse(fen) {
+  const [rings, side, whiteToSet, blackToSet] = fen.split(" ");
+  const stones = {};
+  rings.split("/").forEach((ring, i) => {
+    [...ring].forEach((stone, along) => {
+      stones[RINGS[i][along]] = stone === "W" ? "white" : stone === "B" ? "black" : null;
+    });
+  });
+  return { stones, side, toSet: { white: Number(whiteToSet), black: Number(blackToSet) } };
+}
+
+function closesMill(stones, point, player) {
+  return MILLS.some((mill) => mill.includes(point) && mill.every((p) => p === point || stones[p] === player));
+}
+
+function element(name, attributes) {
+  const node = document.createElementNS(SVG, name);
+  for (const [key, value] of Object.entries(attributes)) {
+    node.setAttribute(key, value);
+  }
+  return node;
+}
+
+function draw() {
+  board.replaceChildren();
+  for (const [a, b, c] of MILLS) {
+    const [x1, y1] = coordinates(a);
+    const [x2, y2] = coordinates(c);
+    board.append(element("line", { x1, y1, x2, y2 }));
+  }
+  const stones = game ? parse(game.position).stones : {};
+  for (const point of RINGS.flat()) {
+    const [cx, cy] = coordinates(point);
+    const stone = stones[point];
+    const circle = element("circle", { cx, cy, r: stone ? 0.35 : 0.15, class: stone || "point" });
+    if (point === from || point === to) {
+      circle.classList.add("selected");
+    }
+    circle.addEventListener("click", () => click(point));
+    board.append(circle);
+  }
+}
+
+function show() {
+  draw();
+  document.getElementById("moves").replaceChildren(
+    ...game.moves.map((move) => Object.assign(document.createElement("li"), { textContent: move })),
+  );
+  const clock = game.clock;
+  const time = (ms) => `${Math.floor(ms / 60000)}:${String(Math.floor(ms / 1000) % 60).padStart(2, "0")}`;
+  document.getElementById("clock").textContent = `white ${time(clock.white_ms)}, black ${time(clock.black_ms)}`;
+  if (game.outcome) {
+    status.textContent = `${game.outcome.winner} wins by ${game.outcome.reason}.`;
+  } else if (to) {
+    status.textContent = "Mill! Pick a stone to remove.";
+  } else if (game.to_move === human) {
+    status.textContent = `Your move, ${game.phase} phase.`;
+  } else {
+    status.textContent = "Thinking...";
+  }
+}
+
+async function call(path, body) {
+  const response = await fetch(path, { method: "POST", body: JSON.stringify(body) });
+  const answer = await response.json();
+  if (!response.ok) {
+    throw new Error(`${answer.error}: ${answer.details.join(", ")}`);
+  }
+  return answer;
+}
+
+async function play(move) {
+  from = null;
+  to = null;
+  status.textContent = "Thinking...";
+  try {
+    game = await call(`/game/${game.id}/move`, { move });
+  } catch (error) {
+    status.textContent = error.message;
+    draw();
+    return;
+  }
+  show();
+}
+
+function click(point) {
+  if (!game || game.outcome || game.to_move !== human) {
+    return;
+  }
+  const { stones, toSet } = parse(game.position);
+  const opponent = human === "white" ? "black" : "white";
+  const placement = () => (from ? `${from}-${to}` : to);
+
+  if (to) {
+    if (stones[point] === opponent) {
+      play(`${placement()}x${point}`);
+    }
+    return;
+  }
+  if (toSet[human] > 0) {
+    if (stones[point]) {
+      return;
+    }
+    to = point;
+  } else if (stones[point] === human) {
+    from = point === from ? null : point;
+    draw();
+    return;
+  } else if (from && !stones[point]) {
+    to = point;
+    stones[from] = null;
+  } else {
+    return;
+  }
+  if (closesMill(stones, to, human)) {
+    show();
+  } else {
+    play(placement());
+  }
+}
+
+document.getElementById("new-game").addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const form = new FormData(event.target);
+  human = form.get("side");
+  from = null;
+  to = null;
+  status.textContent = "Starting...";
+  try {
+    game = await call("/game", {
+      engine: human === "white" ? "black" : "white",
+      time_ms: Number(form.get("minutes")) * 60000,
+    });
+  } catch (error) {
+    status.textContent = error.message;
+    return;
+  }
+  show();
+});
+
+draw();